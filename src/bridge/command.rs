@@ -23,9 +23,31 @@ pub fn create_nvim_command() -> TokioCommand {
     #[cfg(windows)]
     set_windows_creation_flags(&mut cmd);
 
+    set_working_directory(&mut cmd);
+
     cmd
 }
 
+// Starts in the working directory of the last session, unless files or arguments were given, which
+// are relative to the directory Neovide was started in
+fn set_working_directory(cmd: &mut TokioCommand) {
+    let cmd_line_settings = SETTINGS.get::<CmdLineSettings>();
+    if cmd_line_settings.wsl || !cmd_line_settings.neovim_args.is_empty() {
+        return;
+    }
+
+    if let Some(working_directory) = SETTINGS.get::<PersistentSession>().working_directory {
+        if working_directory.is_dir() {
+            cmd.current_dir(working_directory);
+        } else {
+            warn!(
+                "Last working directory {:?} no longer exists",
+                working_directory
+            );
+        }
+    }
+}
+
 #[cfg(target_os = "windows")]
 fn set_windows_creation_flags(cmd: &mut TokioCommand) {
     cmd.creation_flags(0x0800_0000); // CREATE_NO_WINDOW
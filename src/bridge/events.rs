@@ -8,7 +8,7 @@ use log::debug;
 use rmpv::Value;
 use skia_safe::Color4f;
 
-use crate::editor::{Colors, CursorMode, CursorShape, Style, UnderlineStyle};
+use crate::editor::{Colors, CursorMode, CursorShape, Style, UnderlineStyle, WindowBlend};
 
 #[derive(Clone, Debug)]
 pub enum ParseError {
@@ -20,6 +20,7 @@ pub enum ParseError {
     F64(Value),
     Bool(Value),
     WindowAnchor(Value),
+    WindowHandle(Value),
     Format(String),
 }
 type Result<T> = std::result::Result<T, ParseError>;
@@ -37,6 +38,9 @@ impl fmt::Display for ParseError {
             ParseError::WindowAnchor(value) => {
                 write!(f, "invalid window anchor format {}", value)
             }
+            ParseError::WindowHandle(value) => {
+                write!(f, "invalid window handle format {}", value)
+            }
             ParseError::Format(debug_text) => {
                 write!(f, "invalid event format {}", debug_text)
             }
@@ -119,7 +123,7 @@ pub enum WindowAnchor {
     SouthEast,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum EditorMode {
     // The set of modes reported will change in new versions of Nvim, for
     // instance more sub-modes and temporary states might be represented as
@@ -194,6 +198,7 @@ pub enum RedrawEvent {
     },
     WindowPosition {
         grid: u64,
+        window: u64,
         start_row: u64,
         start_column: u64,
         width: u64,
@@ -201,6 +206,7 @@ pub enum RedrawEvent {
     },
     WindowFloatPosition {
         grid: u64,
+        window: u64,
         anchor: WindowAnchor,
         anchor_grid: u64,
         anchor_row: f64,
@@ -210,6 +216,7 @@ pub enum RedrawEvent {
     },
     WindowExternalPosition {
         grid: u64,
+        window: u64,
     },
     WindowHide {
         grid: u64,
@@ -360,6 +367,18 @@ fn parse_bool(bool_value: Value) -> Result<bool> {
     bool_value.try_into().map_err(ParseError::Bool)
 }
 
+fn parse_window_handle(window_value: Value) -> Result<u64> {
+    let handle = match &window_value {
+        // Windows are sent as msgpack extension values wrapping the handle
+        Value::Ext(_, data) => rmpv::decode::read_value(&mut data.as_slice()).ok(),
+        _ => Some(window_value.clone()),
+    };
+
+    handle
+        .and_then(|handle| handle.as_u64())
+        .ok_or(ParseError::WindowHandle(window_value))
+}
+
 fn parse_set_title(set_title_arguments: Vec<Value>) -> Result<RedrawEvent> {
     let [title] = extract_values(set_title_arguments)?;
 
@@ -398,6 +417,9 @@ fn parse_mode_info_set(mode_info_set_arguments: Vec<Value>) -> Result<RedrawEven
                 "attr_id" => {
                     mode_info.style_id = Some(parse_u64(value)?);
                 }
+                "attr_id_lm" => {
+                    mode_info.langmap_style_id = Some(parse_u64(value)?);
+                }
                 _ => {}
             }
         }
@@ -615,11 +637,11 @@ fn parse_grid_scroll(grid_scroll_arguments: Vec<Value>) -> Result<RedrawEvent> {
 }
 
 fn parse_win_pos(win_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
-    let [grid, _window, start_row, start_column, width, height] =
-        extract_values(win_pos_arguments)?;
+    let [grid, window, start_row, start_column, width, height] = extract_values(win_pos_arguments)?;
 
     Ok(RedrawEvent::WindowPosition {
         grid: parse_u64(grid)?,
+        window: parse_window_handle(window)?,
         start_row: parse_u64(start_row)?,
         start_column: parse_u64(start_column)?,
         width: parse_u64(width)?,
@@ -639,7 +661,7 @@ fn parse_window_anchor(value: Value) -> Result<WindowAnchor> {
 }
 
 fn parse_win_float_pos(win_float_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
-    let ([grid, _window, anchor, anchor_grid, anchor_row, anchor_column, focusable], [sort_order]) =
+    let ([grid, window, anchor, anchor_grid, anchor_row, anchor_column, focusable], [sort_order]) =
         extract_values_with_optional(win_float_pos_arguments)?;
 
     let sort_order = if let Some(sort_order) = sort_order {
@@ -650,6 +672,7 @@ fn parse_win_float_pos(win_float_pos_arguments: Vec<Value>) -> Result<RedrawEven
 
     Ok(RedrawEvent::WindowFloatPosition {
         grid: parse_u64(grid)?,
+        window: parse_window_handle(window)?,
         anchor: parse_window_anchor(anchor)?,
         anchor_grid: parse_u64(anchor_grid)?,
         anchor_row: parse_f64(anchor_row)?,
@@ -660,10 +683,11 @@ fn parse_win_float_pos(win_float_pos_arguments: Vec<Value>) -> Result<RedrawEven
 }
 
 fn parse_win_external_pos(win_external_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
-    let [grid, _window] = extract_values(win_external_pos_arguments)?;
+    let [grid, window] = extract_values(win_external_pos_arguments)?;
 
     Ok(RedrawEvent::WindowExternalPosition {
         grid: parse_u64(grid)?,
+        window: parse_window_handle(window)?,
     })
 }
 
@@ -831,6 +855,69 @@ fn parse_msg_history_show(msg_history_show_arguments: Vec<Value>) -> Result<Redr
     })
 }
 
+fn parse_number(number_value: Value) -> Result<f32> {
+    number_value
+        .as_f64()
+        .map(|number| number as f32)
+        .ok_or(ParseError::F64(number_value))
+}
+
+fn parse_window_blend(window_blend: Value) -> Result<(u64, WindowBlend)> {
+    let [window, opacity, blur] = extract_values(parse_array(window_blend)?)?;
+
+    let opacity = match opacity {
+        Value::Nil => None,
+        opacity => Some(parse_number(opacity)?),
+    };
+    // Blur can be disabled with false, or set to an amount
+    let blur = match blur {
+        Value::Nil | Value::Boolean(true) => None,
+        Value::Boolean(false) => Some(0.0),
+        blur => Some(parse_number(blur)?),
+    };
+
+    Ok((parse_window_handle(window)?, WindowBlend { opacity, blur }))
+}
+
+pub fn parse_window_blends(window_blends_arguments: Vec<Value>) -> Result<Vec<(u64, WindowBlend)>> {
+    let [window_blends] = extract_values(window_blends_arguments)?;
+
+    parse_array(window_blends)?
+        .into_iter()
+        .map(parse_window_blend)
+        .collect()
+}
+
+// Positions are sent as zero based [row, column] pairs and returned as (column, row) like the
+// cursor's grid position
+fn parse_secondary_cursor_position(position: Value) -> Result<(u64, u64)> {
+    let [row, column] = extract_values(parse_array(position)?)?;
+    Ok((parse_u64(column)?, parse_u64(row)?))
+}
+
+pub fn parse_secondary_cursors(
+    secondary_cursors_arguments: Vec<Value>,
+) -> Result<(u64, Vec<(u64, u64)>)> {
+    let [grid, positions] = extract_values(secondary_cursors_arguments)?;
+
+    let positions = parse_array(positions)?
+        .into_iter()
+        .map(parse_secondary_cursor_position)
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((parse_u64(grid)?, positions))
+}
+
+pub fn parse_langmap_active(langmap_arguments: Vec<Value>) -> Result<bool> {
+    let [active] = extract_values(langmap_arguments)?;
+    parse_bool(active)
+}
+
+pub fn parse_working_directory(working_directory_arguments: Vec<Value>) -> Result<String> {
+    let [working_directory] = extract_values(working_directory_arguments)?;
+    parse_string(working_directory)
+}
+
 pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
     let mut event_contents = parse_array(event_value)?.into_iter();
     let event_name = event_contents
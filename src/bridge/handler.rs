@@ -1,18 +1,27 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
-use log::trace;
+use log::{error, trace};
 use nvim_rs::{Handler, Neovim};
 use rmpv::Value;
 
 use crate::bridge::clipboard::{get_remote_clipboard, set_remote_clipboard};
-#[cfg(windows)]
 use crate::bridge::ui_commands::{ParallelCommand, UiCommand};
 use crate::{
-    bridge::{events::parse_redraw_event, TxWrapper},
+    bridge::{
+        events::{
+            parse_langmap_active, parse_redraw_event, parse_secondary_cursors, parse_window_blends,
+            parse_working_directory,
+        },
+        TxWrapper,
+    },
     editor::EditorCommand,
     error_handling::ResultPanicExplanation,
     event_aggregator::EVENT_AGGREGATOR,
+    performance_stats::PERFORMANCE_STATS,
     running_tracker::*,
     settings::SETTINGS,
+    window::WindowCommand,
 };
 
 #[derive(Clone)]
@@ -66,10 +75,13 @@ impl Handler for NeovimHandler {
         match event_name.as_ref() {
             "redraw" => {
                 for events in arguments {
+                    let parse_start = Instant::now();
                     let parsed_events = parse_redraw_event(events)
                         .unwrap_or_explained_panic("Could not parse event from neovim");
+                    PERFORMANCE_STATS.record_parse_time(parse_start.elapsed());
 
                     for parsed_event in parsed_events {
+                        PERFORMANCE_STATS.record_redraw_event();
                         EVENT_AGGREGATOR.send(EditorCommand::NeovimRedrawEvent(parsed_event));
                     }
                 }
@@ -91,9 +103,38 @@ impl Handler for NeovimHandler {
             "neovide.unregister_right_click" => {
                 EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::UnregisterRightClick));
             }
+            "neovide.new_window" => {
+                EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::NewWindow));
+            }
             "neovide.set_clipboard" => {
                 set_remote_clipboard(arguments).ok();
             }
+            "neovide.window_blend" => match parse_window_blends(arguments) {
+                Ok(window_blends) => {
+                    EVENT_AGGREGATOR.send(EditorCommand::UpdateWindowBlends(window_blends));
+                }
+                Err(error) => error!("Could not parse window blends: {}", error),
+            },
+            "neovide.secondary_cursors" => match parse_secondary_cursors(arguments) {
+                Ok((grid, positions)) => {
+                    EVENT_AGGREGATOR.send(EditorCommand::SetSecondaryCursors { grid, positions });
+                }
+                Err(error) => error!("Could not parse secondary cursors: {}", error),
+            },
+            "neovide.langmap" => match parse_langmap_active(arguments) {
+                Ok(active) => {
+                    EVENT_AGGREGATOR.send(EditorCommand::SetLangmapActive(active));
+                }
+                Err(error) => error!("Could not parse langmap state: {}", error),
+            },
+            "neovide.working_directory" => match parse_working_directory(arguments) {
+                Ok(working_directory) => {
+                    EVENT_AGGREGATOR.send(WindowCommand::WorkingDirectoryChanged(
+                        working_directory.into(),
+                    ));
+                }
+                Err(error) => error!("Could not parse working directory: {}", error),
+            },
             _ => {}
         }
     }
@@ -36,6 +36,146 @@ const REGISTER_CLIPBOARD_PROVIDER_LUA: &str = r"
         cache_enabled = 0
     }";
 
+const REGISTER_WINDOW_BLEND_LUA: &str = r"
+    local channel = ...
+    local last_blends = nil
+
+    local function window_blends()
+        local blends = {}
+        for _, window in ipairs(vim.api.nvim_list_wins()) do
+            local has_opacity, opacity = pcall(vim.api.nvim_win_get_var, window, 'neovide_opacity')
+            if not has_opacity then
+                local winblend = vim.api.nvim_win_get_option(window, 'winblend')
+                opacity = winblend > 0 and (100 - winblend) / 100 or nil
+            end
+            local has_blur, blur = pcall(vim.api.nvim_win_get_var, window, 'neovide_blur')
+            if not has_blur then
+                blur = nil
+            end
+            if opacity ~= nil or blur ~= nil then
+                -- vim.NIL keeps the unset values in place
+                if opacity == nil then
+                    opacity = vim.NIL
+                end
+                if blur == nil then
+                    blur = vim.NIL
+                end
+                table.insert(blends, { window, opacity, blur })
+            end
+        end
+        return blends
+    end
+
+    function _G.neovide_update_window_blends()
+        local blends = window_blends()
+        local serialized_blends = vim.inspect(blends)
+        if serialized_blends ~= last_blends then
+            last_blends = serialized_blends
+            vim.rpcnotify(channel, 'neovide.window_blend', blends)
+        end
+    end
+
+    -- Watches the window variables of every window, so that setting them applies immediately
+    local watched_windows = {}
+    function _G.neovide_watch_window_blends()
+        for _, window in ipairs(vim.api.nvim_list_wins()) do
+            if not watched_windows[window] then
+                watched_windows[window] = true
+                vim.fn.win_execute(window,
+                    [[call dictwatcheradd(w:, 'neovide_*', 'NeovideWindowBlendChanged')]])
+            end
+        end
+    end
+
+    function _G.neovide_unwatch_window_blends(window)
+        watched_windows[window] = nil
+        neovide_update_window_blends()
+    end
+
+    vim.cmd([[
+        function! NeovideWindowBlendChanged(dict, key, change) abort
+            if a:key ==# 'neovide_opacity' || a:key ==# 'neovide_blur'
+                lua neovide_update_window_blends()
+            endif
+        endfunction
+
+        augroup NeovideWindowBlend
+            autocmd!
+            autocmd WinNew,WinEnter,CursorHold * lua neovide_watch_window_blends()
+            autocmd WinNew,WinEnter,BufWinEnter,VimResized * lua neovide_update_window_blends()
+            autocmd WinClosed * lua neovide_unwatch_window_blends(tonumber(vim.fn.expand('<amatch>')))
+            autocmd OptionSet winblend lua neovide_update_window_blends()
+        augroup END
+    ]])
+
+    neovide_watch_window_blends()
+    neovide_update_window_blends()";
+
+const REGISTER_LANGMAP_LUA: &str = r"
+    local channel = ...
+    local last_active = nil
+
+    local function langmap_active()
+        local mode = vim.api.nvim_get_mode().mode
+        if mode:sub(1, 1) == 'c' then
+            local cmdtype = vim.fn.getcmdtype()
+            if cmdtype == '/' or cmdtype == '?' then
+                -- An imsearch of -1 follows iminsert
+                local imsearch = vim.bo.imsearch
+                if imsearch == -1 then
+                    imsearch = vim.bo.iminsert
+                end
+                return imsearch == 1
+            end
+            return false
+        end
+        local first = mode:sub(1, 1)
+        return (first == 'i' or first == 'R') and vim.bo.iminsert == 1
+    end
+
+    function _G.neovide_update_langmap()
+        local active = langmap_active()
+        if active ~= last_active then
+            last_active = active
+            vim.rpcnotify(channel, 'neovide.langmap', active)
+        end
+    end
+
+    -- CTRL-^ toggles iminsert and imsearch without firing OptionSet. The key is seen before it is
+    -- handled, so the state is checked once it has been.
+    local ctrl_hat = vim.api.nvim_replace_termcodes('<C-^>', true, true, true)
+    vim.on_key(function(key)
+        if key == ctrl_hat then
+            vim.schedule(neovide_update_langmap)
+        end
+    end, vim.api.nvim_create_namespace('NeovideLangmap'))
+
+    -- Plugins can still change the options through the API without any event, which only shows
+    -- once the cursor moves or a character is typed
+    vim.cmd([[
+        augroup NeovideLangmap
+            autocmd!
+            autocmd ModeChanged,BufEnter,CmdlineEnter,TextChangedI * lua neovide_update_langmap()
+            autocmd CursorMovedI,InsertCharPre * lua neovide_update_langmap()
+            autocmd OptionSet iminsert,imsearch lua neovide_update_langmap()
+        augroup END
+    ]])";
+
+const REGISTER_WORKING_DIRECTORY_LUA: &str = r"
+    local channel = ...
+
+    function _G.neovide_update_working_directory()
+        vim.rpcnotify(channel, 'neovide.working_directory', vim.fn.getcwd(-1, -1))
+    end
+
+    neovide_update_working_directory()
+    vim.cmd([[
+        augroup NeovideWorkingDirectory
+            autocmd!
+            autocmd DirChanged global lua neovide_update_working_directory()
+        augroup END
+    ]])";
+
 pub async fn setup_neovide_remote_clipboard(nvim: &Neovim<TxWrapper>, neovide_channel: u64) {
     // users can opt-out with
     // vim: `let g:neovide_no_custom_clipboard = v:true`
@@ -107,6 +247,16 @@ pub async fn setup_neovide_specific_state(nvim: &Neovim<TxWrapper>, is_remote: b
             neovide_channel
         );
 
+        // Create a command for opening another window on the same Neovim
+        nvim.command(&build_neovide_command(
+            neovide_channel,
+            0,
+            "NeovideNewWindow",
+            "new_window",
+        ))
+        .await
+        .ok();
+
         // Create a command for registering right click context hooking
         #[cfg(windows)]
         nvim.command(&build_neovide_command(
@@ -129,8 +279,29 @@ pub async fn setup_neovide_specific_state(nvim: &Neovim<TxWrapper>, is_remote: b
         .await
         .ok();
 
+        // Send the opacity and blur of each window whenever windows or winblend change
+        nvim.execute_lua(
+            REGISTER_WINDOW_BLEND_LUA,
+            vec![Value::from(neovide_channel)],
+        )
+        .await
+        .ok();
+
+        // Tell when language mappings are active, which selects the lCursor highlight
+        nvim.execute_lua(REGISTER_LANGMAP_LUA, vec![Value::from(neovide_channel)])
+            .await
+            .ok();
+
         if is_remote {
             setup_neovide_remote_clipboard(nvim, neovide_channel).await;
+        } else {
+            // Keep track of the global working directory, so the next session can start there
+            nvim.execute_lua(
+                REGISTER_WORKING_DIRECTORY_LUA,
+                vec![Value::from(neovide_channel)],
+            )
+            .await
+            .ok();
         }
     } else {
         warn!("Neovide could not find the correct channel id. Some functionality may be disabled.");
@@ -150,7 +321,6 @@ pub async fn setup_neovide_specific_state(nvim: &Neovim<TxWrapper>, is_remote: b
         .ok();
 }
 
-#[cfg(windows)]
 pub fn build_neovide_command(channel: u64, num_args: u64, command: &str, event: &str) -> String {
     let nargs: String = if num_args > 1 {
         "+".to_string()
@@ -1,10 +1,14 @@
-use std::sync::Arc;
+use std::{
+    env,
+    process::{Command, Stdio},
+    sync::Arc,
+    time::Instant,
+};
 
-#[cfg(windows)]
-use log::error;
-use log::trace;
+use log::{error, trace};
 
 use nvim_rs::{call_args, rpc::model::IntoVal, Neovim};
+use rmpv::Value;
 use tokio::sync::mpsc::unbounded_channel;
 
 #[cfg(windows)]
@@ -12,9 +16,128 @@ use crate::windows_utils::{
     register_rightclick_directory, register_rightclick_file, unregister_rightclick,
 };
 use crate::{
-    bridge::TxWrapper, event_aggregator::EVENT_AGGREGATOR, running_tracker::RUNNING_TRACKER,
+    bridge::TxWrapper, cmd_line::CmdLineSettings, event_aggregator::EVENT_AGGREGATOR,
+    frame::Frame, performance_stats::PERFORMANCE_STATS, running_tracker::RUNNING_TRACKER,
+    settings::SETTINGS,
 };
 
+// Runs a resize command in the context of the split whose top left corner sits at the given
+// position on the base grid. Multigrid doesn't give us a mapping from grid ids to window handles,
+// so the window position is the only thing both sides agree on.
+const RESIZE_SPLIT_LUA: &str = r"
+    local row, column, command = ...
+    for _, window in ipairs(vim.api.nvim_list_wins()) do
+        local position = vim.api.nvim_win_get_position(window)
+        if vim.api.nvim_win_get_config(window).relative == ''
+            and position[1] == row
+            and position[2] == column
+        then
+            vim.fn.win_execute(window, command)
+            return
+        end
+    end";
+
+// Opens the current buffer in a new external window, sized like the editor
+const NEW_WINDOW_LUA: &str = r"
+    local ok, message = pcall(vim.api.nvim_open_win, 0, true, {
+        external = true,
+        width = vim.o.columns,
+        height = math.max(vim.o.lines - vim.o.cmdheight - 1, 1),
+    })
+    if not ok then
+        vim.api.nvim_err_writeln('NeovideNewWindow: ' .. message)
+    end";
+
+// The file of the current buffer, unless it is a terminal, help page, or any other buffer that
+// isn't backed by a file on disk
+const CURRENT_FILE_VIM: &str = "&buftype ==# '' && bufname() !~# '://' ? expand('%:p') : ''";
+
+// The command-line arguments of this instance to pass on to a new one. Their environment variable
+// fallbacks are inherited anyway. Multigrid is left out, as a new instance is only started without
+// it.
+fn new_instance_args(settings: &CmdLineSettings) -> Vec<String> {
+    let mut args = vec!["--nofork".to_owned()];
+
+    if settings.log_to_file {
+        args.push("--log".to_owned());
+    }
+    if settings.no_tabs {
+        args.push("--notabs".to_owned());
+    }
+    if settings.wsl {
+        args.push("--wsl".to_owned());
+    }
+    if settings.no_idle {
+        args.push("--noidle".to_owned());
+    }
+    if !settings.srgb {
+        args.push("--nosrgb".to_owned());
+    }
+    if !settings.vsync {
+        args.push("--novsync".to_owned());
+    }
+
+    let frame = match settings.frame {
+        Frame::Full => None,
+        #[cfg(target_os = "macos")]
+        Frame::Transparent => Some("transparent"),
+        #[cfg(target_os = "macos")]
+        Frame::Buttonless => Some("buttonless"),
+        Frame::None => Some("none"),
+    };
+    let values = [
+        ("--frame", frame.map(str::to_owned)),
+        ("--remote-tcp", settings.remote_tcp.clone()),
+        ("--neovim-bin", settings.neovim_bin.clone()),
+        ("--profile", settings.profile.clone()),
+        ("--startup-background", settings.startup_background.clone()),
+        ("--wayland-app-id", Some(settings.wayland_app_id.clone())),
+        ("--x11-wm-class", Some(settings.x11_wm_class.clone())),
+        ("--x11-wm-class-instance", Some(settings.x11_wm_class_instance.clone())),
+    ];
+    for (flag, value) in values {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            args.push(flag.to_owned());
+            args.push(value);
+        }
+    }
+
+    args
+}
+
+// Without multigrid Neovim can't show a grid outside of the editor window, so the new window is a
+// separate Neovide with a Neovim of its own, opening the same file in the same directory. When
+// connected to a remote Neovim, the new instance attaches to that one instead.
+fn open_new_instance(file: Option<String>, working_directory: Option<String>) {
+    let current_exe = match env::current_exe() {
+        Ok(current_exe) => current_exe,
+        Err(error) => {
+            error!("Could not find the Neovide executable: {}", error);
+            return;
+        }
+    };
+
+    let settings = SETTINGS.get::<CmdLineSettings>();
+    let mut command = Command::new(current_exe);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .args(new_instance_args(&settings));
+    if let Some(working_directory) = working_directory {
+        command.current_dir(working_directory);
+    }
+    if settings.remote_tcp.is_none() {
+        if let Some(file) = file.filter(|file| !file.is_empty()) {
+            command.arg(file);
+        }
+    }
+
+    if let Err(error) = command.spawn() {
+        error!("Could not open a new Neovide window: {}", error);
+    }
+}
+
 // Serial commands are any commands which must complete before the next value is sent. This
 // includes keyboard and mouse input which would cause problems if sent out of order.
 //
@@ -113,6 +236,20 @@ pub enum ParallelCommand {
         width: u64,
         height: u64,
     },
+    ResizeSplit {
+        grid_position: (u64, u64),
+        vertical: bool,
+        size: u64,
+    },
+    ResizeGrid {
+        grid: u64,
+        width: u64,
+        height: u64,
+    },
+    CloseWindow {
+        window_handle: u64,
+    },
+    NewWindow,
     FileDrop(String),
     FocusLost,
     FocusGained,
@@ -137,6 +274,60 @@ impl ParallelCommand {
                 .ui_try_resize(width.max(10) as i64, height.max(3) as i64)
                 .await
                 .expect("Resize failed"),
+            ParallelCommand::ResizeSplit {
+                grid_position: (grid_left, grid_top),
+                vertical,
+                size,
+            } => {
+                let resize_command = if vertical {
+                    format!("vertical resize {}", size)
+                } else {
+                    format!("resize {}", size)
+                };
+                nvim.execute_lua(
+                    RESIZE_SPLIT_LUA,
+                    vec![
+                        Value::from(grid_top),
+                        Value::from(grid_left),
+                        Value::from(resize_command),
+                    ],
+                )
+                .await
+                .unwrap_or_else(|error| error!("Could not resize split: {}", error));
+            }
+            ParallelCommand::ResizeGrid {
+                grid,
+                width,
+                height,
+            } => {
+                nvim.ui_try_resize_grid(grid as i64, width.max(1) as i64, height.max(1) as i64)
+                    .await
+                    .ok();
+            }
+            ParallelCommand::CloseWindow { window_handle } => {
+                nvim.call("nvim_win_close", call_args![window_handle as i64, false])
+                    .await
+                    .ok();
+            }
+            ParallelCommand::NewWindow => {
+                if SETTINGS.get::<CmdLineSettings>().multi_grid {
+                    nvim.execute_lua(NEW_WINDOW_LUA, vec![])
+                        .await
+                        .unwrap_or_else(|error| error!("Could not open a new window: {}", error));
+                } else {
+                    let file = nvim
+                        .eval(CURRENT_FILE_VIM)
+                        .await
+                        .ok()
+                        .and_then(|file| file.as_str().map(str::to_owned));
+                    let working_directory = nvim
+                        .call_function("getcwd", vec![])
+                        .await
+                        .ok()
+                        .and_then(|directory| directory.as_str().map(str::to_owned));
+                    open_new_instance(file, working_directory);
+                }
+            }
             ParallelCommand::FocusLost => nvim
                 .command("if exists('#FocusLost') | doautocmd <nomodeline> FocusLost | endif")
                 .await
@@ -268,7 +459,10 @@ pub fn start_ui_command_handler(nvim: Arc<Neovim<TxWrapper>>) {
         while RUNNING_TRACKER.is_running() {
             match serial_rx.recv().await {
                 Some(serial_command) => {
+                    // Serial commands wait for Neovim to answer, so they measure the round trip
+                    let start = Instant::now();
                     serial_command.execute(&nvim).await;
+                    PERFORMANCE_STATS.record_rpc_latency(start.elapsed());
                 }
                 None => {
                     RUNNING_TRACKER.quit("serial ui command channel failed");
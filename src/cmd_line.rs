@@ -18,8 +18,11 @@ pub struct CmdLineSettings {
     pub multi_grid: bool,
     pub no_idle: bool,
     pub srgb: bool,
+    pub vsync: bool,
     // Command-line arguments with environment variable fallback
     pub neovim_bin: Option<String>,
+    pub profile: Option<String>,
+    pub startup_background: Option<String>,
     pub wayland_app_id: String,
     pub x11_wm_class: String,
     pub x11_wm_class_instance: String,
@@ -44,8 +47,11 @@ impl Default for CmdLineSettings {
             multi_grid: false,
             no_idle: false,
             srgb: true,
+            vsync: true,
             // Command-line arguments with environment variable fallback
             neovim_bin: None,
+            profile: None,
+            startup_background: None,
             wayland_app_id: String::new(),
             x11_wm_class_instance: String::new(),
             x11_wm_class: String::new(),
@@ -134,6 +140,11 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
                 .long("nosrgb")
                 .help("Do not use standard color space to initialize the window. Swapping this variable sometimes fixes issues on startup"),
         )
+        .arg(
+            Arg::new("novsync")
+                .long("novsync")
+                .help("Do not wait for the monitor's vertical blank, draw at the refresh rate setting instead"),
+        )
         // Command-line arguments with environment variable fallback
         .arg(
             Arg::new("neovim_bin")
@@ -141,6 +152,18 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
                 .takes_value(true)
                 .help("Specify path to neovim"),
         )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .takes_value(true)
+                .help("Keep the window and session state in a settings file of its own"),
+        )
+        .arg(
+            Arg::new("startup_background")
+                .long("startup-background")
+                .takes_value(true)
+                .help("Background color to show until Neovim reports its colors"),
+        )
         .arg(
             Arg::new("wayland_app_id")
                 .long("wayland-app-id")
@@ -189,6 +212,13 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
         neovim_args.extend::<Vec<String>>(files_to_open);
     }
 
+    let profile = parse_profile(
+        matches
+            .value_of("profile")
+            .map(|v| v.to_owned())
+            .or_else(|| std::env::var("NEOVIDE_PROFILE").ok()),
+    )?;
+
     /*
      * Integrate Environment Variables as Defaults to the command-line ones.
      *
@@ -198,7 +228,10 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
         // Pass through arguments
         neovim_args,
         // Command-line arguments only
-        geometry: parse_window_geometry(matches.value_of("geometry").map(|i| i.to_owned()))?,
+        geometry: parse_window_geometry(
+            matches.value_of("geometry").map(|i| i.to_owned()),
+            profile.as_deref(),
+        )?,
         log_to_file: matches.is_present("log_to_file"),
         no_fork: matches.is_present("nofork"),
         remote_tcp: matches.value_of("remote_tcp").map(|i| i.to_owned()),
@@ -216,11 +249,18 @@ pub fn handle_command_line_arguments(args: Vec<String>) -> Result<(), String> {
         no_idle: matches.is_present("noidle") || std::env::var("NEOVIDE_NO_IDLE").is_ok(),
         // Srgb is enabled by default, so set it to false if nosrgb or NOEVIDE_NO_SRGB is set
         srgb: !(matches.is_present("nosrgb") || std::env::var("NEOVIDE_NO_SRGB").is_ok()),
+        // Vsync is enabled by default, so set it to false if novsync or NEOVIDE_NO_VSYNC is set
+        vsync: !(matches.is_present("novsync") || std::env::var("NEOVIDE_NO_VSYNC").is_ok()),
         // Command-line arguments with environment variable fallback
         neovim_bin: matches
             .value_of("neovim_bin")
             .map(|v| v.to_owned())
             .or_else(|| std::env::var("NEOVIM_BIN").ok()),
+        profile,
+        startup_background: matches
+            .value_of("startup_background")
+            .map(|v| v.to_owned())
+            .or_else(|| std::env::var("NEOVIDE_STARTUP_BACKGROUND").ok()),
         wayland_app_id: matches
             .value_of("wayland_app_id")
             .map(|v| v.to_owned())
@@ -389,6 +429,33 @@ mod tests {
         assert_eq!(SETTINGS.get::<CmdLineSettings>().frame, Frame::None);
     }
 
+    #[test]
+    fn test_novsync_flag() {
+        let args: Vec<String> = vec!["neovide", "--novsync"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        handle_command_line_arguments(args).expect("Could not parse arguments");
+        assert!(!SETTINGS.get::<CmdLineSettings>().vsync);
+    }
+
+    #[test]
+    fn test_startup_background_arg() {
+        let args: Vec<String> = vec!["neovide", "--startup-background", "#1e1e2e"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        handle_command_line_arguments(args).expect("Could not parse arguments");
+        assert_eq!(
+            SETTINGS.get::<CmdLineSettings>().startup_background,
+            Some("#1e1e2e".to_owned())
+        );
+    }
+
     #[test]
     fn test_neovim_bin_arg() {
         let args: Vec<String> = vec!["neovide", "--neovim-bin", "foo"]
@@ -416,4 +483,30 @@ mod tests {
             Some("foo".to_owned())
         );
     }
+
+    #[test]
+    fn test_profile_arg() {
+        let args: Vec<String> = vec!["neovide", "--profile", "work"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        handle_command_line_arguments(args).expect("Could not parse arguments");
+        assert_eq!(
+            SETTINGS.get::<CmdLineSettings>().profile,
+            Some("work".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_invalid_profile_arg() {
+        let args: Vec<String> = vec!["neovide", "--profile", "../work"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _accessing_settings = ACCESSING_SETTINGS.lock().unwrap();
+        assert!(handle_command_line_arguments(args).is_err());
+    }
 }
@@ -28,6 +28,8 @@ impl CursorShape {
 pub struct CursorMode {
     pub shape: Option<CursorShape>,
     pub style_id: Option<u64>,
+    /// Highlight used instead of `style_id` while language mappings are active
+    pub langmap_style_id: Option<u64>,
     pub cell_percentage: Option<f32>,
     pub blinkwait: Option<u64>,
     pub blinkon: Option<u64>,
@@ -44,8 +46,12 @@ pub struct Cursor {
     pub blinkon: Option<u64>,
     pub blinkoff: Option<u64>,
     pub style: Option<Arc<Style>>,
+    pub langmap_style: Option<Arc<Style>>,
+    /// Whether `:lmap` mappings apply to what is typed, which selects `langmap_style`
+    pub langmap_active: bool,
     pub enabled: bool,
-    pub double_width: bool,
+    /// Number of cells the character under the cursor spans
+    pub cell_width: u64,
     pub grid_cell: GridCell,
 }
 
@@ -56,34 +62,70 @@ impl Cursor {
             parent_window_id: 0,
             shape: CursorShape::Block,
             style: None,
+            langmap_style: None,
+            langmap_active: false,
             cell_percentage: None,
             blinkwait: None,
             blinkon: None,
             blinkoff: None,
             enabled: true,
-            double_width: false,
+            cell_width: 1,
             grid_cell: (" ".to_string(), None),
         }
     }
 
+    /// The highlight of the current mode, taking language mappings into account.
+    pub fn active_style(&self) -> Option<&Arc<Style>> {
+        if self.langmap_active {
+            self.langmap_style.as_ref().or(self.style.as_ref())
+        } else {
+            self.style.as_ref()
+        }
+    }
+
+    // Without colors of its own the cursor shows the character under it in reverse video, like
+    // Neovim does when the cursor highlight is missing or empty
+    fn cell_foreground(&self, default_colors: &Colors) -> Color4f {
+        match &self.grid_cell.1 {
+            Some(style) => style.foreground(default_colors),
+            None => default_colors.foreground.unwrap(),
+        }
+    }
+
+    fn cell_background(&self, default_colors: &Colors) -> Color4f {
+        match &self.grid_cell.1 {
+            Some(style) => style.background(default_colors),
+            None => default_colors.background.unwrap(),
+        }
+    }
+
     pub fn foreground(&self, default_colors: &Colors) -> Color4f {
-        self.style
-            .as_ref()
-            .and_then(|s| s.colors.foreground)
-            .unwrap_or_else(|| default_colors.background.unwrap())
+        self.active_style()
+            .and_then(|s| {
+                if s.reverse {
+                    s.colors.background
+                } else {
+                    s.colors.foreground
+                }
+            })
+            .unwrap_or_else(|| self.cell_background(default_colors))
     }
 
     pub fn background(&self, default_colors: &Colors) -> Color4f {
-        self.style
-            .as_ref()
-            .and_then(|s| s.colors.background)
-            .unwrap_or_else(|| default_colors.foreground.unwrap())
+        self.active_style()
+            .and_then(|s| {
+                if s.reverse {
+                    s.colors.foreground
+                } else {
+                    s.colors.background
+                }
+            })
+            .unwrap_or_else(|| self.cell_foreground(default_colors))
     }
 
     pub fn alpha(&self) -> u8 {
         return self
-            .style
-            .as_ref()
+            .active_style()
             .map(|s| (255_f32 * ((100 - s.blend) as f32 / 100.0_f32)) as u8)
             .unwrap_or(255);
     }
@@ -92,6 +134,7 @@ impl Cursor {
         let CursorMode {
             shape,
             style_id,
+            langmap_style_id,
             cell_percentage,
             blinkwait,
             blinkon,
@@ -106,6 +149,8 @@ impl Cursor {
             self.style = styles.get(style_id).cloned();
         }
 
+        self.langmap_style = langmap_style_id.and_then(|id| styles.get(&id).cloned());
+
         self.cell_percentage = *cell_percentage;
         self.blinkwait = *blinkwait;
         self.blinkon = *blinkon;
@@ -200,6 +245,7 @@ mod tests {
         let cursor_mode = CursorMode {
             shape: Some(CursorShape::Horizontal),
             style_id: Some(1),
+            langmap_style_id: None,
             cell_percentage: Some(100.0),
             blinkwait: Some(1),
             blinkon: Some(1),
@@ -221,6 +267,7 @@ mod tests {
         let cursor_mode_with_none = CursorMode {
             shape: None,
             style_id: None,
+            langmap_style_id: None,
             cell_percentage: None,
             blinkwait: None,
             blinkon: None,
@@ -234,4 +281,138 @@ mod tests {
         assert_eq!(cursor.blinkon, None);
         assert_eq!(cursor.blinkoff, None);
     }
+
+    #[test]
+    fn test_langmap_style() {
+        let mut styles = HashMap::new();
+        styles.insert(1, Arc::new(Style::new(COLORS)));
+        styles.insert(2, Arc::new(Style::new(DEFAULT_COLORS)));
+
+        let mut cursor = Cursor::new();
+        cursor.change_mode(
+            &CursorMode {
+                style_id: Some(1),
+                langmap_style_id: Some(2),
+                ..CursorMode::default()
+            },
+            &styles,
+        );
+        assert_eq!(cursor.active_style(), styles.get(&1));
+
+        cursor.langmap_active = true;
+        assert_eq!(cursor.active_style(), styles.get(&2));
+        assert_eq!(
+            cursor.background(&NONE_COLORS),
+            DEFAULT_COLORS.background.unwrap()
+        );
+
+        // Modes without an lCursor highlight keep using the regular one
+        cursor.change_mode(
+            &CursorMode {
+                style_id: Some(1),
+                ..CursorMode::default()
+            },
+            &styles,
+        );
+        assert_eq!(cursor.active_style(), styles.get(&1));
+
+        // As do undefined highlights
+        cursor.change_mode(
+            &CursorMode {
+                style_id: Some(1),
+                langmap_style_id: Some(0),
+                ..CursorMode::default()
+            },
+            &styles,
+        );
+        assert_eq!(cursor.active_style(), styles.get(&1));
+    }
+
+    #[test]
+    fn test_langmap_blend() {
+        let mut blended = Style::new(COLORS);
+        blended.blend = 50;
+        let mut styles = HashMap::new();
+        styles.insert(1, Arc::new(Style::new(COLORS)));
+        styles.insert(2, Arc::new(blended));
+
+        let mut cursor = Cursor::new();
+        cursor.change_mode(
+            &CursorMode {
+                style_id: Some(1),
+                langmap_style_id: Some(2),
+                ..CursorMode::default()
+            },
+            &styles,
+        );
+        assert_eq!(cursor.alpha(), 255);
+        cursor.langmap_active = true;
+        assert_eq!(cursor.alpha(), 127);
+    }
+
+    #[test]
+    fn test_reverse_video_fallback() {
+        let mut styles = HashMap::new();
+        styles.insert(1, Arc::new(Style::new(NONE_COLORS)));
+
+        let mut cursor = Cursor::new();
+        cursor.grid_cell = ("a".to_string(), Some(Arc::new(Style::new(COLORS))));
+
+        // Neither a missing highlight nor one without colors hide the character
+        for style_id in [0, 1] {
+            cursor.change_mode(
+                &CursorMode {
+                    style_id: Some(style_id),
+                    ..CursorMode::default()
+                },
+                &styles,
+            );
+            assert_eq!(
+                cursor.foreground(&DEFAULT_COLORS),
+                COLORS.background.unwrap()
+            );
+            assert_eq!(
+                cursor.background(&DEFAULT_COLORS),
+                COLORS.foreground.unwrap()
+            );
+        }
+
+        // Reversed cells are reversed back
+        let mut reversed = Style::new(COLORS);
+        reversed.reverse = true;
+        cursor.grid_cell.1 = Some(Arc::new(reversed));
+        assert_eq!(
+            cursor.foreground(&DEFAULT_COLORS),
+            COLORS.foreground.unwrap()
+        );
+        assert_eq!(
+            cursor.background(&DEFAULT_COLORS),
+            COLORS.background.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reverse_cursor_highlight() {
+        let mut reversed = Style::new(COLORS);
+        reversed.reverse = true;
+        let mut styles = HashMap::new();
+        styles.insert(1, Arc::new(reversed));
+
+        let mut cursor = Cursor::new();
+        cursor.change_mode(
+            &CursorMode {
+                style_id: Some(1),
+                ..CursorMode::default()
+            },
+            &styles,
+        );
+        assert_eq!(
+            cursor.foreground(&DEFAULT_COLORS),
+            COLORS.background.unwrap()
+        );
+        assert_eq!(
+            cursor.background(&DEFAULT_COLORS),
+            COLORS.foreground.unwrap()
+        );
+    }
 }
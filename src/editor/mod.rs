@@ -24,6 +24,9 @@ pub use window::*;
 
 const MODE_CMDLINE: u64 = 4;
 
+/// Sort order of the message grid, which is drawn above every other window.
+pub const MESSAGE_GRID_SORT_ORDER: u64 = std::u64::MAX;
+
 #[derive(Clone, Debug)]
 pub struct AnchorInfo {
     pub anchor_grid_id: u64,
@@ -54,10 +57,18 @@ impl WindowAnchor {
 pub enum EditorCommand {
     NeovimRedrawEvent(RedrawEvent),
     RedrawScreen,
+    UpdateWindowBlends(Vec<(u64, WindowBlend)>),
+    SetSecondaryCursors {
+        grid: u64,
+        positions: Vec<(u64, u64)>,
+    },
+    SetLangmapActive(bool),
 }
 
 pub struct Editor {
     pub windows: HashMap<u64, Window>,
+    // Keyed by Neovim window handle rather than grid id
+    pub window_blends: HashMap<u64, WindowBlend>,
     pub cursor: Cursor,
     pub defined_styles: HashMap<u64, Arc<Style>>,
     pub mode_list: Vec<CursorMode>,
@@ -69,6 +80,7 @@ impl Editor {
     pub fn new() -> Editor {
         Editor {
             windows: HashMap::new(),
+            window_blends: HashMap::new(),
             cursor: Cursor::new(),
             defined_styles: HashMap::new(),
             mode_list: Vec::new(),
@@ -181,27 +193,39 @@ impl Editor {
                 }
                 RedrawEvent::WindowPosition {
                     grid,
+                    window,
                     start_row,
                     start_column,
                     width,
                     height,
-                } => self.set_window_position(grid, start_column, start_row, width, height),
+                } => {
+                    self.set_window_position(grid, start_column, start_row, width, height);
+                    self.set_window_handle(grid, window);
+                }
                 RedrawEvent::WindowFloatPosition {
                     grid,
+                    window,
                     anchor,
                     anchor_grid,
                     anchor_column: anchor_left,
                     anchor_row: anchor_top,
                     sort_order,
                     ..
-                } => self.set_window_float_position(
-                    grid,
-                    anchor_grid,
-                    anchor,
-                    anchor_left,
-                    anchor_top,
-                    sort_order,
-                ),
+                } => {
+                    self.set_window_float_position(
+                        grid,
+                        anchor_grid,
+                        anchor,
+                        anchor_left,
+                        anchor_top,
+                        sort_order,
+                    );
+                    self.set_window_handle(grid, window);
+                }
+                RedrawEvent::WindowExternalPosition { grid, window } => {
+                    self.set_window_handle(grid, window);
+                    self.set_window_external(grid, window);
+                }
                 RedrawEvent::WindowHide { grid } => {
                     let window = self.windows.get(&grid);
                     if let Some(window) = window {
@@ -221,6 +245,25 @@ impl Editor {
                 _ => {}
             },
             EditorCommand::RedrawScreen => self.redraw_screen(),
+            EditorCommand::UpdateWindowBlends(window_blends) => {
+                self.update_window_blends(window_blends)
+            }
+            EditorCommand::SetLangmapActive(active) => {
+                // Usually arrives after Neovim's flush, so the cursor is sent again right away
+                if self.cursor.langmap_active != active {
+                    self.cursor.langmap_active = active;
+                    self.send_cursor_info();
+                    self.draw_command_batcher.send_batch();
+                    REDRAW_SCHEDULER.queue_next_frame();
+                }
+            }
+            EditorCommand::SetSecondaryCursors { grid, positions } => {
+                self.draw_command_batcher
+                    .queue(DrawCommand::SecondaryCursors { grid, positions })
+                    .ok();
+                self.draw_command_batcher.send_batch();
+                REDRAW_SCHEDULER.queue_next_frame();
+            }
         };
     }
 
@@ -311,6 +354,47 @@ impl Editor {
         }
     }
 
+    fn set_window_external(&mut self, grid: u64, window_handle: u64) {
+        if let Some(window) = self.windows.get_mut(&grid) {
+            window.make_external(window_handle);
+            window.show();
+        } else {
+            error!("Attempted to make window external that does not exist.");
+        }
+    }
+
+    fn set_window_handle(&mut self, grid: u64, window_handle: u64) {
+        let blend = self
+            .window_blends
+            .get(&window_handle)
+            .copied()
+            .unwrap_or_default();
+
+        if let Some(window) = self.windows.get_mut(&grid) {
+            window.window_handle = Some(window_handle);
+            window.set_blend(blend);
+        }
+    }
+
+    fn update_window_blends(&mut self, window_blends: Vec<(u64, WindowBlend)>) {
+        self.window_blends = window_blends.into_iter().collect();
+
+        for window in self.windows.values_mut() {
+            if let Some(window_handle) = window.window_handle {
+                let blend = self
+                    .window_blends
+                    .get(&window_handle)
+                    .copied()
+                    .unwrap_or_default();
+                window.set_blend(blend);
+            }
+        }
+
+        // Blends are reported outside of Neovim's flushes, so they are applied right away
+        self.draw_command_batcher.send_batch();
+        REDRAW_SCHEDULER.queue_next_frame();
+    }
+
     fn set_message_position(&mut self, grid: u64, grid_top: u64) {
         let parent_width = self
             .windows
@@ -323,7 +407,7 @@ impl Editor {
             anchor_type: WindowAnchor::NorthWest,
             anchor_left: 0.0,
             anchor_top: grid_top as f64,
-            sort_order: std::u64::MAX,
+            sort_order: MESSAGE_GRID_SORT_ORDER,
         };
 
         if let Some(window) = self.windows.get_mut(&grid) {
@@ -410,11 +494,11 @@ impl Editor {
     fn send_cursor_info(&mut self) {
         let (grid_left, grid_top) = self.cursor.grid_position;
         if let Some(window) = self.windows.get(&self.cursor.parent_window_id) {
-            let (character, style, double_width) = window.get_cursor_grid_cell(grid_left, grid_top);
+            let (character, style, cell_width) = window.get_cursor_grid_cell(grid_left, grid_top);
             self.cursor.grid_cell = (character, style);
-            self.cursor.double_width = double_width;
+            self.cursor.cell_width = cell_width;
         } else {
-            self.cursor.double_width = false;
+            self.cursor.cell_width = 1;
             self.cursor.grid_cell = (" ".to_string(), None);
         }
         self.draw_command_batcher
@@ -14,13 +14,24 @@ pub enum WindowType {
     Message,
 }
 
+/// Opacity and background blur requested for a single Neovim window. Unset values fall back to
+/// the global settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowBlend {
+    pub opacity: Option<f32>,
+    pub blur: Option<f32>,
+}
+
 pub struct Window {
     grid_id: u64,
+    pub window_handle: Option<u64>,
     grid: CharacterGrid,
     pub window_type: WindowType,
 
     pub anchor_info: Option<AnchorInfo>,
     grid_position: (f64, f64),
+    blend: WindowBlend,
+    external: bool,
 
     draw_command_batcher: Arc<DrawCommandBatcher>,
 }
@@ -36,10 +47,13 @@ impl Window {
     ) -> Window {
         let window = Window {
             grid_id,
+            window_handle: None,
             grid: CharacterGrid::new(grid_size),
             window_type,
             anchor_info,
             grid_position,
+            blend: WindowBlend::default(),
+            external: false,
             draw_command_batcher,
         };
         window.send_updated_position();
@@ -67,18 +81,22 @@ impl Window {
         &self,
         window_left: u64,
         window_top: u64,
-    ) -> (String, Option<Arc<Style>>, bool) {
+    ) -> (String, Option<Arc<Style>>, u64) {
         let grid_cell = match self.grid.get_cell(window_left, window_top) {
             Some((character, style)) => (character.clone(), style.clone()),
             _ => (' '.to_string(), None),
         };
 
-        let double_width = match self.grid.get_cell(window_left + 1, window_top) {
-            Some((character, _)) => character.is_empty(),
-            _ => false,
-        };
+        // Wide characters are followed by empty cells for the columns they extend into
+        let mut cell_width = 1;
+        while let Some((character, _)) = self.grid.get_cell(window_left + cell_width, window_top) {
+            if !character.is_empty() {
+                break;
+            }
+            cell_width += 1;
+        }
 
-        (grid_cell.0, grid_cell.1, double_width)
+        (grid_cell.0, grid_cell.1, cell_width)
     }
 
     pub fn get_width(&self) -> u64 {
@@ -99,6 +117,12 @@ impl Window {
         grid_size: (u64, u64),
         grid_position: (f64, f64),
     ) {
+        if self.external {
+            // Moving back into the editor closes the top-level window it had
+            self.external = false;
+            self.send_command(WindowDrawCommand::Close);
+        }
+
         self.grid.resize(grid_size);
         self.anchor_info = anchor_info;
         self.grid_position = grid_position;
@@ -106,6 +130,21 @@ impl Window {
         self.redraw();
     }
 
+    /// Moves the window out of the editor into a top-level window of its own, where it is drawn
+    /// from the top left corner.
+    pub fn make_external(&mut self, window_handle: u64) {
+        if self.external {
+            return;
+        }
+
+        self.external = true;
+        self.anchor_info = None;
+        self.grid_position = (0.0, 0.0);
+        self.send_command(WindowDrawCommand::External { window_handle });
+        self.send_updated_position();
+        self.redraw();
+    }
+
     pub fn resize(&mut self, new_size: (u64, u64)) {
         self.grid.resize(new_size);
         self.send_updated_position();
@@ -314,6 +353,15 @@ impl Window {
         }
     }
 
+    pub fn set_blend(&mut self, blend: WindowBlend) {
+        if self.blend != blend {
+            self.blend = blend;
+            self.send_command(WindowDrawCommand::Blend(blend));
+            // Cell backgrounds are blended differently when the window has its own opacity
+            self.redraw();
+        }
+    }
+
     pub fn hide(&self) {
         self.send_command(WindowDrawCommand::Hide);
     }
@@ -381,4 +429,34 @@ mod tests {
             .expect("Could not receive commands");
         assert!(!sent_commands.is_empty());
     }
+
+    #[test]
+    fn cursor_grid_cell_spans_wide_characters() {
+        let mut window = Window::new(
+            1,
+            WindowType::Editor,
+            None,
+            (0.0, 0.0),
+            (10, 2),
+            Arc::new(DrawCommandBatcher::new()),
+        );
+
+        let cell = |text: &str| GridLineCell {
+            text: text.to_owned(),
+            highlight_id: None,
+            repeat: None,
+        };
+        window.draw_grid_line(0, 0, vec![cell("界"), cell(""), cell("a")], &HashMap::new());
+
+        assert_eq!(
+            window.get_cursor_grid_cell(0, 0),
+            ("界".to_owned(), None, 2)
+        );
+        assert_eq!(window.get_cursor_grid_cell(2, 0), ("a".to_owned(), None, 1));
+        // Cursors outside of the grid still cover a single cell
+        assert_eq!(
+            window.get_cursor_grid_cell(20, 0),
+            (" ".to_owned(), None, 1)
+        );
+    }
 }
@@ -2,10 +2,14 @@ use std::{
     any::{type_name, Any, TypeId},
     collections::{hash_map::Entry, HashMap},
     fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use parking_lot::{Mutex, RwLock};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::sync::mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver};
 
 use crate::channel_utils::*;
 
@@ -17,9 +21,63 @@ thread_local! {
     static THREAD_SENDERS: RwLock<HashMap<TypeId, Box<dyn Any + Send>>> = RwLock::new(HashMap::new());
 }
 
+/// Receiver returned by `register_event`, which keeps track of how many events are still queued.
+pub struct EventReceiver<T> {
+    receiver: UnboundedReceiver<T>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl<T> EventReceiver<T> {
+    fn received(&self, event: Option<T>) -> Option<T> {
+        if event.is_some() {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+        }
+        event
+    }
+
+    pub async fn recv(&mut self) -> Option<T> {
+        let event = self.receiver.recv().await;
+        self.received(event)
+    }
+
+    pub fn blocking_recv(&mut self) -> Option<T> {
+        let event = self.receiver.blocking_recv();
+        self.received(event)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let event = self.receiver.try_recv()?;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        Ok(event)
+    }
+}
+
+// A sender together with the number of its events that haven't been received yet
+struct ParentSender {
+    sender: Mutex<Box<dyn Any + Send>>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl ParentSender {
+    fn new<T: Any + Clone + Debug + Send>(sender: LoggingTx<T>) -> Self {
+        ParentSender {
+            sender: Mutex::new(Box::new(sender)),
+            queued: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn sender<T: Any + Clone + Debug + Send>(&self) -> LoggingTx<T> {
+        let sender = self.sender.lock();
+        sender.downcast_ref::<LoggingTx<T>>().unwrap().clone()
+    }
+}
+
 pub struct EventAggregator {
-    parent_senders: RwLock<HashMap<TypeId, Mutex<Box<dyn Any + Send>>>>,
+    parent_senders: RwLock<HashMap<TypeId, ParentSender>>,
     unclaimed_receivers: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    // Only registered event types count towards the queue depth, events nobody listens for are
+    // never received
+    registered_queues: RwLock<Vec<Arc<AtomicUsize>>>,
 }
 
 impl Default for EventAggregator {
@@ -27,39 +85,59 @@ impl Default for EventAggregator {
         EventAggregator {
             parent_senders: RwLock::new(HashMap::new()),
             unclaimed_receivers: RwLock::new(HashMap::new()),
+            registered_queues: RwLock::new(Vec::new()),
         }
     }
 }
 
 impl EventAggregator {
-    fn get_sender<T: Any + Clone + Debug + Send>(&self) -> LoggingTx<T> {
-        match self.parent_senders.write().entry(TypeId::of::<T>()) {
-            Entry::Occupied(entry) => {
-                let sender = entry.get().lock();
-                sender.downcast_ref::<LoggingTx<T>>().unwrap().clone()
-            }
+    /// Number of events sent but not received yet, across all registered event types.
+    pub fn queue_depth(&self) -> usize {
+        self.registered_queues
+            .read()
+            .iter()
+            .map(|queued| queued.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    fn get_sender<T: Any + Clone + Debug + Send>(&self) -> (LoggingTx<T>, Arc<AtomicUsize>) {
+        let type_id = TypeId::of::<T>();
+
+        // Sending is the hot path, so the write lock is only taken for the first event of a type
+        if let Some(parent_sender) = self.parent_senders.read().get(&type_id) {
+            return (parent_sender.sender(), parent_sender.queued.clone());
+        }
+
+        match self.parent_senders.write().entry(type_id) {
+            Entry::Occupied(entry) => (entry.get().sender(), entry.get().queued.clone()),
             Entry::Vacant(entry) => {
                 let (sender, receiver) = unbounded_channel();
                 let logging_tx = LoggingTx::attach(sender, type_name::<T>().to_owned());
-                entry.insert(Mutex::new(Box::new(logging_tx.clone())));
+                let parent_sender = entry.insert(ParentSender::new(logging_tx.clone()));
                 self.unclaimed_receivers
                     .write()
-                    .insert(TypeId::of::<T>(), Box::new(receiver));
-                logging_tx
+                    .insert(type_id, Box::new(receiver));
+                (logging_tx, parent_sender.queued.clone())
             }
         }
     }
 
     pub fn send<T: Any + Clone + Debug + Send>(&self, event: T) {
-        let sender = self.get_sender::<T>();
+        let (sender, queued) = self.get_sender::<T>();
+        // Counted before sending, so that the receiver can never see the event before it's counted
+        queued.fetch_add(1, Ordering::Relaxed);
         sender.send(event).unwrap();
     }
 
-    pub fn register_event<T: Any + Clone + Debug + Send>(&self) -> UnboundedReceiver<T> {
+    pub fn register_event<T: Any + Clone + Debug + Send>(&self) -> EventReceiver<T> {
         let type_id = TypeId::of::<T>();
 
-        if let Some(receiver) = self.unclaimed_receivers.write().remove(&type_id) {
-            *receiver.downcast::<UnboundedReceiver<T>>().unwrap()
+        // Released before looking at the senders, get_sender takes the locks the other way around
+        let unclaimed_receiver = self.unclaimed_receivers.write().remove(&type_id);
+
+        let (receiver, queued) = if let Some(receiver) = unclaimed_receiver {
+            let queued = self.parent_senders.read()[&type_id].queued.clone();
+            (*receiver.downcast::<UnboundedReceiver<T>>().unwrap(), queued)
         } else {
             let (sender, receiver) = unbounded_channel();
             let logging_sender = LoggingTx::attach(sender, type_name::<T>().to_owned());
@@ -67,11 +145,13 @@ impl EventAggregator {
             match self.parent_senders.write().entry(type_id) {
                 Entry::Occupied(_) => panic!("EventAggregator: type already registered"),
                 Entry::Vacant(entry) => {
-                    entry.insert(Mutex::new(Box::new(logging_sender)));
+                    let parent_sender = entry.insert(ParentSender::new(logging_sender));
+                    (receiver, parent_sender.queued.clone())
                 }
             }
+        };
 
-            receiver
-        }
+        self.registered_queues.write().push(queued.clone());
+        EventReceiver { receiver, queued }
     }
 }
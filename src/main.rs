@@ -16,6 +16,7 @@ mod editor;
 mod error_handling;
 mod event_aggregator;
 mod frame;
+mod performance_stats;
 mod redraw_scheduler;
 mod renderer;
 mod running_tracker;
@@ -40,7 +41,7 @@ use bridge::start_bridge;
 use cmd_line::CmdLineSettings;
 use editor::start_editor;
 use renderer::{cursor_renderer::CursorSettings, RendererSettings};
-use settings::SETTINGS;
+use settings::{restore_last_session, SETTINGS};
 use window::{create_window, KeyboardSettings, WindowSettings};
 
 pub use channel_utils::*;
@@ -139,6 +140,7 @@ fn main() {
     CursorSettings::register();
     KeyboardSettings::register();
 
+    restore_last_session();
     start_bridge();
     start_editor();
     create_window();
@@ -0,0 +1,109 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+lazy_static! {
+    pub static ref PERFORMANCE_STATS: PerformanceStats = PerformanceStats::new();
+}
+
+// Weight of the newest sample in the smoothed RPC latency
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Counters collected on the bridge and editor threads, read by the profiler once per frame.
+pub struct PerformanceStats {
+    redraw_events: AtomicU64,
+    parse_nanos: AtomicU64,
+    rpc_latency_nanos: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub redraw_events: u64,
+    pub parse_time: Duration,
+    pub rpc_latency: Duration,
+}
+
+impl PerformanceStats {
+    fn new() -> Self {
+        Self {
+            redraw_events: AtomicU64::new(0),
+            parse_nanos: AtomicU64::new(0),
+            rpc_latency_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_redraw_event(&self) {
+        self.redraw_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_parse_time(&self, duration: Duration) {
+        self.parse_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_rpc_latency(&self, duration: Duration) {
+        // Racing updates only lose a sample, which doesn't matter for a smoothed value
+        let previous = self.rpc_latency_nanos.load(Ordering::Relaxed);
+        let sample = duration.as_nanos() as u64;
+        let smoothed = if previous == 0 {
+            sample
+        } else {
+            (previous as f64 + (sample as f64 - previous as f64) * LATENCY_SMOOTHING) as u64
+        };
+        self.rpc_latency_nanos.store(smoothed, Ordering::Relaxed);
+    }
+
+    /// Returns the stats collected since the previous call and starts counting again.
+    pub fn take_frame_stats(&self) -> FrameStats {
+        FrameStats {
+            redraw_events: self.redraw_events.swap(0, Ordering::Relaxed),
+            parse_time: Duration::from_nanos(self.parse_nanos.swap(0, Ordering::Relaxed)),
+            rpc_latency: Duration::from_nanos(self.rpc_latency_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_frame_stats_resets_counters() {
+        let stats = PerformanceStats::new();
+        stats.record_redraw_event();
+        stats.record_redraw_event();
+        stats.record_parse_time(Duration::from_micros(300));
+        stats.record_parse_time(Duration::from_micros(200));
+
+        let frame_stats = stats.take_frame_stats();
+        assert_eq!(frame_stats.redraw_events, 2);
+        assert_eq!(frame_stats.parse_time, Duration::from_micros(500));
+
+        let frame_stats = stats.take_frame_stats();
+        assert_eq!(frame_stats.redraw_events, 0);
+        assert_eq!(frame_stats.parse_time, Duration::from_secs(0));
+    }
+
+    #[test]
+    fn test_rpc_latency_is_smoothed() {
+        let stats = PerformanceStats::new();
+        stats.record_rpc_latency(Duration::from_millis(10));
+        assert_eq!(
+            stats.take_frame_stats().rpc_latency,
+            Duration::from_millis(10)
+        );
+
+        stats.record_rpc_latency(Duration::from_millis(20));
+        assert_eq!(
+            stats.take_frame_stats().rpc_latency,
+            Duration::from_millis(12)
+        );
+
+        // Latency isn't reset between frames
+        assert_eq!(
+            stats.take_frame_stats().rpc_latency,
+            Duration::from_millis(12)
+        );
+    }
+}
@@ -43,6 +43,11 @@ impl RedrawScheduler {
         self.frame_queued.store(true, Ordering::Relaxed);
     }
 
+    /// Whether a frame was requested right away, meaning something is still animating.
+    pub fn is_frame_queued(&self) -> bool {
+        self.frame_queued.load(Ordering::Relaxed)
+    }
+
     pub fn should_draw(&self) -> bool {
         if self.frame_queued.load(Ordering::Relaxed) {
             self.frame_queued.store(false, Ordering::Relaxed);
@@ -1,5 +1,9 @@
+use log::error;
+use nvim_rs::Value;
 use skia_safe::Point;
 
+use crate::settings::ParseFromValue;
+
 #[allow(dead_code)]
 pub fn ease_linear(t: f32) -> f32 {
     t
@@ -65,25 +69,386 @@ pub fn ease_out_expo(t: f32) -> f32 {
     }
 }
 
-pub fn lerp(start: f32, end: f32, t: f32) -> f32 {
-    start + (end - start) * t
+pub fn ease_cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    // One coordinate of the curve through (0, 0), (x1, y1), (x2, y2) and (1, 1)
+    fn bezier(p1: f32, p2: f32, s: f32) -> f32 {
+        let inverse = 1.0 - s;
+        3.0 * inverse * inverse * s * p1 + 3.0 * inverse * s * s * p2 + s * s * s
+    }
+
+    fn bezier_slope(p1: f32, p2: f32, s: f32) -> f32 {
+        let inverse = 1.0 - s;
+        3.0 * inverse * inverse * p1 + 6.0 * inverse * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+    }
+
+    if t <= 0.0 {
+        return 0.0;
+    } else if t >= 1.0 {
+        return 1.0;
+    }
+
+    // t is the x coordinate, so find the curve parameter for it. Newton's method converges
+    // quickly for most curves, bisection handles the ones with flat spots
+    let mut s = t;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, s) - t;
+        if error.abs() < 1e-6 {
+            return bezier(y1, y2, s);
+        }
+
+        let slope = bezier_slope(x1, x2, s);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    s = t;
+    for _ in 0..32 {
+        let x = bezier(x1, x2, s);
+        if (x - t).abs() < 1e-6 {
+            break;
+        }
+
+        if x < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+
+    bezier(y1, y2, s)
+}
+
+pub fn ease_spring(damping: f32, frequency: f32, t: f32) -> f32 {
+    // Distance left to the destination of a damped spring released at the start, oscillating
+    // `frequency` times over the animation when underdamped
+    fn displacement(damping: f32, omega: f32, t: f32) -> f32 {
+        if damping < 1.0 {
+            let damped_omega = omega * (1.0 - damping * damping).sqrt();
+            (-damping * omega * t).exp()
+                * ((damped_omega * t).cos()
+                    + damping * omega / damped_omega * (damped_omega * t).sin())
+        } else if damping > 1.0 {
+            // Overdamped, the sum of a fast and a slow decay
+            let spread = omega * (damping * damping - 1.0).sqrt();
+            let fast = -damping * omega - spread;
+            let slow = -damping * omega + spread;
+            (fast * (slow * t).exp() - slow * (fast * t).exp()) / (fast - slow)
+        } else {
+            (-omega * t).exp() * (1.0 + omega * t)
+        }
+    }
+
+    let t = t.max(0.0).min(1.0);
+    let omega = 2.0 * std::f32::consts::PI * frequency;
+
+    // The spring hasn't fully settled at the end of the animation with low damping or frequency,
+    // so the remaining distance is spread over the animation to arrive without a jump
+    let remaining = displacement(damping, omega, 1.0);
+    1.0 - (displacement(damping, omega, t) - remaining * t)
 }
 
-pub fn ease(ease_func: fn(f32) -> f32, start: f32, end: f32, t: f32) -> f32 {
-    lerp(start, end, ease_func(t))
+/// Easing curve selectable from a setting. Accepts the name of one of the easing functions above,
+/// `cubic_bezier(x1, y1, x2, y2)` like in CSS, or `spring(damping, frequency)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InExpo,
+    OutExpo,
+    CubicBezier(f32, f32, f32, f32),
+    Spring { damping: f32, frequency: f32 },
 }
 
-pub fn ease_point(ease_func: fn(f32) -> f32, start: Point, end: Point, t: f32) -> Point {
-    Point {
-        x: ease(ease_func, start.x, end.x, t),
-        y: ease(ease_func, start.y, end.y, t),
+impl Easing {
+    const NAMES: [(&'static str, Easing); 9] = [
+        ("linear", Easing::Linear),
+        ("in_quad", Easing::InQuad),
+        ("out_quad", Easing::OutQuad),
+        ("in_out_quad", Easing::InOutQuad),
+        ("in_cubic", Easing::InCubic),
+        ("out_cubic", Easing::OutCubic),
+        ("in_out_cubic", Easing::InOutCubic),
+        ("in_expo", Easing::InExpo),
+        ("out_expo", Easing::OutExpo),
+    ];
+
+    fn parse(text: &str) -> Option<Easing> {
+        let text = text.trim();
+        if let Some((_, easing)) = Easing::NAMES.iter().find(|(name, _)| *name == text) {
+            return Some(*easing);
+        }
+
+        let (function, arguments) = text.strip_suffix(')')?.split_once('(')?;
+        let arguments = arguments
+            .split(',')
+            .map(|argument| argument.trim().parse::<f32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        match (function.trim(), arguments.as_slice()) {
+            // Like in CSS, the x coordinates have to stay in range for the curve to be a function
+            ("cubic_bezier", &[x1, y1, x2, y2])
+                if (0.0..=1.0).contains(&x1) && (0.0..=1.0).contains(&x2) =>
+            {
+                Some(Easing::CubicBezier(x1, y1, x2, y2))
+            }
+            ("spring", &[damping, frequency]) if damping > 0.0 && frequency > 0.0 => {
+                Some(Easing::Spring { damping, frequency })
+            }
+            _ => None,
+        }
+    }
+
+    /// Eases t. The named easings extrapolate outside of the 0 to 1 range, the cubic bezier and
+    /// spring curves keep their end values there.
+    pub fn apply(&self, t: f32) -> f32 {
+        match *self {
+            Easing::Linear => ease_linear(t),
+            Easing::InQuad => ease_in_quad(t),
+            Easing::OutQuad => ease_out_quad(t),
+            Easing::InOutQuad => ease_in_out_quad(t),
+            Easing::InCubic => ease_in_cubic(t),
+            Easing::OutCubic => ease_out_cubic(t),
+            Easing::InOutCubic => ease_in_out_cubic(t),
+            Easing::InExpo => ease_in_expo(t),
+            Easing::OutExpo => ease_out_expo(t),
+            Easing::CubicBezier(x1, y1, x2, y2) => ease_cubic_bezier(x1, y1, x2, y2, t),
+            Easing::Spring { damping, frequency } => ease_spring(damping, frequency, t),
+        }
+    }
+
+    pub fn ease(&self, start: f32, end: f32, t: f32) -> f32 {
+        lerp(start, end, self.apply(t))
+    }
+
+    pub fn ease_point(&self, start: Point, end: Point, t: f32) -> Point {
+        Point {
+            x: self.ease(start.x, end.x, t),
+            y: self.ease(start.y, end.y, t),
+        }
+    }
+}
+
+impl ParseFromValue for Easing {
+    fn parse_from_value(&mut self, value: Value) {
+        if value.is_str() {
+            let text = value.as_str().unwrap();
+            match Easing::parse(text) {
+                Some(easing) => *self = easing,
+                None => error!("Expected an easing function, but received {:?}", text),
+            }
+        } else {
+            error!(
+                "Expected an easing function string, but received {:?}",
+                value
+            );
+        }
+    }
+}
+
+impl From<Easing> for Value {
+    fn from(easing: Easing) -> Self {
+        match easing {
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                Value::from(format!("cubic_bezier({}, {}, {}, {})", x1, y1, x2, y2))
+            }
+            Easing::Spring { damping, frequency } => {
+                Value::from(format!("spring({}, {})", damping, frequency))
+            }
+            named_easing => {
+                let (name, _) = Easing::NAMES
+                    .iter()
+                    .find(|(_, easing)| *easing == named_easing)
+                    .unwrap();
+                Value::from(*name)
+            }
+        }
     }
 }
 
+pub fn lerp(start: f32, end: f32, t: f32) -> f32 {
+    start + (end - start) * t
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_parse_easing() {
+        let mut easing = Easing::Linear;
+
+        easing.parse_from_value(Value::from("in_out_cubic"));
+        assert_eq!(easing, Easing::InOutCubic);
+
+        easing.parse_from_value(Value::from("bounce"));
+        assert_eq!(easing, Easing::InOutCubic);
+
+        easing.parse_from_value(Value::from(1));
+        assert_eq!(easing, Easing::InOutCubic);
+    }
+
+    #[test]
+    fn test_easing_names_round_trip() {
+        for (name, easing) in Easing::NAMES {
+            assert_eq!(Value::from(easing), Value::from(name));
+
+            let mut parsed = Easing::Linear;
+            parsed.parse_from_value(Value::from(name));
+            assert_eq!(parsed, easing);
+        }
+    }
+
+    #[test]
+    fn test_parse_cubic_bezier() {
+        let mut easing = Easing::Linear;
+
+        easing.parse_from_value(Value::from("cubic_bezier(0.25, 0.1, 0.25, 1)"));
+        assert_eq!(easing, Easing::CubicBezier(0.25, 0.1, 0.25, 1.0));
+        assert_eq!(
+            Value::from(easing),
+            Value::from("cubic_bezier(0.25, 0.1, 0.25, 1)")
+        );
+
+        // x coordinates out of range and wrong argument counts are rejected
+        easing.parse_from_value(Value::from("cubic_bezier(1.5, 0, 0, 1)"));
+        assert_eq!(easing, Easing::CubicBezier(0.25, 0.1, 0.25, 1.0));
+        easing.parse_from_value(Value::from("cubic_bezier(0, 0, 1)"));
+        assert_eq!(easing, Easing::CubicBezier(0.25, 0.1, 0.25, 1.0));
+    }
+
+    #[test]
+    fn test_parse_spring() {
+        let mut easing = Easing::Linear;
+
+        easing.parse_from_value(Value::from(" spring( 0.5, 2 ) "));
+        assert_eq!(
+            easing,
+            Easing::Spring {
+                damping: 0.5,
+                frequency: 2.0
+            }
+        );
+        assert_eq!(Value::from(easing), Value::from("spring(0.5, 2)"));
+
+        easing.parse_from_value(Value::from("spring(0, 2)"));
+        assert_eq!(
+            easing,
+            Easing::Spring {
+                damping: 0.5,
+                frequency: 2.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_easing_apply() {
+        assert_eq!(Easing::InOutCubic.ease(1.0, 0.0, 0.25), 0.9375);
+        assert_eq!(Easing::OutExpo.ease(1.0, 0.0, 1.0), 0.0);
+        // The curves only defined from 0 to 1 stay at their ends outside of it
+        assert_eq!(Easing::CubicBezier(0.25, 0.1, 0.25, 1.0).apply(2.0), 1.0);
+        assert_eq!(Easing::CubicBezier(0.25, 0.1, 0.25, 1.0).apply(-1.0), 0.0);
+        let spring = Easing::Spring {
+            damping: 0.3,
+            frequency: 2.0,
+        };
+        assert_eq!(spring.apply(2.0), 1.0);
+        assert_eq!(spring.apply(-1.0), 0.0);
+    }
+
+    #[test]
+    fn test_all_easings_start_and_end_in_place() {
+        let easings = Easing::NAMES.iter().map(|(_, easing)| *easing).chain([
+            Easing::CubicBezier(0.42, 0.0, 0.58, 1.0),
+            Easing::CubicBezier(0.3, -0.5, 0.7, 1.5),
+            Easing::Spring {
+                damping: 0.3,
+                frequency: 2.0,
+            },
+            Easing::Spring {
+                damping: 1.0,
+                frequency: 2.0,
+            },
+        ]);
+
+        for easing in easings {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn test_cubic_bezier_linear() {
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!((ease_cubic_bezier(0.25, 0.25, 0.75, 0.75, t) - t).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_cubic_bezier_matches_css_ease() {
+        // Reference values of CSS ease, which is cubic-bezier(0.25, 0.1, 0.25, 1)
+        let expected = [(0.25, 0.4094), (0.5, 0.8024), (0.75, 0.9604)];
+        for (t, value) in expected {
+            assert!(
+                (ease_cubic_bezier(0.25, 0.1, 0.25, 1.0, t) - value).abs() < 1e-3,
+                "{}",
+                t
+            );
+        }
+    }
+
+    #[test]
+    fn test_cubic_bezier_is_monotonic_for_monotonic_curves() {
+        let mut previous = 0.0;
+        for i in 1..=100 {
+            let value = ease_cubic_bezier(0.42, 0.0, 0.58, 1.0, i as f32 / 100.0);
+            assert!(value >= previous);
+            previous = value;
+        }
+    }
+
+    #[test]
+    fn test_spring_overshoots_when_underdamped() {
+        let overshoots = (1..100).any(|i| ease_spring(0.3, 2.0, i as f32 / 100.0) > 1.0);
+        assert!(overshoots);
+    }
+
+    #[test]
+    fn test_spring_does_not_overshoot_when_critically_damped() {
+        for i in 0..100 {
+            let value = ease_spring(1.0, 2.0, i as f32 / 100.0);
+            assert!((0.0..=1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_overdamped_spring_is_slower_than_critically_damped() {
+        let mut previous = 0.0;
+        for i in 1..=100 {
+            let value = ease_spring(3.0, 2.0, i as f32 / 100.0);
+            assert!(value >= previous && value <= 1.0 + 1e-6);
+            previous = value;
+        }
+        assert!(ease_spring(3.0, 2.0, 0.25) < ease_spring(1.0, 2.0, 0.25) - 0.2);
+    }
+
+    #[test]
+    fn test_spring_settles_without_a_jump() {
+        // Barely damped and slow, so the raw spring is still far from resting at the end
+        let before_end = ease_spring(0.05, 0.3, 0.999);
+        assert!((before_end - 1.0).abs() < 0.01, "{}", before_end);
+        assert_eq!(ease_spring(0.05, 0.3, 1.0), 1.0);
+    }
+
     #[test]
     fn test_lerp() {
         assert_eq!(lerp(1.0, 0.0, 1.0), 0.0);
@@ -91,72 +456,72 @@ mod test {
 
     #[test]
     fn test_ease_linear() {
-        assert_eq!(ease(ease_linear, 1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::Linear.ease(1.0, 0.0, 1.0), 0.0);
     }
 
     #[test]
     fn test_ease_in_quad() {
-        assert_eq!(ease(ease_in_quad, 1.00, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::InQuad.ease(1.00, 0.0, 1.0), 0.0);
     }
 
     #[test]
     fn test_ease_out_quad() {
-        assert_eq!(ease(ease_out_quad, 1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::OutQuad.ease(1.0, 0.0, 1.0), 0.0);
     }
 
     #[test]
     fn test_ease_in_expo() {
-        assert_eq!(ease(ease_in_expo, 1.0, 0.0, 1.0), 0.0);
-        assert_eq!(ease(ease_in_expo, 1.0, 0.0, 0.0), 1.0);
+        assert_eq!(Easing::InExpo.ease(1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::InExpo.ease(1.0, 0.0, 0.0), 1.0);
     }
 
     #[test]
     fn test_ease_out_expo() {
-        assert_eq!(ease(ease_out_expo, 1.0, 0.0, 1.0), 0.0);
-        assert_eq!(ease(ease_out_expo, 1.0, 0.0, 1.1), 0.00048828125);
+        assert_eq!(Easing::OutExpo.ease(1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::OutExpo.ease(1.0, 0.0, 1.1), 0.00048828125);
     }
 
     #[test]
     fn test_ease_in_out_quad() {
-        assert_eq!(ease(ease_in_out_quad, 1.0, 0.0, 1.0), 0.0);
-        assert_eq!(ease(ease_in_out_quad, 1.00, 0.0, 0.4), 0.67999995);
+        assert_eq!(Easing::InOutQuad.ease(1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::InOutQuad.ease(1.00, 0.0, 0.4), 0.67999995);
     }
 
     #[test]
     fn test_ease_in_cubic() {
-        assert_eq!(ease(ease_in_cubic, 1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::InCubic.ease(1.0, 0.0, 1.0), 0.0);
     }
 
     #[test]
     fn test_ease_out_cubic() {
-        assert_eq!(ease(ease_out_cubic, 1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::OutCubic.ease(1.0, 0.0, 1.0), 0.0);
     }
 
     #[test]
     fn test_ease_in_out_cubic() {
-        assert_eq!(ease(ease_in_out_cubic, 1.0, 0.0, 1.0), 0.0);
-        assert_eq!(ease(ease_in_out_cubic, 1.0, 0.0, 0.25), 0.9375);
+        assert_eq!(Easing::InOutCubic.ease(1.0, 0.0, 1.0), 0.0);
+        assert_eq!(Easing::InOutCubic.ease(1.0, 0.0, 0.25), 0.9375);
     }
 
     #[test]
     fn test_ease_point_linear() {
         let start = Point { x: 0.0, y: 0.0 };
         let end = Point { x: 1.0, y: 1.0 };
-        assert_eq!(ease_point(ease_linear, start, end, 1.0), end);
+        assert_eq!(Easing::Linear.ease_point(start, end, 1.0), end);
     }
 
     #[test]
     fn test_ease_point_in_quad() {
         let start = Point { x: 0.0, y: 0.0 };
         let end = Point { x: 1.0, y: 1.0 };
-        assert_eq!(ease_point(ease_in_quad, start, end, 1.0), end);
+        assert_eq!(Easing::InQuad.ease_point(start, end, 1.0), end);
     }
 
     #[test]
     fn test_ease_point_out_quad() {
         let start = Point { x: 0.0, y: 0.0 };
         let end = Point { x: 1.0, y: 1.0 };
-        assert_eq!(ease_point(ease_out_quad, start, end, 1.0), end);
+        assert_eq!(Easing::OutQuad.ease_point(start, end, 1.0), end);
     }
 
     #[test]
@@ -167,22 +532,22 @@ mod test {
             x: 0.68000007,
             y: 0.68000007,
         };
-        assert_eq!(ease_point(ease_in_out_quad, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_in_out_quad, start, end, 1.4), expected);
+        assert_eq!(Easing::InOutQuad.ease_point(start, end, 1.0), end);
+        assert_eq!(Easing::InOutQuad.ease_point(start, end, 1.4), expected);
     }
 
     #[test]
     fn test_ease_point_in_cubic() {
         let start = Point { x: 0.0, y: 0.0 };
         let end = Point { x: 1.0, y: 1.0 };
-        assert_eq!(ease_point(ease_in_cubic, start, end, 1.0), end);
+        assert_eq!(Easing::InCubic.ease_point(start, end, 1.0), end);
     }
 
     #[test]
     fn test_ease_point_out_cubic() {
         let start = Point { x: 0.0, y: 0.0 };
         let end = Point { x: 1.0, y: 1.0 };
-        assert_eq!(ease_point(ease_out_cubic, start, end, 1.0), end);
+        assert_eq!(Easing::OutCubic.ease_point(start, end, 1.0), end);
     }
 
     #[test]
@@ -193,16 +558,16 @@ mod test {
             x: 0.0625,
             y: 0.0625,
         };
-        assert_eq!(ease_point(ease_in_out_cubic, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_in_out_cubic, start, end, 0.25), expected);
+        assert_eq!(Easing::InOutCubic.ease_point(start, end, 1.0), end);
+        assert_eq!(Easing::InOutCubic.ease_point(start, end, 0.25), expected);
     }
 
     #[test]
     fn test_ease_point_in_expo() {
         let start = Point { x: 0.0, y: 0.0 };
         let end = Point { x: 1.0, y: 1.0 };
-        assert_eq!(ease_point(ease_in_expo, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_in_expo, start, end, 0.0), start);
+        assert_eq!(Easing::InExpo.ease_point(start, end, 1.0), end);
+        assert_eq!(Easing::InExpo.ease_point(start, end, 0.0), start);
     }
 
     #[test]
@@ -213,7 +578,7 @@ mod test {
             x: 0.9995117,
             y: 0.9995117,
         };
-        assert_eq!(ease_point(ease_out_expo, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_out_expo, start, end, 1.1), expected);
+        assert_eq!(Easing::OutExpo.ease_point(start, end, 1.0), end);
+        assert_eq!(Easing::OutExpo.ease_point(start, end, 1.1), expected);
     }
 }
@@ -0,0 +1,330 @@
+use std::{fs, path::PathBuf};
+
+use log::error;
+use nvim_rs::Value;
+use skia_safe::{
+    Canvas, Color, Data, Image, Paint, Point, Rect, SamplingOptions, Shader, Size, TileMode,
+};
+
+use crate::{renderer::RendererSettings, settings::*};
+
+/// How the background image fills the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackgroundImageMode {
+    /// Scaled to cover the whole window, cropping what doesn't fit
+    Cover,
+    /// Scaled to fit into the window, leaving the rest to the gradient or background color
+    Contain,
+    /// Repeated at its natural size
+    Tile,
+}
+
+impl ParseFromValue for BackgroundImageMode {
+    fn parse_from_value(&mut self, value: Value) {
+        if value.is_str() {
+            *self = match value.as_str().unwrap() {
+                "cover" => BackgroundImageMode::Cover,
+                "contain" => BackgroundImageMode::Contain,
+                "tile" => BackgroundImageMode::Tile,
+                value => {
+                    error!("Expected a background image mode, but received {:?}", value);
+                    return;
+                }
+            };
+        } else {
+            error!(
+                "Expected a background image mode string, but received {:?}",
+                value
+            );
+        }
+    }
+}
+
+impl From<BackgroundImageMode> for Value {
+    fn from(mode: BackgroundImageMode) -> Self {
+        match mode {
+            BackgroundImageMode::Cover => Value::from("cover"),
+            BackgroundImageMode::Contain => Value::from("contain"),
+            BackgroundImageMode::Tile => Value::from("tile"),
+        }
+    }
+}
+
+/// Colors of a linear gradient, evenly spaced from start to end.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackgroundGradient(pub Vec<Color>);
+
+impl ParseFromValue for BackgroundGradient {
+    fn parse_from_value(&mut self, value: Value) {
+        let values = match value.as_array() {
+            Some(values) => values,
+            None => {
+                error!("Expected a list of colors, but received {:?}", value);
+                return;
+            }
+        };
+
+        let mut colors = Vec::with_capacity(values.len());
+        for value in values {
+            match value.as_str().map(csscolorparser::parse) {
+                Some(Ok(color)) => {
+                    let [red, green, blue, alpha] = color.to_rgba8();
+                    colors.push(Color::from_argb(alpha, red, green, blue));
+                }
+                Some(Err(error)) => {
+                    error!("Expected a color, but received {:?}: {}", value, error);
+                    return;
+                }
+                None => {
+                    error!("Expected a color string, but received {:?}", value);
+                    return;
+                }
+            }
+        }
+
+        *self = BackgroundGradient(colors);
+    }
+}
+
+impl From<BackgroundGradient> for Value {
+    fn from(BackgroundGradient(colors): BackgroundGradient) -> Self {
+        Value::from(
+            colors
+                .iter()
+                .map(|color| {
+                    Value::from(format!(
+                        "#{:02x}{:02x}{:02x}{:02x}",
+                        color.r(),
+                        color.g(),
+                        color.b(),
+                        color.a()
+                    ))
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// Where the image is drawn within the target for the cover and contain modes, keeping its aspect
+/// ratio and centering it. Tiled images keep their natural size.
+fn image_rect(mode: BackgroundImageMode, image_size: Size, target: Rect) -> Rect {
+    let scale_x = target.width() / image_size.width;
+    let scale_y = target.height() / image_size.height;
+    let scale = match mode {
+        BackgroundImageMode::Cover => scale_x.max(scale_y),
+        BackgroundImageMode::Contain => scale_x.min(scale_y),
+        BackgroundImageMode::Tile => 1.0,
+    };
+
+    let size = Size::new(image_size.width * scale, image_size.height * scale);
+    Rect::from_point_and_size(
+        (
+            target.center_x() - size.width / 2.0,
+            target.center_y() - size.height / 2.0,
+        ),
+        size,
+    )
+}
+
+/// Start and end of a linear gradient, with the angle in degrees like in CSS, so 0 goes up and 90
+/// goes right. The gradient line is long enough for the corners to get the first and last color.
+fn gradient_points(angle: f32, target: Rect) -> (Point, Point) {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let length = (target.width() * sin).abs() + (target.height() * cos).abs();
+    let half = Point::new(sin, -cos) * (length / 2.0);
+    let center = target.center();
+
+    (center - half, center + half)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(relative_path), Some(home)) => home.join(relative_path),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn load_image(path: &str) -> Option<Image> {
+    let bytes = fs::read(expand_home(path))
+        .map_err(|error| error!("Could not read background image {}: {}", path, error))
+        .ok()?;
+
+    let image = Image::from_encoded(Data::new_copy(&bytes));
+    if image.is_none() {
+        error!("Could not decode background image {}", path);
+    }
+    image
+}
+
+/// Image and gradient drawn behind the editor.
+#[derive(Default)]
+pub struct Background {
+    image_path: String,
+    image: Option<Image>,
+}
+
+impl Background {
+    /// Loads the image again whenever the setting points to another file.
+    pub fn update(&mut self, settings: &RendererSettings) {
+        if self.image_path != settings.background_image {
+            self.image_path = settings.background_image.clone();
+            self.image = if self.image_path.is_empty() {
+                None
+            } else {
+                load_image(&self.image_path)
+            };
+        }
+    }
+
+    /// Draws the gradient and image into the target. Returns false if neither is configured.
+    pub fn draw(&self, canvas: &mut Canvas, target: Rect, settings: &RendererSettings) -> bool {
+        let gradient = &settings.background_gradient.0;
+        if gradient.is_empty() && self.image.is_none() {
+            return false;
+        }
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+
+        if gradient.len() == 1 {
+            paint.set_color(gradient[0]);
+            canvas.draw_rect(target, &paint);
+        } else if gradient.len() > 1 {
+            let points = gradient_points(settings.background_gradient_angle, target);
+            paint.set_shader(Shader::linear_gradient(
+                points,
+                gradient.as_slice(),
+                None,
+                TileMode::Clamp,
+                None,
+                None,
+            ));
+            canvas.draw_rect(target, &paint);
+            paint.set_shader(None);
+        }
+
+        if let Some(image) = &self.image {
+            let mode = settings.background_image_mode;
+            let image_size = Size::new(image.width() as f32, image.height() as f32);
+            let image_rect = image_rect(mode, image_size, target);
+
+            canvas.save();
+            canvas.clip_rect(target, None, Some(false));
+            if mode == BackgroundImageMode::Tile {
+                paint.set_shader(image.to_shader(
+                    (TileMode::Repeat, TileMode::Repeat),
+                    SamplingOptions::default(),
+                    None,
+                ));
+                canvas.draw_rect(target, &paint);
+                paint.set_shader(None);
+            } else {
+                canvas.draw_image_rect(image, None, image_rect, &paint);
+            }
+
+            let dim = settings.background_image_dim.min(1.0).max(0.0);
+            if dim > 0.0 {
+                paint.set_color(Color::BLACK.with_a((dim * 255.0) as u8));
+                match mode {
+                    BackgroundImageMode::Tile => canvas.draw_rect(target, &paint),
+                    _ => canvas.draw_rect(image_rect, &paint),
+                };
+            }
+            canvas.restore();
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Rect = Rect {
+        left: 0.0,
+        top: 0.0,
+        right: 200.0,
+        bottom: 100.0,
+    };
+
+    #[test]
+    fn test_cover_fills_the_target() {
+        let rect = image_rect(BackgroundImageMode::Cover, Size::new(50.0, 50.0), TARGET);
+        assert_eq!(rect, Rect::new(0.0, -50.0, 200.0, 150.0));
+    }
+
+    #[test]
+    fn test_contain_fits_into_the_target() {
+        let rect = image_rect(BackgroundImageMode::Contain, Size::new(50.0, 50.0), TARGET);
+        assert_eq!(rect, Rect::new(50.0, 0.0, 150.0, 100.0));
+    }
+
+    #[test]
+    fn test_tile_keeps_the_natural_size() {
+        let rect = image_rect(BackgroundImageMode::Tile, Size::new(50.0, 50.0), TARGET);
+        assert_eq!(rect, Rect::new(75.0, 25.0, 125.0, 75.0));
+    }
+
+    fn assert_point_eq(actual: Point, expected: Point) {
+        assert!(
+            (actual - expected).length() < 0.001,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_gradient_points() {
+        let (start, end) = gradient_points(180.0, TARGET);
+        assert_point_eq(start, Point::new(100.0, 0.0));
+        assert_point_eq(end, Point::new(100.0, 100.0));
+
+        let (start, end) = gradient_points(90.0, TARGET);
+        assert_point_eq(start, Point::new(0.0, 50.0));
+        assert_point_eq(end, Point::new(200.0, 50.0));
+    }
+
+    #[test]
+    fn test_parse_image_mode() {
+        let mut mode = BackgroundImageMode::Cover;
+
+        mode.parse_from_value(Value::from("tile"));
+        assert_eq!(mode, BackgroundImageMode::Tile);
+
+        mode.parse_from_value(Value::from("stretch"));
+        assert_eq!(mode, BackgroundImageMode::Tile);
+
+        assert_eq!(
+            Value::from(BackgroundImageMode::Contain),
+            Value::from("contain")
+        );
+    }
+
+    #[test]
+    fn test_parse_gradient() {
+        let mut gradient = BackgroundGradient::default();
+
+        gradient.parse_from_value(Value::from(vec![
+            Value::from("#ff0000"),
+            Value::from("rgba(0, 0, 255, 0.5)"),
+        ]));
+        assert_eq!(
+            gradient,
+            BackgroundGradient(vec![
+                Color::from_argb(255, 255, 0, 0),
+                Color::from_argb(128, 0, 0, 255)
+            ])
+        );
+
+        // Invalid lists keep the previous gradient
+        gradient.parse_from_value(Value::from(vec![Value::from("not a color")]));
+        assert_eq!(gradient.0.len(), 2);
+        gradient.parse_from_value(Value::from("#ff0000"));
+        assert_eq!(gradient.0.len(), 2);
+
+        gradient.parse_from_value(Value::from(Vec::<Value>::new()));
+        assert_eq!(gradient, BackgroundGradient::default());
+    }
+}
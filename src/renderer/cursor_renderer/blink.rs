@@ -1,33 +1,66 @@
 use std::time::{Duration, Instant};
 
-use crate::{editor::Cursor, redraw_scheduler::REDRAW_SCHEDULER};
+use super::CursorSettings;
+use crate::editor::Cursor;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BlinkState {
     Waiting,
     On,
     Off,
 }
 
+/// When the cursor has to be drawn again for the blink to continue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlinkRedraw {
+    /// The cursor looks the same until it changes
+    Never,
+    /// The cursor looks the same until the given time
+    At(Instant),
+    /// The cursor is fading, so every frame looks different
+    NextFrame,
+}
+
 pub struct BlinkStatus {
     state: BlinkState,
     last_transition: Instant,
+    // Last time the cursor moved or changed shape, used to stop blinking when idle
+    last_activity: Instant,
     previous_cursor: Option<Cursor>,
 }
 
 impl BlinkStatus {
-    pub fn new() -> BlinkStatus {
+    pub fn new(now: Instant) -> BlinkStatus {
         BlinkStatus {
             state: BlinkState::Waiting,
-            last_transition: Instant::now(),
+            last_transition: now,
+            last_activity: now,
             previous_cursor: None,
         }
     }
 
-    pub fn update_status(&mut self, new_cursor: &Cursor) -> bool {
-        if self.previous_cursor.is_none() || new_cursor != self.previous_cursor.as_ref().unwrap() {
+    fn phase_length(&self, cursor: &Cursor) -> Option<Duration> {
+        match self.state {
+            BlinkState::Waiting => cursor.blinkwait,
+            BlinkState::Off => cursor.blinkoff,
+            BlinkState::On => cursor.blinkon,
+        }
+        .filter(|millis| *millis > 0)
+        .map(Duration::from_millis)
+    }
+
+    /// Advances the blink to the given time and returns the opacity of the cursor between 0 and
+    /// 1, together with when it needs to be drawn again.
+    pub fn update_status(
+        &mut self,
+        new_cursor: &Cursor,
+        settings: &CursorSettings,
+        now: Instant,
+    ) -> (f32, BlinkRedraw) {
+        if self.previous_cursor.as_ref() != Some(new_cursor) {
             self.previous_cursor = Some(new_cursor.clone());
-            self.last_transition = Instant::now();
+            self.last_transition = now;
+            self.last_activity = now;
             if new_cursor.blinkwait.is_some() && new_cursor.blinkwait != Some(0) {
                 self.state = BlinkState::Waiting;
             } else {
@@ -39,43 +72,208 @@ impl BlinkStatus {
             || new_cursor.blinkoff == Some(0)
             || new_cursor.blinkon == Some(0)
         {
-            return true;
+            return (1.0, BlinkRedraw::Never);
         }
 
-        let delay = match self.state {
-            BlinkState::Waiting => new_cursor.blinkwait,
-            BlinkState::Off => new_cursor.blinkoff,
-            BlinkState::On => new_cursor.blinkon,
+        let idle_deadline = if settings.blink_idle_timeout > 0.0 {
+            Some(self.last_activity + Duration::from_secs_f32(settings.blink_idle_timeout))
+        } else {
+            None
+        };
+        if idle_deadline.map_or(false, |deadline| now >= deadline) {
+            self.state = BlinkState::On;
+            return (1.0, BlinkRedraw::Never);
         }
-        .filter(|millis| *millis > 0)
-        .map(Duration::from_millis);
 
-        if delay
-            .map(|delay| self.last_transition + delay < Instant::now())
-            .unwrap_or(false)
-        {
+        // Catch up with every phase that ended since the last frame
+        while let Some(phase_length) = self.phase_length(new_cursor) {
+            let phase_end = self.last_transition + phase_length;
+            if phase_end > now {
+                break;
+            }
+
             self.state = match self.state {
                 BlinkState::Waiting => BlinkState::On,
                 BlinkState::On => BlinkState::Off,
                 BlinkState::Off => BlinkState::On,
             };
-            self.last_transition = Instant::now();
+            self.last_transition = phase_end;
         }
 
-        let scheduled_frame = (match self.state {
-            BlinkState::Waiting => new_cursor.blinkwait,
-            BlinkState::Off => new_cursor.blinkoff,
-            BlinkState::On => new_cursor.blinkon,
-        })
-        .map(|delay| self.last_transition + Duration::from_millis(delay));
+        let phase_length = match self.phase_length(new_cursor) {
+            Some(phase_length) => phase_length,
+            None => {
+                let opacity = if self.state == BlinkState::Off {
+                    0.0
+                } else {
+                    1.0
+                };
+                return (opacity, BlinkRedraw::Never);
+            }
+        };
 
-        if let Some(scheduled_frame) = scheduled_frame {
-            REDRAW_SCHEDULER.schedule(scheduled_frame);
+        let phase_end = self.last_transition + phase_length;
+        let next_change = match idle_deadline {
+            Some(idle_deadline) if idle_deadline < phase_end => idle_deadline,
+            _ => phase_end,
+        };
+
+        if !settings.smooth_blink || self.state == BlinkState::Waiting {
+            let opacity = if self.state == BlinkState::Off {
+                0.0
+            } else {
+                1.0
+            };
+            return (opacity, BlinkRedraw::At(next_change));
         }
 
-        match self.state {
-            BlinkState::Off => false,
-            BlinkState::On | BlinkState::Waiting => true,
+        // Fade out across the on phase and back in across the off phase
+        let t = ((now - self.last_transition).as_secs_f32() / phase_length.as_secs_f32()).min(1.0);
+        let opacity = match self.state {
+            BlinkState::On => settings.blink_easing.ease(1.0, 0.0, t),
+            _ => settings.blink_easing.ease(0.0, 1.0, t),
+        };
+
+        (opacity, BlinkRedraw::NextFrame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::animation_utils::Easing;
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    fn blinking_cursor() -> Cursor {
+        Cursor {
+            blinkwait: Some(100),
+            blinkon: Some(200),
+            blinkoff: Some(400),
+            ..Cursor::new()
         }
     }
+
+    fn smooth_settings() -> CursorSettings {
+        CursorSettings {
+            smooth_blink: true,
+            blink_easing: Easing::Linear,
+            ..CursorSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_hard_blink_switches_between_phases() {
+        let start = Instant::now();
+        let settings = CursorSettings::default();
+        let cursor = blinking_cursor();
+        let mut blink = BlinkStatus::new(start);
+
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start),
+            (1.0, BlinkRedraw::At(start + ms(100)))
+        );
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(150)),
+            (1.0, BlinkRedraw::At(start + ms(300)))
+        );
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(300)),
+            (0.0, BlinkRedraw::At(start + ms(700)))
+        );
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(750)),
+            (1.0, BlinkRedraw::At(start + ms(900)))
+        );
+    }
+
+    #[test]
+    fn test_catches_up_with_missed_phases() {
+        let start = Instant::now();
+        let settings = CursorSettings::default();
+        let cursor = blinking_cursor();
+        let mut blink = BlinkStatus::new(start);
+        blink.update_status(&cursor, &settings, start);
+
+        // Waiting ends at 100, two on and off cycles of 600 milliseconds follow and the third
+        // one is off from 1500
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(1600)),
+            (0.0, BlinkRedraw::At(start + ms(1900)))
+        );
+    }
+
+    #[test]
+    fn test_smooth_blink_fades() {
+        let start = Instant::now();
+        let settings = smooth_settings();
+        let cursor = blinking_cursor();
+        let mut blink = BlinkStatus::new(start);
+
+        // Blinkwait keeps the cursor solid
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(50)),
+            (1.0, BlinkRedraw::At(start + ms(150)))
+        );
+
+        let (opacity, redraw) = blink.update_status(&cursor, &settings, start + ms(250));
+        assert!((opacity - 0.5).abs() < 0.001);
+        assert_eq!(redraw, BlinkRedraw::NextFrame);
+
+        let (opacity, _) = blink.update_status(&cursor, &settings, start + ms(350));
+        assert!(opacity.abs() < 0.001);
+
+        let (opacity, _) = blink.update_status(&cursor, &settings, start + ms(550));
+        assert!((opacity - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_stops_blinking_when_idle() {
+        let start = Instant::now();
+        let settings = CursorSettings {
+            blink_idle_timeout: 1.0,
+            ..CursorSettings::default()
+        };
+        let cursor = blinking_cursor();
+        let mut blink = BlinkStatus::new(start);
+        blink.update_status(&cursor, &settings, start);
+
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(950)),
+            (0.0, BlinkRedraw::At(start + ms(1000)))
+        );
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(1000)),
+            (1.0, BlinkRedraw::Never)
+        );
+
+        // Moving the cursor starts blinking again
+        let moved_cursor = Cursor {
+            grid_position: (1, 0),
+            ..blinking_cursor()
+        };
+        let later = start + ms(5000);
+        assert_eq!(
+            blink.update_status(&moved_cursor, &settings, later),
+            (1.0, BlinkRedraw::At(later + ms(100)))
+        );
+    }
+
+    #[test]
+    fn test_zero_disables_blinking() {
+        let start = Instant::now();
+        let settings = smooth_settings();
+        let cursor = Cursor {
+            blinkon: Some(0),
+            ..blinking_cursor()
+        };
+        let mut blink = BlinkStatus::new(start);
+
+        assert_eq!(
+            blink.update_status(&cursor, &settings, start + ms(500)),
+            (1.0, BlinkRedraw::Never)
+        );
+    }
 }
@@ -3,10 +3,8 @@ use nvim_rs::Value;
 use skia_safe::{paint::Style, BlendMode, Canvas, Color, Paint, Point, Rect};
 
 use crate::{
-    editor::Cursor,
-    renderer::cursor_renderer::CursorSettings,
-    renderer::{animation_utils::*, grid_renderer::GridRenderer},
-    settings::*,
+    bridge::EditorMode, editor::Cursor, renderer::animation_utils::ease_spring,
+    renderer::cursor_renderer::CursorSettings, renderer::grid_renderer::GridRenderer, settings::*,
 };
 
 pub trait CursorVfx {
@@ -18,6 +16,8 @@ pub trait CursorVfx {
         dt: f32,
     ) -> bool;
     fn restart(&mut self, position: Point);
+    /// Called when the editor switches to another mode, with the center of the cursor.
+    fn mode_changed(&mut self, _position: Point) {}
     fn render(
         &self,
         settings: &CursorSettings,
@@ -27,71 +27,265 @@ pub trait CursorVfx {
     );
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum HighlightMode {
     SonicBoom,
     Ripple,
     Wireframe,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TrailMode {
     Railgun,
     Torpedo,
     PixieDust,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum VfxMode {
     Highlight(HighlightMode),
     Trail(TrailMode),
+    Smear,
+    ModeRipple,
+    Typewriter,
     Disabled,
 }
 
-impl ParseFromValue for VfxMode {
+impl VfxMode {
+    fn from_name(name: &str) -> Option<VfxMode> {
+        Some(match name {
+            "sonicboom" => VfxMode::Highlight(HighlightMode::SonicBoom),
+            "ripple" => VfxMode::Highlight(HighlightMode::Ripple),
+            "wireframe" => VfxMode::Highlight(HighlightMode::Wireframe),
+            "railgun" => VfxMode::Trail(TrailMode::Railgun),
+            "torpedo" => VfxMode::Trail(TrailMode::Torpedo),
+            "pixiedust" => VfxMode::Trail(TrailMode::PixieDust),
+            "smear" => VfxMode::Smear,
+            "moderipple" => VfxMode::ModeRipple,
+            "typewriter" => VfxMode::Typewriter,
+            "" => VfxMode::Disabled,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            VfxMode::Highlight(HighlightMode::SonicBoom) => "sonicboom",
+            VfxMode::Highlight(HighlightMode::Ripple) => "ripple",
+            VfxMode::Highlight(HighlightMode::Wireframe) => "wireframe",
+            VfxMode::Trail(TrailMode::Railgun) => "railgun",
+            VfxMode::Trail(TrailMode::Torpedo) => "torpedo",
+            VfxMode::Trail(TrailMode::PixieDust) => "pixiedust",
+            VfxMode::Smear => "smear",
+            VfxMode::ModeRipple => "moderipple",
+            VfxMode::Typewriter => "typewriter",
+            VfxMode::Disabled => "",
+        }
+    }
+}
+
+// Keys for choosing effects per editor mode, besides "default"
+const EDITOR_MODE_NAMES: &[&str] = &["normal", "insert", "visual", "replace", "cmdline"];
+
+fn editor_mode_name(mode: &EditorMode) -> Option<&'static str> {
+    match mode {
+        EditorMode::Normal => Some("normal"),
+        EditorMode::Insert => Some("insert"),
+        EditorMode::Visual => Some("visual"),
+        EditorMode::Replace => Some("replace"),
+        EditorMode::CmdLine => Some("cmdline"),
+        EditorMode::Unknown(_) => None,
+    }
+}
+
+/// The effects to show, either the same ones in every editor mode or chosen per mode. Several
+/// effects can be shown at once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VfxConfig {
+    default: Vec<VfxMode>,
+    per_mode: Vec<(String, Vec<VfxMode>)>,
+}
+
+impl VfxConfig {
+    pub fn modes_for(&self, mode: &EditorMode) -> &[VfxMode] {
+        editor_mode_name(mode)
+            .and_then(|name| {
+                self.per_mode
+                    .iter()
+                    .find(|(mode_name, _)| mode_name == name)
+            })
+            .map_or(&self.default, |(_, modes)| modes)
+    }
+}
+
+// Parses a single effect name or a list of them
+fn parse_vfx_modes(value: &Value) -> Option<Vec<VfxMode>> {
+    let modes = match value.as_array() {
+        Some(values) => values
+            .iter()
+            .map(|value| value.as_str().and_then(VfxMode::from_name))
+            .collect::<Option<Vec<_>>>()?,
+        None => vec![VfxMode::from_name(value.as_str()?)?],
+    };
+
+    Some(
+        modes
+            .into_iter()
+            .filter(|mode| *mode != VfxMode::Disabled)
+            .collect(),
+    )
+}
+
+fn vfx_modes_to_value(modes: &[VfxMode]) -> Value {
+    match modes {
+        [] => Value::from(""),
+        [mode] => Value::from(mode.name()),
+        modes => Value::from(
+            modes
+                .iter()
+                .map(|mode| Value::from(mode.name()))
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+impl ParseFromValue for VfxConfig {
     fn parse_from_value(&mut self, value: Value) {
-        if value.is_str() {
-            *self = match value.as_str().unwrap() {
-                "sonicboom" => VfxMode::Highlight(HighlightMode::SonicBoom),
-                "ripple" => VfxMode::Highlight(HighlightMode::Ripple),
-                "wireframe" => VfxMode::Highlight(HighlightMode::Wireframe),
-                "railgun" => VfxMode::Trail(TrailMode::Railgun),
-                "torpedo" => VfxMode::Trail(TrailMode::Torpedo),
-                "pixiedust" => VfxMode::Trail(TrailMode::PixieDust),
-                "" => VfxMode::Disabled,
-                value => {
-                    error!("Expected a VfxMode name, but received {:?}", value);
-                    return;
+        if let Some(entries) = value.as_map() {
+            let mut config = VfxConfig::default();
+            for (key, modes) in entries {
+                let key = match key.as_str() {
+                    Some(key) if key == "default" || EDITOR_MODE_NAMES.contains(&key) => key,
+                    _ => {
+                        error!("Expected an editor mode name, but received {:?}", key);
+                        return;
+                    }
+                };
+                let modes = match parse_vfx_modes(modes) {
+                    Some(modes) => modes,
+                    None => {
+                        error!("Expected VfxMode names, but received {:?}", modes);
+                        return;
+                    }
+                };
+
+                if key == "default" {
+                    config.default = modes;
+                } else {
+                    config.per_mode.push((key.to_owned(), modes));
                 }
-            };
+            }
+            *self = config;
         } else {
-            error!("Expected a VfxMode string, but received {:?}", value);
+            match parse_vfx_modes(&value) {
+                Some(modes) => {
+                    *self = VfxConfig {
+                        default: modes,
+                        per_mode: Vec::new(),
+                    }
+                }
+                None => error!(
+                    "Expected a VfxMode name, list or dictionary, but received {:?}",
+                    value
+                ),
+            }
         }
     }
 }
 
-impl From<VfxMode> for Value {
-    fn from(mode: VfxMode) -> Self {
-        match mode {
-            VfxMode::Highlight(HighlightMode::SonicBoom) => Value::from("sonicboom"),
-            VfxMode::Highlight(HighlightMode::Ripple) => Value::from("ripple"),
-            VfxMode::Highlight(HighlightMode::Wireframe) => Value::from("wireframe"),
-            VfxMode::Trail(TrailMode::Railgun) => Value::from("railgun"),
-            VfxMode::Trail(TrailMode::Torpedo) => Value::from("torpedo"),
-            VfxMode::Trail(TrailMode::PixieDust) => Value::from("pixiedust"),
-            VfxMode::Disabled => Value::from(""),
+impl From<VfxConfig> for Value {
+    fn from(config: VfxConfig) -> Self {
+        if config.per_mode.is_empty() {
+            return vfx_modes_to_value(&config.default);
         }
+
+        let mut entries = vec![(Value::from("default"), vfx_modes_to_value(&config.default))];
+        entries.extend(
+            config
+                .per_mode
+                .iter()
+                .map(|(name, modes)| (Value::from(name.as_str()), vfx_modes_to_value(modes))),
+        );
+        Value::Map(entries)
     }
 }
 
-pub fn new_cursor_vfx(mode: &VfxMode) -> Option<Box<dyn CursorVfx>> {
+fn new_effect(mode: &VfxMode) -> Option<Box<dyn CursorVfx>> {
     match mode {
         VfxMode::Highlight(mode) => Some(Box::new(PointHighlight::new(mode))),
         VfxMode::Trail(mode) => Some(Box::new(ParticleTrail::new(mode))),
+        VfxMode::Smear => Some(Box::new(Smear::new())),
+        VfxMode::ModeRipple => Some(Box::new(ModeRipple::new())),
+        VfxMode::Typewriter => Some(Box::new(Typewriter::new())),
         VfxMode::Disabled => None,
     }
 }
 
+pub fn new_cursor_vfx(modes: &[VfxMode]) -> Option<Box<dyn CursorVfx>> {
+    let mut effects: Vec<_> = modes.iter().filter_map(new_effect).collect();
+    if effects.len() > 1 {
+        let mut composite = CompositeVfx::default();
+        for effect in effects {
+            composite.push(effect);
+        }
+        Some(Box::new(composite))
+    } else {
+        effects.pop()
+    }
+}
+
+/// Shows several effects at once.
+#[derive(Default)]
+pub struct CompositeVfx {
+    effects: Vec<Box<dyn CursorVfx>>,
+}
+
+impl CompositeVfx {
+    pub fn push(&mut self, effect: Box<dyn CursorVfx>) {
+        self.effects.push(effect);
+    }
+}
+
+impl CursorVfx for CompositeVfx {
+    fn update(
+        &mut self,
+        settings: &CursorSettings,
+        current_cursor_destination: Point,
+        cursor_dimensions: Point,
+        dt: f32,
+    ) -> bool {
+        let mut animating = false;
+        for effect in self.effects.iter_mut() {
+            animating |= effect.update(settings, current_cursor_destination, cursor_dimensions, dt);
+        }
+        animating
+    }
+
+    fn restart(&mut self, position: Point) {
+        for effect in self.effects.iter_mut() {
+            effect.restart(position);
+        }
+    }
+
+    fn mode_changed(&mut self, position: Point) {
+        for effect in self.effects.iter_mut() {
+            effect.mode_changed(position);
+        }
+    }
+
+    fn render(
+        &self,
+        settings: &CursorSettings,
+        canvas: &mut Canvas,
+        grid_renderer: &mut GridRenderer,
+        cursor: &Cursor,
+    ) {
+        for effect in self.effects.iter() {
+            effect.render(settings, canvas, grid_renderer, cursor);
+        }
+    }
+}
+
 pub struct PointHighlight {
     t: f32,
     center_position: Point,
@@ -141,7 +335,7 @@ impl CursorVfx for PointHighlight {
 
         let colors = &grid_renderer.default_style.colors;
         let base_color: Color = cursor.background(colors).to_color();
-        let alpha = ease(ease_in_quad, settings.vfx_opacity, 0.0, self.t) as u8;
+        let alpha = settings.vfx_easing.ease(settings.vfx_opacity, 0.0, self.t) as u8;
         let color = Color::from_argb(alpha, base_color.r(), base_color.g(), base_color.b());
 
         paint.set_color(color);
@@ -175,7 +369,233 @@ impl CursorVfx for PointHighlight {
     }
 }
 
-#[derive(Clone)]
+/// A ripple that only starts when the editor changes modes, not on every cursor shape change.
+pub struct ModeRipple {
+    highlight: PointHighlight,
+}
+
+impl ModeRipple {
+    pub fn new() -> ModeRipple {
+        ModeRipple {
+            highlight: PointHighlight {
+                t: 1.0,
+                center_position: Point::new(0.0, 0.0),
+                mode: HighlightMode::Ripple,
+            },
+        }
+    }
+}
+
+impl CursorVfx for ModeRipple {
+    fn update(
+        &mut self,
+        settings: &CursorSettings,
+        current_cursor_destination: Point,
+        cursor_dimensions: Point,
+        dt: f32,
+    ) -> bool {
+        self.highlight
+            .update(settings, current_cursor_destination, cursor_dimensions, dt)
+    }
+
+    fn restart(&mut self, _position: Point) {}
+
+    fn mode_changed(&mut self, position: Point) {
+        self.highlight.restart(position);
+    }
+
+    fn render(
+        &self,
+        settings: &CursorSettings,
+        canvas: &mut Canvas,
+        grid_renderer: &mut GridRenderer,
+        cursor: &Cursor,
+    ) {
+        self.highlight
+            .render(settings, canvas, grid_renderer, cursor);
+    }
+}
+
+// How long the afterimages of the smear stay visible, in seconds
+const SMEAR_LIFETIME: f32 = 0.15;
+const MAX_SMEAR_IMAGES: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Afterimage {
+    rect: Rect,
+    age: f32,
+}
+
+/// Leaves fading copies of the cursor along the way it moved.
+pub struct Smear {
+    afterimages: Vec<Afterimage>,
+    previous_cursor_dest: Option<Point>,
+}
+
+impl Smear {
+    pub fn new() -> Smear {
+        Smear {
+            afterimages: Vec::new(),
+            previous_cursor_dest: None,
+        }
+    }
+}
+
+impl CursorVfx for Smear {
+    fn update(
+        &mut self,
+        _settings: &CursorSettings,
+        current_cursor_dest: Point,
+        cursor_dimensions: Point,
+        dt: f32,
+    ) -> bool {
+        for afterimage in self.afterimages.iter_mut() {
+            afterimage.age += dt;
+        }
+        self.afterimages
+            .retain(|afterimage| afterimage.age < SMEAR_LIFETIME);
+
+        if let Some(previous_cursor_dest) = self.previous_cursor_dest {
+            if current_cursor_dest != previous_cursor_dest {
+                let travel = current_cursor_dest - previous_cursor_dest;
+                let count = ((travel.length() / cursor_dimensions.x.max(1.0)).ceil() as usize)
+                    .min(MAX_SMEAR_IMAGES);
+
+                // The images closer to where the cursor started are older, so the smear shrinks
+                // towards the cursor
+                for i in 0..count {
+                    let t = i as f32 / count as f32;
+                    let center = previous_cursor_dest + travel * t;
+                    self.afterimages.push(Afterimage {
+                        rect: Rect::from_point_and_size(
+                            center - cursor_dimensions * 0.5,
+                            (cursor_dimensions.x, cursor_dimensions.y),
+                        ),
+                        age: (1.0 - t) * SMEAR_LIFETIME * 0.5,
+                    });
+                }
+            }
+        }
+        self.previous_cursor_dest = Some(current_cursor_dest);
+
+        !self.afterimages.is_empty()
+    }
+
+    fn restart(&mut self, _position: Point) {}
+
+    fn render(
+        &self,
+        settings: &CursorSettings,
+        canvas: &mut Canvas,
+        grid_renderer: &mut GridRenderer,
+        cursor: &Cursor,
+    ) {
+        let mut paint = Paint::new(skia_safe::colors::WHITE, None);
+        paint.set_blend_mode(BlendMode::SrcOver);
+
+        let colors = &grid_renderer.default_style.colors;
+        let base_color: Color = cursor.background(colors).to_color();
+
+        for afterimage in self.afterimages.iter() {
+            let t = afterimage.age / SMEAR_LIFETIME;
+            let alpha = settings.vfx_easing.ease(settings.vfx_opacity, 0.0, t) as u8;
+            paint.set_color(Color::from_argb(
+                alpha,
+                base_color.r(),
+                base_color.g(),
+                base_color.b(),
+            ));
+            canvas.draw_rect(afterimage.rect, &paint);
+        }
+    }
+}
+
+// How long a bounce of the typewriter effect lasts, in seconds
+const TYPEWRITER_BOUNCE_LENGTH: f32 = 0.3;
+// How high the cursor jumps, relative to its height
+const TYPEWRITER_BOUNCE_HEIGHT: f32 = 0.25;
+
+/// Makes a copy of the cursor hop up and settle again every time the cursor moves, like the
+/// carriage of a typewriter.
+pub struct Typewriter {
+    t: f32,
+    previous_cursor_dest: Option<Point>,
+    cursor_dimensions: Point,
+}
+
+impl Typewriter {
+    pub fn new() -> Typewriter {
+        Typewriter {
+            t: 1.0,
+            previous_cursor_dest: None,
+            cursor_dimensions: Point::new(0.0, 0.0),
+        }
+    }
+
+    /// Vertical offset of the bouncing copy, negative while above the cursor.
+    fn offset(&self) -> f32 {
+        let settle = ease_spring(0.3, 2.0, self.t);
+        -(1.0 - settle) * TYPEWRITER_BOUNCE_HEIGHT * self.cursor_dimensions.y
+    }
+}
+
+impl CursorVfx for Typewriter {
+    fn update(
+        &mut self,
+        _settings: &CursorSettings,
+        current_cursor_dest: Point,
+        cursor_dimensions: Point,
+        dt: f32,
+    ) -> bool {
+        self.cursor_dimensions = cursor_dimensions;
+
+        match self.previous_cursor_dest {
+            Some(previous_cursor_dest) if previous_cursor_dest != current_cursor_dest => {
+                self.t = 0.0;
+            }
+            _ => self.t = (self.t + dt / TYPEWRITER_BOUNCE_LENGTH).min(1.0),
+        }
+        self.previous_cursor_dest = Some(current_cursor_dest);
+
+        self.t < 1.0
+    }
+
+    fn restart(&mut self, _position: Point) {}
+
+    fn render(
+        &self,
+        settings: &CursorSettings,
+        canvas: &mut Canvas,
+        grid_renderer: &mut GridRenderer,
+        cursor: &Cursor,
+    ) {
+        let center = match self.previous_cursor_dest {
+            Some(center) if self.t < 1.0 => center,
+            _ => return,
+        };
+
+        let mut paint = Paint::new(skia_safe::colors::WHITE, None);
+        paint.set_blend_mode(BlendMode::SrcOver);
+
+        let colors = &grid_renderer.default_style.colors;
+        let base_color: Color = cursor.background(colors).to_color();
+        let alpha = settings.vfx_easing.ease(settings.vfx_opacity, 0.0, self.t) as u8;
+        paint.set_color(Color::from_argb(
+            alpha,
+            base_color.r(),
+            base_color.g(),
+            base_color.b(),
+        ));
+
+        let rect = Rect::from_point_and_size(
+            center - self.cursor_dimensions * 0.5 + Point::new(0.0, self.offset()),
+            (self.cursor_dimensions.x, self.cursor_dimensions.y),
+        );
+        canvas.draw_rect(rect, &paint);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 struct ParticleData {
     pos: Point,
     speed: Point,
@@ -192,11 +612,15 @@ pub struct ParticleTrail {
 
 impl ParticleTrail {
     pub fn new(trail_mode: &TrailMode) -> ParticleTrail {
+        ParticleTrail::with_rng(trail_mode, RngState::new())
+    }
+
+    fn with_rng(trail_mode: &TrailMode, rng: RngState) -> ParticleTrail {
         ParticleTrail {
             particles: vec![],
             previous_cursor_dest: Point::new(0.0, 0.0),
             trail_mode: trail_mode.clone(),
-            rng: RngState::new(),
+            rng,
         }
     }
 
@@ -378,6 +802,19 @@ impl RngState {
             inc: (0xDA3E_39CB_94B9_5BDBu64 << 1) | 1,
         }
     }
+
+    // Seeding as done by the reference implementation
+    #[cfg(test)]
+    fn with_seed(seed: u64) -> RngState {
+        let mut rng = RngState {
+            state: 0,
+            inc: (0xDA3E_39CB_94B9_5BDBu64 << 1) | 1,
+        };
+        rng.next();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next();
+        rng
+    }
     fn next(&mut self) -> u32 {
         let old_state = self.state;
 
@@ -440,3 +877,196 @@ fn rotate_vec(v: Point, rot: f32) -> Point {
 
     Point::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURSOR_DIMENSIONS: Point = Point { x: 10.0, y: 20.0 };
+
+    fn settings() -> CursorSettings {
+        CursorSettings::default()
+    }
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let mut first = RngState::with_seed(42);
+        let mut second = RngState::with_seed(42);
+        let mut other = RngState::with_seed(7);
+
+        let first_values: Vec<u32> = (0..8).map(|_| first.next()).collect();
+        let second_values: Vec<u32> = (0..8).map(|_| second.next()).collect();
+        let other_values: Vec<u32> = (0..8).map(|_| other.next()).collect();
+        assert_eq!(first_values, second_values);
+        assert_ne!(first_values, other_values);
+
+        let mut rng = RngState::new();
+        for _ in 0..1000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_trail_with_the_same_seed_spawns_the_same_particles() {
+        let mut first = ParticleTrail::with_rng(&TrailMode::PixieDust, RngState::with_seed(3));
+        let mut second = ParticleTrail::with_rng(&TrailMode::PixieDust, RngState::with_seed(3));
+        let destination = Point::new(400.0, 0.0);
+
+        assert!(first.update(&settings(), destination, CURSOR_DIMENSIONS, 0.016));
+        assert!(second.update(&settings(), destination, CURSOR_DIMENSIONS, 0.016));
+        assert!(!first.particles.is_empty());
+        assert_eq!(first.particles, second.particles);
+
+        // Pixiedust spawns somewhere along the travelled distance, half a cell down
+        for particle in first.particles.iter() {
+            assert!((0.0..400.0).contains(&particle.pos.x));
+            assert_eq!(particle.pos.y, 10.0);
+        }
+    }
+
+    #[test]
+    fn test_trail_particles_die() {
+        let mut trail = ParticleTrail::with_rng(&TrailMode::Torpedo, RngState::with_seed(1));
+        let destination = Point::new(400.0, 0.0);
+
+        trail.update(&settings(), destination, CURSOR_DIMENSIONS, 0.016);
+        let lifetime = settings().vfx_particle_lifetime;
+        assert!(!trail.update(&settings(), destination, CURSOR_DIMENSIONS, lifetime));
+        assert!(trail.particles.is_empty());
+    }
+
+    #[test]
+    fn test_parse_vfx_config() {
+        let mut config = VfxConfig::default();
+
+        config.parse_from_value(Value::from("railgun"));
+        assert_eq!(
+            config.modes_for(&EditorMode::Insert),
+            &[VfxMode::Trail(TrailMode::Railgun)]
+        );
+
+        config.parse_from_value(Value::from(vec![
+            Value::from("smear"),
+            Value::from("moderipple"),
+        ]));
+        assert_eq!(
+            config.modes_for(&EditorMode::Normal),
+            &[VfxMode::Smear, VfxMode::ModeRipple]
+        );
+
+        config.parse_from_value(Value::Map(vec![
+            (Value::from("default"), Value::from("moderipple")),
+            (Value::from("insert"), Value::from("typewriter")),
+        ]));
+        assert_eq!(
+            config.modes_for(&EditorMode::Insert),
+            &[VfxMode::Typewriter]
+        );
+        assert_eq!(
+            config.modes_for(&EditorMode::Visual),
+            &[VfxMode::ModeRipple]
+        );
+        assert_eq!(
+            config.modes_for(&EditorMode::Unknown("terminal".to_owned())),
+            &[VfxMode::ModeRipple]
+        );
+
+        // Invalid values keep the previous config
+        let previous = config.clone();
+        config.parse_from_value(Value::from("fireworks"));
+        config.parse_from_value(Value::Map(vec![(
+            Value::from("operator"),
+            Value::from("smear"),
+        )]));
+        assert_eq!(config, previous);
+
+        config.parse_from_value(Value::from(""));
+        assert!(config.modes_for(&EditorMode::Normal).is_empty());
+    }
+
+    #[test]
+    fn test_vfx_config_round_trip() {
+        let mut config = VfxConfig::default();
+        for value in [
+            Value::from("pixiedust"),
+            Value::from(vec![Value::from("smear"), Value::from("typewriter")]),
+            Value::Map(vec![
+                (Value::from("default"), Value::from("")),
+                (Value::from("cmdline"), Value::from("sonicboom")),
+            ]),
+        ] {
+            config.parse_from_value(value.clone());
+            assert_eq!(Value::from(config.clone()), value);
+        }
+    }
+
+    #[test]
+    fn test_new_cursor_vfx_composes_effects() {
+        assert!(new_cursor_vfx(&[]).is_none());
+        assert!(new_cursor_vfx(&[VfxMode::Disabled]).is_none());
+
+        let mut composite = CompositeVfx::default();
+        composite.push(Box::new(ModeRipple::new()));
+        composite.push(Box::new(Typewriter::new()));
+
+        let origin = Point::new(0.0, 0.0);
+        assert!(!composite.update(&settings(), origin, CURSOR_DIMENSIONS, 0.016));
+
+        // Either effect animating keeps the composite animating
+        composite.mode_changed(origin);
+        assert!(composite.update(&settings(), origin, CURSOR_DIMENSIONS, 0.016));
+    }
+
+    #[test]
+    fn test_mode_ripple_ignores_shape_changes() {
+        let mut ripple = ModeRipple::new();
+        let origin = Point::new(0.0, 0.0);
+
+        ripple.restart(origin);
+        assert!(!ripple.update(&settings(), origin, CURSOR_DIMENSIONS, 0.016));
+
+        ripple.mode_changed(origin);
+        assert!(ripple.update(&settings(), origin, CURSOR_DIMENSIONS, 0.016));
+        assert!(!ripple.update(&settings(), origin, CURSOR_DIMENSIONS, 1.0));
+    }
+
+    #[test]
+    fn test_smear_leaves_fading_afterimages() {
+        let mut smear = Smear::new();
+
+        assert!(!smear.update(&settings(), Point::new(0.0, 0.0), CURSOR_DIMENSIONS, 0.016));
+        assert!(smear.update(&settings(), Point::new(40.0, 0.0), CURSOR_DIMENSIONS, 0.016));
+        assert_eq!(smear.afterimages.len(), 4);
+        assert_eq!(
+            smear.afterimages[0].rect,
+            Rect::from_xywh(-5.0, -10.0, 10.0, 20.0)
+        );
+
+        assert!(!smear.update(
+            &settings(),
+            Point::new(40.0, 0.0),
+            CURSOR_DIMENSIONS,
+            SMEAR_LIFETIME
+        ));
+        assert!(smear.afterimages.is_empty());
+    }
+
+    #[test]
+    fn test_typewriter_bounces_and_settles() {
+        let mut typewriter = Typewriter::new();
+
+        typewriter.update(&settings(), Point::new(0.0, 0.0), CURSOR_DIMENSIONS, 0.016);
+        assert!(typewriter.update(&settings(), Point::new(10.0, 0.0), CURSOR_DIMENSIONS, 0.016));
+        assert!(typewriter.offset() < 0.0);
+        assert!(typewriter.offset() >= -TYPEWRITER_BOUNCE_HEIGHT * CURSOR_DIMENSIONS.y);
+
+        assert!(!typewriter.update(
+            &settings(),
+            Point::new(10.0, 0.0),
+            CURSOR_DIMENSIONS,
+            TYPEWRITER_BOUNCE_LENGTH
+        ));
+        assert_eq!(typewriter.offset(), 0.0);
+    }
+}
@@ -1,13 +1,14 @@
 mod blink;
 mod cursor_vfx;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use glutin::event::{Event, WindowEvent};
-use skia_safe::{op, Canvas, Paint, Path, Point};
+use skia_safe::{op, Canvas, Paint, Path, Point, Rect};
 
 use crate::{
     bridge::EditorMode,
+    dimensions::Dimensions,
     editor::{Cursor, CursorShape},
     redraw_scheduler::REDRAW_SCHEDULER,
     renderer::animation_utils::*,
@@ -27,6 +28,7 @@ const STANDARD_CORNERS: &[(f32, f32); 4] = &[(-0.5, -0.5), (0.5, -0.5), (0.5, 0.
 pub struct CursorSettings {
     antialiasing: bool,
     animation_length: f32,
+    animation_easing: Easing,
     distance_length_adjust: bool,
     animate_in_insert_mode: bool,
     animate_command_line: bool,
@@ -38,7 +40,16 @@ pub struct CursorSettings {
     /// outline instead of as a full rectangle.
     unfocused_outline_width: f32,
 
-    vfx_mode: cursor_vfx::VfxMode,
+    smooth_blink: bool,
+    blink_easing: Easing,
+    /// Seconds without the cursor moving after which it stops blinking and stays visible, or 0
+    /// to blink forever.
+    blink_idle_timeout: f32,
+
+    secondary_opacity: f32,
+
+    vfx_mode: cursor_vfx::VfxConfig,
+    vfx_easing: Easing,
     vfx_opacity: f32,
     vfx_particle_lifetime: f32,
     vfx_particle_density: f32,
@@ -52,12 +63,18 @@ impl Default for CursorSettings {
         CursorSettings {
             antialiasing: true,
             animation_length: 0.06,
+            animation_easing: Easing::OutExpo,
             distance_length_adjust: true,
             animate_in_insert_mode: true,
             animate_command_line: true,
             trail_size: 0.7,
             unfocused_outline_width: 1.0 / 8.0,
-            vfx_mode: cursor_vfx::VfxMode::Disabled,
+            smooth_blink: false,
+            blink_easing: Easing::InOutQuad,
+            blink_idle_timeout: 0.0,
+            secondary_opacity: 0.5,
+            vfx_mode: cursor_vfx::VfxConfig::default(),
+            vfx_easing: Easing::InQuad,
             vfx_opacity: 200.0,
             vfx_particle_lifetime: 1.2,
             vfx_particle_density: 7.0,
@@ -68,6 +85,54 @@ impl Default for CursorSettings {
     }
 }
 
+// Where a corner of the cursor rests relative to the center of the cells it covers, in fractions
+// of the cursor dimensions
+fn relative_corner(cursor_shape: &CursorShape, cell_percentage: f32, (x, y): (f32, f32)) -> Point {
+    match cursor_shape {
+        CursorShape::Block => (x, y).into(),
+        // Transform the x position so that the right side is translated over to the BAR_WIDTH
+        // position
+        CursorShape::Vertical => ((x + 0.5) * cell_percentage - 0.5, y).into(),
+        // Do the same as above, but flip the y coordinate and then flip the result so that the
+        // horizontal bar is at the bottom of the character space instead of the top.
+        CursorShape::Horizontal => (x, -((-y + 0.5) * cell_percentage - 0.5)).into(),
+    }
+}
+
+// Block and horizontal cursors cover every cell of a wide character, while vertical bars keep a
+// percentage of a single cell's width
+fn cursor_dimensions(
+    cursor_shape: &CursorShape,
+    cell_width: u64,
+    font_dimensions: Dimensions,
+) -> Point {
+    let cells = match cursor_shape {
+        CursorShape::Vertical => 1,
+        CursorShape::Block | CursorShape::Horizontal => cell_width.max(1),
+    };
+
+    (
+        (font_dimensions.width * cells) as f32,
+        font_dimensions.height as f32,
+    )
+        .into()
+}
+
+// Draws the glyph under the cursor clipped to the cursor's path, so it shows in the cursor's
+// foreground color wherever the cursor covers it and leaves the rest of the cell untouched. The
+// clip is antialiased like the cursor itself, so their edges line up while animating.
+fn draw_clipped_glyph(
+    canvas: &mut Canvas,
+    cursor_path: &Path,
+    antialias: bool,
+    draw_glyph: impl FnOnce(&mut Canvas),
+) {
+    canvas.save();
+    canvas.clip_path(cursor_path, None, Some(antialias));
+    draw_glyph(canvas);
+    canvas.restore();
+}
+
 #[derive(Debug, Clone)]
 pub struct Corner {
     start_position: Point,
@@ -163,27 +228,103 @@ impl Corner {
                 (self.t + corner_dt / (settings.animation_length * self.length_multiplier)).min(1.0)
         }
 
-        self.current_position = ease_point(
-            ease_out_expo,
-            self.start_position,
-            corner_destination,
-            self.t,
-        );
+        self.current_position =
+            settings
+                .animation_easing
+                .ease_point(self.start_position, corner_destination, self.t);
 
         true
     }
 }
 
+/// Where the content of a cursor's window currently is on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ContentAnchor {
+    // Screen position of the window's content, scroll offset included
+    offset: Point,
+    // The window's region, which the cursor is clipped to while its content scrolls
+    clip: Rect,
+}
+
+/// The parts of a window's state that place its content on screen, in grid cells.
+#[derive(Clone, Copy, Debug)]
+struct ContentLayout {
+    position: Point,
+    size: Dimensions,
+    // The lines skipped by viewport jumps are taken out of the content, so that the cursor only
+    // travels the distance the scroll animation shows instead of the whole jump
+    top_line: f32,
+    scroll: f32,
+}
+
+impl From<&RenderedWindow> for ContentLayout {
+    fn from(window: &RenderedWindow) -> ContentLayout {
+        let jumped_rows = window.jumped_rows as f32;
+
+        ContentLayout {
+            position: window.grid_current_position,
+            size: window.grid_size,
+            top_line: window.current_surface.top_line as f32 - jumped_rows,
+            scroll: window.current_scroll - jumped_rows,
+        }
+    }
+}
+
+// Splits a grid position into its place within the window's scrolled content and the anchor of
+// that content on screen, both in pixels. Only the former is animated by the cursor, so the cursor
+// moves along with scrolling and window animations instead of lagging behind its text.
+fn locate_in_content(
+    layout: ContentLayout,
+    (grid_x, grid_y): (u64, u64),
+    font_dimensions: Point,
+) -> (Point, ContentAnchor) {
+    let content_position = Point::new(
+        grid_x as f32 * font_dimensions.x,
+        (grid_y as f32 + layout.top_line) * font_dimensions.y,
+    );
+    let window_position = Point::new(
+        layout.position.x * font_dimensions.x,
+        layout.position.y * font_dimensions.y,
+    );
+    let anchor = ContentAnchor {
+        offset: window_position - Point::new(0.0, layout.scroll * font_dimensions.y),
+        clip: Rect::from_point_and_size(
+            window_position,
+            (
+                layout.size.width as f32 * font_dimensions.x,
+                layout.size.height as f32 * font_dimensions.y,
+            ),
+        ),
+    };
+
+    (content_position, anchor)
+}
+
+/// An extra cursor registered by a plugin, animated like the primary one.
+struct SecondaryCursor {
+    grid_position: (u64, u64),
+    destination: Option<(Point, ContentAnchor)>,
+    corners: Vec<Corner>,
+    // New cursors appear in place instead of moving in from wherever their corners start
+    placed: bool,
+}
+
 pub struct CursorRenderer {
     pub corners: Vec<Corner>,
     cursor: Cursor,
+    // Relative to the anchor of the cursor's window
     destination: Point,
+    anchor: Option<ContentAnchor>,
+    anchor_window_id: u64,
     blink_status: BlinkStatus,
-    previous_cursor_shape: Option<CursorShape>,
+    previous_cursor_shape: Option<(CursorShape, f32)>,
     previous_editor_mode: EditorMode,
     cursor_vfx: Option<Box<dyn cursor_vfx::CursorVfx>>,
-    previous_vfx_mode: cursor_vfx::VfxMode,
+    previous_vfx_modes: Vec<cursor_vfx::VfxMode>,
+    previous_vfx_editor_mode: EditorMode,
     window_has_focus: bool,
+    // Keyed by grid id
+    secondary_cursors: HashMap<u64, Vec<SecondaryCursor>>,
 }
 
 impl CursorRenderer {
@@ -192,12 +333,16 @@ impl CursorRenderer {
             corners: vec![Corner::new(); 4],
             cursor: Cursor::new(),
             destination: (0.0, 0.0).into(),
-            blink_status: BlinkStatus::new(),
+            anchor: None,
+            anchor_window_id: 0,
+            blink_status: BlinkStatus::new(Instant::now()),
             previous_cursor_shape: None,
             previous_editor_mode: EditorMode::Normal,
             cursor_vfx: None,
-            previous_vfx_mode: cursor_vfx::VfxMode::Disabled,
+            previous_vfx_modes: Vec::new(),
+            previous_vfx_editor_mode: EditorMode::Normal,
             window_has_focus: true,
+            secondary_cursors: HashMap::new(),
         };
         renderer.set_cursor_shape(&CursorShape::Block, DEFAULT_CELL_PERCENTAGE);
         renderer
@@ -213,36 +358,56 @@ impl CursorRenderer {
         }
     }
 
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+
     pub fn update_cursor(&mut self, new_cursor: Cursor) {
         self.cursor = new_cursor;
     }
 
+    /// Replaces the secondary cursors of a grid. Cursors are matched with the previous ones by
+    /// their order, so that those that moved animate to their new position.
+    pub fn set_secondary_cursors(&mut self, grid: u64, positions: Vec<(u64, u64)>) {
+        if positions.is_empty() {
+            self.secondary_cursors.remove(&grid);
+            return;
+        }
+
+        let cursors = self.secondary_cursors.entry(grid).or_default();
+        cursors.truncate(positions.len());
+        for (index, grid_position) in positions.into_iter().enumerate() {
+            match cursors.get_mut(index) {
+                Some(cursor) => cursor.grid_position = grid_position,
+                None => cursors.push(SecondaryCursor {
+                    grid_position,
+                    destination: None,
+                    corners: self.corners.clone(),
+                    placed: false,
+                }),
+            }
+        }
+    }
+
+    pub fn remove_secondary_cursors(&mut self, grid: u64) {
+        self.secondary_cursors.remove(&grid);
+    }
+
     fn set_cursor_shape(&mut self, cursor_shape: &CursorShape, cell_percentage: f32) {
         self.corners = self
             .corners
             .clone()
             .into_iter()
             .enumerate()
-            .map(|(i, corner)| {
-                let (x, y) = STANDARD_CORNERS[i];
-
-                Corner {
-                    relative_position: match cursor_shape {
-                        CursorShape::Block => (x, y).into(),
-                        // Transform the x position so that the right side is translated over to
-                        // the BAR_WIDTH position
-                        CursorShape::Vertical => ((x + 0.5) * cell_percentage - 0.5, y).into(),
-                        // Do the same as above, but flip the y coordinate and then flip the result
-                        // so that the horizontal bar is at the bottom of the character space
-                        // instead of the top.
-                        CursorShape::Horizontal => {
-                            (x, -((-y + 0.5) * cell_percentage - 0.5)).into()
-                        }
-                    },
-                    t: 0.0,
-                    start_position: corner.current_position,
-                    ..corner
-                }
+            .map(|(i, corner)| Corner {
+                relative_position: relative_corner(
+                    cursor_shape,
+                    cell_percentage,
+                    STANDARD_CORNERS[i],
+                ),
+                t: 0.0,
+                start_position: corner.current_position,
+                ..corner
             })
             .collect::<Vec<Corner>>();
     }
@@ -252,27 +417,49 @@ impl CursorRenderer {
         (font_width, font_height): (u64, u64),
         windows: &HashMap<u64, RenderedWindow>,
     ) {
+        let font_dimensions = Point::new(font_width as f32, font_height as f32);
         let (cursor_grid_x, cursor_grid_y) = self.cursor.grid_position;
 
-        if let Some(window) = windows.get(&self.cursor.parent_window_id) {
-            let grid_x = cursor_grid_x as f32 + window.grid_current_position.x;
-            let mut grid_y = cursor_grid_y as f32 + window.grid_current_position.y
-                - (window.current_scroll - window.current_surface.top_line as f32);
+        let (destination, anchor) = match windows.get(&self.cursor.parent_window_id) {
+            Some(window) => {
+                let (destination, anchor) =
+                    locate_in_content(window.into(), self.cursor.grid_position, font_dimensions);
+                (destination, Some(anchor))
+            }
+            None => (
+                (
+                    (cursor_grid_x * font_width) as f32,
+                    (cursor_grid_y * font_height) as f32,
+                )
+                    .into(),
+                None,
+            ),
+        };
 
-            // Prevent the cursor from targeting a position outside its current window. Since only
-            // the vertical direction is effected by scrolling, we only have to clamp the vertical
-            // grid position.
-            grid_y = grid_y
-                .max(window.grid_current_position.y)
-                .min(window.grid_current_position.y + window.grid_size.height as f32 - 1.0);
+        // When the cursor moves to another window, its corners are moved into the new window's
+        // content so that the cursor animates over from where it was on screen
+        if self.anchor_window_id != self.cursor.parent_window_id {
+            let previous_offset = self
+                .anchor
+                .map_or(Point::new(0.0, 0.0), |anchor| anchor.offset);
+            let offset = anchor.map_or(Point::new(0.0, 0.0), |anchor| anchor.offset);
+            for corner in self.corners.iter_mut() {
+                corner.current_position += previous_offset - offset;
+                corner.start_position += previous_offset - offset;
+            }
+            self.anchor_window_id = self.cursor.parent_window_id;
+        }
 
-            self.destination = (grid_x * font_width as f32, grid_y * font_height as f32).into();
-        } else {
-            self.destination = (
-                (cursor_grid_x * font_width) as f32,
-                (cursor_grid_y * font_height) as f32,
-            )
-                .into();
+        self.destination = destination;
+        self.anchor = anchor;
+
+        for (grid, cursors) in self.secondary_cursors.iter_mut() {
+            let window = windows.get(grid).filter(|window| !window.hidden);
+            for cursor in cursors.iter_mut() {
+                cursor.destination = window.map(|window| {
+                    locate_in_content(window.into(), cursor.grid_position, font_dimensions)
+                });
+            }
         }
     }
 
@@ -283,12 +470,20 @@ impl CursorRenderer {
         canvas: &mut Canvas,
         dt: f32,
     ) {
-        let render = self.blink_status.update_status(&self.cursor);
         let settings = SETTINGS.get::<CursorSettings>();
+        let (blink_opacity, blink_redraw) =
+            self.blink_status
+                .update_status(&self.cursor, &settings, Instant::now());
+        match blink_redraw {
+            BlinkRedraw::Never => {}
+            BlinkRedraw::At(redraw) => REDRAW_SCHEDULER.schedule(redraw),
+            BlinkRedraw::NextFrame => REDRAW_SCHEDULER.queue_next_frame(),
+        }
 
-        if settings.vfx_mode != self.previous_vfx_mode {
-            self.cursor_vfx = cursor_vfx::new_cursor_vfx(&settings.vfx_mode);
-            self.previous_vfx_mode = settings.vfx_mode.clone();
+        let vfx_modes = settings.vfx_mode.modes_for(current_mode);
+        if vfx_modes != self.previous_vfx_modes.as_slice() {
+            self.cursor_vfx = cursor_vfx::new_cursor_vfx(vfx_modes);
+            self.previous_vfx_modes = vfx_modes.to_vec();
         }
 
         let mut paint = Paint::new(skia_safe::colors::WHITE, None);
@@ -296,16 +491,11 @@ impl CursorRenderer {
 
         let character = self.cursor.grid_cell.0.clone();
 
-        let mut cursor_width = grid_renderer.font_dimensions.width;
-        if self.cursor.double_width && self.cursor.shape == CursorShape::Block {
-            cursor_width *= 2;
-        }
-
-        let cursor_dimensions: Point = (
-            cursor_width as f32,
-            grid_renderer.font_dimensions.height as f32,
-        )
-            .into();
+        let cursor_dimensions = cursor_dimensions(
+            &self.cursor.shape,
+            self.cursor.cell_width,
+            grid_renderer.font_dimensions,
+        );
 
         let in_insert_mode = matches!(current_mode, EditorMode::Insert);
 
@@ -313,19 +503,28 @@ impl CursorRenderer {
             ^ matches!(current_mode, EditorMode::CmdLine);
 
         let center_destination = self.destination + cursor_dimensions * 0.5;
-        let new_cursor = Some(self.cursor.shape.clone());
+        let anchor_offset = self
+            .anchor
+            .map_or(Point::new(0.0, 0.0), |anchor| anchor.offset);
+        let cell_percentage = self
+            .cursor
+            .cell_percentage
+            .unwrap_or(DEFAULT_CELL_PERCENTAGE);
+        let new_cursor = Some((self.cursor.shape.clone(), cell_percentage));
 
         if self.previous_cursor_shape != new_cursor {
-            self.previous_cursor_shape = new_cursor.clone();
-            self.set_cursor_shape(
-                &new_cursor.unwrap(),
-                self.cursor
-                    .cell_percentage
-                    .unwrap_or(DEFAULT_CELL_PERCENTAGE),
-            );
+            self.previous_cursor_shape = new_cursor;
+            self.set_cursor_shape(&self.cursor.shape.clone(), cell_percentage);
+
+            if let Some(vfx) = self.cursor_vfx.as_mut() {
+                vfx.restart(center_destination + anchor_offset);
+            }
+        }
 
+        if self.previous_vfx_editor_mode != *current_mode {
+            self.previous_vfx_editor_mode = current_mode.clone();
             if let Some(vfx) = self.cursor_vfx.as_mut() {
-                vfx.restart(center_destination);
+                vfx.mode_changed(center_destination + anchor_offset);
             }
         }
 
@@ -348,7 +547,12 @@ impl CursorRenderer {
             }
 
             let vfx_animating = if let Some(vfx) = self.cursor_vfx.as_mut() {
-                vfx.update(&settings, center_destination, cursor_dimensions, dt)
+                vfx.update(
+                    &settings,
+                    center_destination + anchor_offset,
+                    cursor_dimensions,
+                    dt,
+                )
             } else {
                 false
             };
@@ -361,17 +565,25 @@ impl CursorRenderer {
         } else {
             self.previous_editor_mode = current_mode.clone();
         }
-        if !(self.cursor.enabled && render) {
+        if !self.cursor.enabled || blink_opacity <= 0.0 {
             return;
         }
+        let alpha = (self.cursor.alpha() as f32 * blink_opacity) as u8;
+
         // Draw Background
         let background_color = self
             .cursor
             .background(&grid_renderer.default_style.colors)
             .to_color()
-            .with_a(self.cursor.alpha());
+            .with_a(alpha);
         paint.set_color(background_color);
 
+        canvas.save();
+        if let Some(anchor) = self.anchor {
+            canvas.clip_rect(anchor.clip, None, Some(false));
+            canvas.translate(anchor.offset);
+        }
+
         let path = if self.window_has_focus || self.cursor.shape != CursorShape::Block {
             self.draw_rectangle(canvas, &paint)
         } else {
@@ -384,12 +596,9 @@ impl CursorRenderer {
             .cursor
             .foreground(&grid_renderer.default_style.colors)
             .to_color()
-            .with_a(self.cursor.alpha());
+            .with_a(alpha);
         paint.set_color(foreground_color);
 
-        canvas.save();
-        canvas.clip_path(&path, None, Some(false));
-
         let y_adjustment = grid_renderer.shaper.y_adjustment();
         let style = &self.cursor.grid_cell.1;
 
@@ -397,14 +606,13 @@ impl CursorRenderer {
         let italic = style.as_ref().map(|x| x.italic).unwrap_or(false);
 
         let blobs = &grid_renderer.shaper.shape_cached(character, bold, italic);
+        let glyph_position = (self.destination.x, self.destination.y + y_adjustment as f32);
 
-        for blob in blobs.iter() {
-            canvas.draw_text_blob(
-                &blob,
-                (self.destination.x, self.destination.y + y_adjustment as f32),
-                &paint,
-            );
-        }
+        draw_clipped_glyph(canvas, &path, settings.antialiasing, |canvas| {
+            for blob in blobs.iter() {
+                canvas.draw_text_blob(&blob, glyph_position, &paint);
+            }
+        });
 
         canvas.restore();
 
@@ -413,6 +621,79 @@ impl CursorRenderer {
         }
     }
 
+    /// Draws the secondary cursors of the grids this renderer shows, with the primary cursor's shape
+    /// and color at reduced opacity. Independent of `draw`, since the primary cursor may be in
+    /// another top-level window.
+    pub fn draw_secondary_cursors(
+        &mut self,
+        grid_renderer: &GridRenderer,
+        canvas: &mut Canvas,
+        dt: f32,
+    ) {
+        let settings = SETTINGS.get::<CursorSettings>();
+        let cursor_dimensions =
+            cursor_dimensions(&self.cursor.shape, 1, grid_renderer.font_dimensions);
+        let cell_percentage = self
+            .cursor
+            .cell_percentage
+            .unwrap_or(DEFAULT_CELL_PERCENTAGE);
+        let opacity = settings.secondary_opacity.max(0.0).min(1.0);
+        let color = self
+            .cursor
+            .background(&grid_renderer.default_style.colors)
+            .to_color()
+            .with_a((self.cursor.alpha() as f32 * opacity) as u8);
+
+        let mut paint = Paint::new(skia_safe::colors::WHITE, None);
+        paint.set_anti_alias(settings.antialiasing);
+        paint.set_color(color);
+
+        let mut animating = false;
+        for cursor in self.secondary_cursors.values_mut().flatten() {
+            let (destination, anchor) = match cursor.destination {
+                Some(destination) => destination,
+                None => continue,
+            };
+            let center_destination = destination + cursor_dimensions * 0.5;
+
+            for (corner, standard_corner) in cursor.corners.iter_mut().zip(STANDARD_CORNERS) {
+                // Follow shape changes of the primary cursor
+                let relative_position =
+                    relative_corner(&self.cursor.shape, cell_percentage, *standard_corner);
+                if corner.relative_position != relative_position {
+                    corner.relative_position = relative_position;
+                    corner.start_position = corner.current_position;
+                    corner.t = 0.0;
+                }
+
+                animating |= corner.update(
+                    &settings,
+                    cursor_dimensions,
+                    center_destination,
+                    dt,
+                    !cursor.placed,
+                );
+            }
+            cursor.placed = true;
+
+            let mut path = Path::new();
+            path.move_to(cursor.corners[0].current_position);
+            path.line_to(cursor.corners[1].current_position);
+            path.line_to(cursor.corners[2].current_position);
+            path.line_to(cursor.corners[3].current_position);
+            path.close();
+            canvas.save();
+            canvas.clip_rect(anchor.clip, None, Some(false));
+            canvas.translate(anchor.offset);
+            canvas.draw_path(&path, &paint);
+            canvas.restore();
+        }
+
+        if animating {
+            REDRAW_SCHEDULER.queue_next_frame();
+        }
+    }
+
     fn draw_rectangle(&self, canvas: &mut Canvas, paint: &Paint) -> Path {
         // The cursor is made up of four points, so I create a path with each of the four
         // corners.
@@ -463,3 +744,218 @@ impl CursorRenderer {
         path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skia_safe::{image::CachingHint, AlphaType, Color, ColorType, IPoint, ImageInfo, Surface};
+
+    const FONT_DIMENSIONS: Dimensions = Dimensions {
+        width: 10,
+        height: 20,
+    };
+    const BACKGROUND: Color = Color::BLACK;
+    const CURSOR: Color = Color::WHITE;
+    const GLYPH: Color = Color::RED;
+
+    // Draws a cursor resting on the top left cell of a three cell wide surface, with a stand-in
+    // glyph that covers the whole character
+    fn render_cursor(shape: CursorShape, cell_percentage: f32, cell_width: u64) -> Vec<u8> {
+        let dimensions = cursor_dimensions(&shape, cell_width, FONT_DIMENSIONS);
+        let center = dimensions * 0.5;
+
+        let mut path = Path::new();
+        for (i, corner) in STANDARD_CORNERS.iter().enumerate() {
+            let relative = relative_corner(&shape, cell_percentage, *corner);
+            let position =
+                center + Point::new(relative.x * dimensions.x, relative.y * dimensions.y);
+            if i == 0 {
+                path.move_to(position);
+            } else {
+                path.line_to(position);
+            }
+        }
+        path.close();
+
+        let mut surface = Surface::new_raster_n32_premul((30, 20)).unwrap();
+        let canvas = surface.canvas();
+        canvas.clear(BACKGROUND);
+        canvas.draw_path(&path, &paint(CURSOR));
+
+        draw_clipped_glyph(canvas, &path, false, |canvas| {
+            let glyph_width = (FONT_DIMENSIONS.width * cell_width) as f32;
+            canvas.draw_rect(Rect::from_wh(glyph_width, 20.0), &paint(GLYPH));
+        });
+
+        let image = surface.image_snapshot();
+        let info = ImageInfo::new((30, 20), ColorType::RGBA8888, AlphaType::Unpremul, None);
+        let mut pixels = vec![0u8; info.compute_min_byte_size()];
+        assert!(image.read_pixels(
+            &info,
+            pixels.as_mut_slice(),
+            info.min_row_bytes(),
+            IPoint::new(0, 0),
+            CachingHint::Allow,
+        ));
+        pixels
+    }
+
+    fn paint(color: Color) -> Paint {
+        let mut paint = Paint::default();
+        paint.set_color(color);
+        paint
+    }
+
+    fn pixel(pixels: &[u8], x: usize, y: usize) -> Color {
+        let offset = (y * 30 + x) * 4;
+        Color::from_argb(
+            pixels[offset + 3],
+            pixels[offset],
+            pixels[offset + 1],
+            pixels[offset + 2],
+        )
+    }
+
+    #[test]
+    fn test_block_cursor_inverts_the_cell() {
+        let pixels = render_cursor(CursorShape::Block, DEFAULT_CELL_PERCENTAGE, 1);
+
+        assert_eq!(pixel(&pixels, 0, 0), GLYPH);
+        assert_eq!(pixel(&pixels, 9, 19), GLYPH);
+        assert_eq!(pixel(&pixels, 10, 10), BACKGROUND);
+    }
+
+    #[test]
+    fn test_block_cursor_covers_wide_characters() {
+        let pixels = render_cursor(CursorShape::Block, DEFAULT_CELL_PERCENTAGE, 2);
+
+        assert_eq!(pixel(&pixels, 0, 10), GLYPH);
+        assert_eq!(pixel(&pixels, 19, 10), GLYPH);
+        assert_eq!(pixel(&pixels, 20, 10), BACKGROUND);
+    }
+
+    #[test]
+    fn test_vertical_cursor_clips_the_glyph() {
+        let pixels = render_cursor(CursorShape::Vertical, 0.3, 2);
+
+        // The bar is 30% of a single cell wide, even on wide characters
+        assert_eq!(pixel(&pixels, 0, 10), GLYPH);
+        assert_eq!(pixel(&pixels, 2, 19), GLYPH);
+        assert_eq!(pixel(&pixels, 3, 10), BACKGROUND);
+        assert_eq!(pixel(&pixels, 15, 10), BACKGROUND);
+    }
+
+    #[test]
+    fn test_horizontal_cursor_clips_the_glyph() {
+        let pixels = render_cursor(CursorShape::Horizontal, 0.25, 2);
+
+        // The bar covers the bottom quarter of both cells
+        assert_eq!(pixel(&pixels, 0, 15), GLYPH);
+        assert_eq!(pixel(&pixels, 19, 19), GLYPH);
+        assert_eq!(pixel(&pixels, 5, 14), BACKGROUND);
+        assert_eq!(pixel(&pixels, 20, 19), BACKGROUND);
+    }
+
+    #[test]
+    fn test_secondary_cursors_are_matched_by_order() {
+        let mut renderer = CursorRenderer::new();
+        renderer.set_secondary_cursors(1, vec![(1, 1), (4, 2)]);
+        renderer.set_secondary_cursors(2, vec![(0, 0)]);
+        assert!(!renderer.secondary_cursors[&1][0].placed);
+        renderer.secondary_cursors.get_mut(&1).unwrap()[0].placed = true;
+
+        renderer.set_secondary_cursors(1, vec![(2, 1)]);
+        let cursors = &renderer.secondary_cursors[&1];
+        assert_eq!(cursors.len(), 1);
+        assert_eq!(cursors[0].grid_position, (2, 1));
+        // The moved cursor animates from where it was instead of appearing in place
+        assert!(cursors[0].placed);
+
+        renderer.set_secondary_cursors(1, vec![]);
+        assert!(!renderer.secondary_cursors.contains_key(&1));
+        renderer.remove_secondary_cursors(2);
+        assert!(renderer.secondary_cursors.is_empty());
+    }
+
+    // An 80x20 window two rows down, showing the given line with the given rows of jumps skipped
+    fn content_layout(top_line: u64, scroll: f32, jumped_rows: i64) -> ContentLayout {
+        ContentLayout {
+            position: Point::new(0.0, 2.0),
+            size: Dimensions {
+                width: 80,
+                height: 20,
+            },
+            top_line: top_line as f32 - jumped_rows as f32,
+            scroll: scroll - jumped_rows as f32,
+        }
+    }
+
+    #[test]
+    fn test_locate_in_content_follows_scrolling() {
+        let font_dimensions = Point::new(10.0, 20.0);
+        let (destination, anchor) =
+            locate_in_content(content_layout(10, 10.0, 0), (3, 5), font_dimensions);
+        assert_eq!(destination, Point::new(30.0, 300.0));
+        assert_eq!(anchor.offset, Point::new(0.0, -160.0));
+        assert_eq!(anchor.clip, Rect::from_xywh(0.0, 40.0, 800.0, 400.0));
+
+        // Scrolling a line down keeps the cursor on its text, so it doesn't move in the content
+        // while the content animates up from where it was
+        let (scrolled_destination, scrolled_anchor) =
+            locate_in_content(content_layout(11, 10.0, 0), (3, 4), font_dimensions);
+        assert_eq!(scrolled_destination, destination);
+        assert_eq!(scrolled_anchor.offset, anchor.offset);
+
+        let (_, settled_anchor) =
+            locate_in_content(content_layout(11, 11.0, 0), (3, 4), font_dimensions);
+        assert_eq!(
+            scrolled_destination + settled_anchor.offset,
+            Point::new(30.0, 40.0 + 4.0 * 20.0)
+        );
+    }
+
+    #[test]
+    fn test_locate_in_content_skips_jumped_rows() {
+        let font_dimensions = Point::new(10.0, 20.0);
+        let (destination, anchor) =
+            locate_in_content(content_layout(10, 10.0, 0), (3, 5), font_dimensions);
+
+        // Jumping from line 10 to line 1000 with one far line animates the scroll from line 999,
+        // and skips the other 989 rows
+        let (jumped_destination, jumped_anchor) =
+            locate_in_content(content_layout(1000, 999.0, 989), (0, 19), font_dimensions);
+        assert_eq!(jumped_anchor.offset, anchor.offset);
+        assert_eq!(
+            jumped_destination - destination,
+            Point::new(-30.0, (19.0 - 5.0 + 1.0) * 20.0)
+        );
+
+        let (_, settled_anchor) =
+            locate_in_content(content_layout(1000, 1000.0, 989), (0, 19), font_dimensions);
+        assert_eq!(
+            jumped_destination + settled_anchor.offset,
+            Point::new(0.0, 40.0 + 19.0 * 20.0)
+        );
+    }
+
+    #[test]
+    fn test_cursor_dimensions() {
+        assert_eq!(
+            cursor_dimensions(&CursorShape::Block, 2, FONT_DIMENSIONS),
+            Point::new(20.0, 20.0)
+        );
+        assert_eq!(
+            cursor_dimensions(&CursorShape::Horizontal, 2, FONT_DIMENSIONS),
+            Point::new(20.0, 20.0)
+        );
+        assert_eq!(
+            cursor_dimensions(&CursorShape::Vertical, 2, FONT_DIMENSIONS),
+            Point::new(10.0, 20.0)
+        );
+        // Cursors always cover at least one cell
+        assert_eq!(
+            cursor_dimensions(&CursorShape::Block, 0, FONT_DIMENSIONS),
+            Point::new(10.0, 20.0)
+        );
+    }
+}
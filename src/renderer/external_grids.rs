@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use crate::renderer::{DrawCommand, WindowDrawCommand};
+
+/// Keeps track of the grids that moved out into top-level windows of their own and collects the
+/// draw commands those windows need.
+#[derive(Default)]
+pub struct ExternalGrids {
+    grids: HashSet<u64>,
+    commands: Vec<DrawCommand>,
+}
+
+impl ExternalGrids {
+    pub fn contains(&self, grid_id: u64) -> bool {
+        self.grids.contains(&grid_id)
+    }
+
+    /// Sets aside the commands meant for external grids. Returns the command if the editor
+    /// window has to handle it as well.
+    pub fn route(&mut self, draw_command: DrawCommand) -> Option<DrawCommand> {
+        match &draw_command {
+            DrawCommand::Window {
+                grid_id,
+                command: WindowDrawCommand::External { .. },
+            } => {
+                // The editor window still has to let go of the grid
+                self.grids.insert(*grid_id);
+                self.commands.push(draw_command.clone());
+                Some(draw_command)
+            }
+            DrawCommand::Window { grid_id, command } if self.grids.contains(grid_id) => {
+                if let WindowDrawCommand::Close = command {
+                    self.grids.remove(grid_id);
+                }
+                self.commands.push(draw_command);
+                None
+            }
+            DrawCommand::Window { .. } | DrawCommand::CloseWindow(_) => Some(draw_command),
+            _ => {
+                // Fonts, colors, modes and the cursor concern every top-level window
+                if !self.grids.is_empty() {
+                    self.commands.push(draw_command.clone());
+                }
+                Some(draw_command)
+            }
+        }
+    }
+
+    pub fn take_commands(&mut self) -> Vec<DrawCommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::EditorMode;
+
+    fn window_command(grid_id: u64, command: WindowDrawCommand) -> DrawCommand {
+        DrawCommand::Window { grid_id, command }
+    }
+
+    #[test]
+    fn test_routes_external_grids() {
+        let mut external_grids = ExternalGrids::default();
+
+        assert!(external_grids
+            .route(window_command(2, WindowDrawCommand::Clear))
+            .is_some());
+        assert!(external_grids.take_commands().is_empty());
+
+        assert!(external_grids
+            .route(window_command(
+                2,
+                WindowDrawCommand::External {
+                    window_handle: 1000
+                }
+            ))
+            .is_some());
+        assert!(external_grids.contains(2));
+        assert!(external_grids
+            .route(window_command(2, WindowDrawCommand::Clear))
+            .is_none());
+        assert!(external_grids
+            .route(window_command(3, WindowDrawCommand::Clear))
+            .is_some());
+        assert!(external_grids
+            .route(DrawCommand::ModeChanged(EditorMode::Normal))
+            .is_some());
+
+        let commands = external_grids.take_commands();
+        assert_eq!(commands.len(), 3);
+        assert!(matches!(
+            commands[1],
+            DrawCommand::Window {
+                grid_id: 2,
+                command: WindowDrawCommand::Clear
+            }
+        ));
+        assert!(matches!(commands[2], DrawCommand::ModeChanged(_)));
+    }
+
+    #[test]
+    fn test_closing_returns_grid_to_editor() {
+        let mut external_grids = ExternalGrids::default();
+        external_grids.route(window_command(
+            2,
+            WindowDrawCommand::External {
+                window_handle: 1000,
+            },
+        ));
+
+        assert!(external_grids
+            .route(window_command(2, WindowDrawCommand::Close))
+            .is_none());
+        assert!(!external_grids.contains(2));
+        assert!(external_grids
+            .route(window_command(2, WindowDrawCommand::Clear))
+            .is_some());
+        assert!(external_grids
+            .route(DrawCommand::ModeChanged(EditorMode::Normal))
+            .is_some());
+        assert_eq!(external_grids.take_commands().len(), 2);
+    }
+}
@@ -29,8 +29,11 @@ pub struct CachingShaper {
     options: FontOptions,
     font_loader: FontLoader,
     blob_cache: LruCache<ShapeKey, Vec<TextBlob>>,
+    blob_cache_hits: u64,
+    blob_cache_misses: u64,
     shape_context: ShapeContext,
     scale_factor: f32,
+    zoom: f32,
     fudge_factor: f32,
 }
 
@@ -42,8 +45,11 @@ impl CachingShaper {
             options,
             font_loader: FontLoader::new(font_size),
             blob_cache: LruCache::new(10000),
+            blob_cache_hits: 0,
+            blob_cache_misses: 0,
             shape_context: ShapeContext::new(),
             scale_factor,
+            zoom: 1.0,
             fudge_factor: 1.0,
         };
         shaper.reset_font_loader();
@@ -65,7 +71,7 @@ impl CachingShaper {
     }
 
     pub fn current_size(&self) -> f32 {
-        self.options.size * self.scale_factor * self.fudge_factor
+        self.options.size * self.scale_factor * self.zoom * self.fudge_factor
     }
 
     pub fn update_scale_factor(&mut self, scale_factor: f32) {
@@ -74,6 +80,12 @@ impl CachingShaper {
         self.reset_font_loader();
     }
 
+    pub fn update_zoom(&mut self, zoom: f32) {
+        debug!("zoom changed: {:.2}", zoom);
+        self.zoom = zoom;
+        self.reset_font_loader();
+    }
+
     pub fn update_font(&mut self, guifont_setting: &str) {
         debug!("Updating font: {}", guifont_setting);
 
@@ -119,6 +131,19 @@ impl CachingShaper {
         self.blob_cache.clear();
     }
 
+    pub fn blob_cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.blob_cache_hits,
+            misses: self.blob_cache_misses,
+            size: self.blob_cache.len(),
+            capacity: self.blob_cache.cap(),
+        }
+    }
+
+    pub fn font_cache_stats(&self) -> CacheStats {
+        self.font_loader.cache_stats()
+    }
+
     pub fn font_names(&self) -> Vec<String> {
         self.font_loader.font_names()
     }
@@ -358,7 +383,10 @@ impl CachingShaper {
     pub fn shape_cached(&mut self, text: String, bold: bool, italic: bool) -> &Vec<TextBlob> {
         let key = ShapeKey::new(text.clone(), bold, italic);
 
-        if !self.blob_cache.contains(&key) {
+        if self.blob_cache.contains(&key) {
+            self.blob_cache_hits += 1;
+        } else {
+            self.blob_cache_misses += 1;
             let blobs = self.shape(text, bold, italic);
             self.blob_cache.put(key.clone(), blobs);
         }
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{cell::RefCell, collections::HashSet, rc::Rc, sync::Arc};
 
 use log::trace;
 use lru::LruCache;
@@ -9,27 +9,42 @@ use crate::renderer::fonts::swash_font::SwashFont;
 static DEFAULT_FONT: &[u8] = include_bytes!("../../../assets/fonts/FiraCodeNerdFont-Regular.ttf");
 static LAST_RESORT_FONT: &[u8] = include_bytes!("../../../assets/fonts/LastResort-Regular.ttf");
 
+// Leaves room for the fonts of a few sizes, as windows can be zoomed separately
+const FONT_CACHE_SIZE: usize = 40;
+
 pub struct FontPair {
     pub key: FontKey,
     pub skia_font: Font,
     pub swash_font: SwashFont,
 }
 
+// Fonts are keyed by their size as well, which is stored as bits to be hashable
+type FontCache = LruCache<(FontKey, u32), Arc<FontPair>>;
+
+thread_local! {
+    // Shared by the font loaders of every window, so that windows using the same font at the same
+    // size only load it once
+    static FONT_CACHE: Rc<RefCell<FontCache>> =
+        Rc::new(RefCell::new(LruCache::new(FONT_CACHE_SIZE)));
+}
+
+fn swash_font(typeface: &Typeface) -> Option<SwashFont> {
+    let (font_data, index) = typeface.to_font_data()?;
+    SwashFont::from_data(font_data, index)
+}
+
 impl FontPair {
-    fn new(key: FontKey, mut skia_font: Font) -> Option<FontPair> {
+    fn new(key: FontKey, typeface: Typeface, swash_font: SwashFont, font_size: f32) -> FontPair {
+        let mut skia_font = Font::from_typeface(typeface, font_size);
         skia_font.set_subpixel(true);
         skia_font.set_hinting(FontHinting::Full);
         skia_font.set_edging(Edging::AntiAlias);
 
-        let typeface = skia_font.typeface().unwrap();
-        let (font_data, index) = typeface.to_font_data().unwrap();
-        let swash_font = SwashFont::from_data(font_data, index)?;
-
-        Some(Self {
+        Self {
             key,
             skia_font,
             swash_font,
-        })
+        }
     }
 }
 
@@ -48,9 +63,30 @@ pub struct FontKey {
     pub family_name: Option<String>,
 }
 
+/// Lookup counters and fill level of a cache, shown by the profiler.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub size: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> Option<f32> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f32 / lookups as f32)
+    }
+}
+
 pub struct FontLoader {
     font_mgr: FontMgr,
-    cache: LruCache<FontKey, Arc<FontPair>>,
+    cache: Rc<RefCell<FontCache>>,
+    // The fonts this loader asked for. The cache outlives it, so it also holds the fonts of
+    // previous guifonts and of other windows.
+    loaded_keys: HashSet<FontKey>,
+    cache_hits: u64,
+    cache_misses: u64,
     font_size: f32,
     last_resort: Option<Arc<FontPair>>,
 }
@@ -59,36 +95,53 @@ impl FontLoader {
     pub fn new(font_size: f32) -> FontLoader {
         FontLoader {
             font_mgr: FontMgr::new(),
-            cache: LruCache::new(20),
+            cache: FONT_CACHE.with(Rc::clone),
+            loaded_keys: HashSet::new(),
+            cache_hits: 0,
+            cache_misses: 0,
             font_size,
             last_resort: None,
         }
     }
 
+    fn cache_key(&self, font_key: &FontKey) -> (FontKey, u32) {
+        (font_key.clone(), self.font_size.to_bits())
+    }
+
     fn load(&mut self, font_key: FontKey) -> Option<FontPair> {
         let font_style = font_style(font_key.bold, font_key.italic);
 
         trace!("Loading font {:?}", font_key);
-        if let Some(family_name) = &font_key.family_name {
-            let typeface = self.font_mgr.match_family_style(family_name, font_style)?;
-            FontPair::new(font_key, Font::from_typeface(typeface, self.font_size))
+        let typeface = if let Some(family_name) = &font_key.family_name {
+            self.font_mgr.match_family_style(family_name, font_style)?
         } else {
             let data = Data::new_copy(DEFAULT_FONT);
-            let typeface = Typeface::from_data(data, 0).unwrap();
-            FontPair::new(font_key, Font::from_typeface(typeface, self.font_size))
-        }
+            Typeface::from_data(data, 0).unwrap()
+        };
+        let swash_font = swash_font(&typeface)?;
+        Some(FontPair::new(
+            font_key,
+            typeface,
+            swash_font,
+            self.font_size,
+        ))
     }
 
     pub fn get_or_load(&mut self, font_key: &FontKey) -> Option<Arc<FontPair>> {
-        if let Some(cached) = self.cache.get(font_key) {
+        let cache_key = self.cache_key(font_key);
+        if let Some(cached) = self.cache.borrow_mut().get(&cache_key) {
+            self.cache_hits += 1;
+            self.loaded_keys.insert(font_key.clone());
             return Some(cached.clone());
         }
+        self.cache_misses += 1;
 
         let loaded_font = self.load(font_key.clone())?;
 
         let font_arc = Arc::new(loaded_font);
 
-        self.cache.put(font_key.clone(), font_arc.clone());
+        self.cache.borrow_mut().put(cache_key, font_arc.clone());
+        self.loaded_keys.insert(font_key.clone());
 
         Some(font_arc)
     }
@@ -110,12 +163,17 @@ impl FontLoader {
             family_name: Some(typeface.family_name()),
         };
 
+        let swash_font = swash_font(&typeface)?;
         let font_pair = Arc::new(FontPair::new(
             font_key.clone(),
-            Font::from_typeface(typeface, self.font_size),
-        )?);
+            typeface,
+            swash_font,
+            self.font_size,
+        ));
 
-        self.cache.put(font_key, font_pair.clone());
+        let cache_key = self.cache_key(&font_key);
+        self.cache.borrow_mut().put(cache_key, font_pair.clone());
+        self.loaded_keys.insert(font_key);
 
         Some(font_pair)
     }
@@ -127,9 +185,9 @@ impl FontLoader {
             let font_key = FontKey::default();
             let data = Data::new_copy(LAST_RESORT_FONT);
             let typeface = Typeface::from_data(data, 0).unwrap();
+            let swash_font = swash_font(&typeface).unwrap();
 
-            let font_pair =
-                FontPair::new(font_key, Font::from_typeface(typeface, self.font_size)).unwrap();
+            let font_pair = FontPair::new(font_key, typeface, swash_font, self.font_size);
             let font_pair = Arc::new(font_pair);
 
             self.last_resort = Some(font_pair.clone());
@@ -137,12 +195,30 @@ impl FontLoader {
         }
     }
 
+    /// The cached fonts this loader loaded, most recently used first.
     pub fn loaded_fonts(&self) -> Vec<Arc<FontPair>> {
-        self.cache.iter().map(|(_, v)| v.clone()).collect()
+        let font_size = self.font_size.to_bits();
+        self.cache
+            .borrow()
+            .iter()
+            .filter(|((key, size), _)| *size == font_size && self.loaded_keys.contains(key))
+            .map(|(_, v)| v.clone())
+            .collect()
     }
 
     pub fn refresh(&mut self, font_pair: &FontPair) {
-        self.cache.get(&font_pair.key);
+        let cache_key = self.cache_key(&font_pair.key);
+        self.cache.borrow_mut().get(&cache_key);
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let cache = self.cache.borrow();
+        CacheStats {
+            hits: self.cache_hits,
+            misses: self.cache_misses,
+            size: cache.len(),
+            capacity: cache.cap(),
+        }
     }
 
     pub fn font_names(&self) -> Vec<String> {
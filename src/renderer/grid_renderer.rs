@@ -1,12 +1,13 @@
 use std::sync::Arc;
 
 use glutin::dpi::PhysicalSize;
-use log::trace;
+use log::{error, trace};
 use skia_safe::{
-    colors, dash_path_effect, BlendMode, Canvas, Color, Paint, Path, Point, Rect, HSV,
+    colors, dash_path_effect, BlendMode, Canvas, Color, Color4f, Paint, Path, Point, Rect, HSV,
 };
 
 use crate::{
+    cmd_line::CmdLineSettings,
     dimensions::Dimensions,
     editor::{Colors, Style, UnderlineStyle},
     renderer::{CachingShaper, RendererSettings},
@@ -24,6 +25,26 @@ pub struct GridRenderer {
     pub is_ready: bool,
 }
 
+// Shown until Neovim reports the default colors of its colorscheme
+fn startup_background() -> Option<Color4f> {
+    let color = SETTINGS.get::<CmdLineSettings>().startup_background?;
+    match csscolorparser::parse(&color) {
+        Ok(color) => {
+            let [red, green, blue, alpha] = color.to_array();
+            Some(Color4f::new(
+                red as f32,
+                green as f32,
+                blue as f32,
+                alpha as f32,
+            ))
+        }
+        Err(error) => {
+            error!("Could not parse startup background {}: {}", color, error);
+            None
+        }
+    }
+}
+
 impl GridRenderer {
     pub fn new(scale_factor: f64) -> Self {
         let mut shaper = CachingShaper::new(scale_factor as f32);
@@ -31,7 +52,7 @@ impl GridRenderer {
         paint.set_anti_alias(false);
         let default_style = Arc::new(Style::new(Colors::new(
             Some(colors::WHITE),
-            Some(colors::BLACK),
+            Some(startup_background().unwrap_or(colors::BLACK)),
             Some(colors::GREY),
         )));
         let em_size = shaper.current_size();
@@ -67,6 +88,11 @@ impl GridRenderer {
         self.update_font_dimensions();
     }
 
+    pub fn handle_zoom_update(&mut self, zoom: f32) {
+        self.shaper.update_zoom(zoom);
+        self.update_font_dimensions();
+    }
+
     pub fn update_font(&mut self, guifont_setting: &str) {
         self.shaper.update_font(guifont_setting);
         self.update_font_dimensions();
@@ -90,6 +116,10 @@ impl GridRenderer {
         self.default_style.colors.background.unwrap().to_color()
     }
 
+    pub fn get_default_foreground(&self) -> Color {
+        self.default_style.colors.foreground.unwrap().to_color()
+    }
+
     pub fn draw_background(
         &mut self,
         canvas: &mut Canvas,
@@ -97,6 +127,7 @@ impl GridRenderer {
         cell_width: u64,
         style: &Option<Arc<Style>>,
         is_floating: bool,
+        window_opacity: Option<f32>,
     ) {
         self.paint.set_blend_mode(BlendMode::Src);
 
@@ -112,7 +143,11 @@ impl GridRenderer {
                 .set_color(style.background(&self.default_style.colors).to_color());
         }
 
-        if is_floating {
+        if let Some(opacity) = window_opacity {
+            // Like winblend, only the backgrounds are blended so that the text stays readable
+            self.paint
+                .set_alpha((255.0 * opacity.min(1.0).max(0.0)) as u8);
+        } else if is_floating {
             self.paint
                 .set_alpha((255.0 * ((100 - style.blend) as f32 / 100.0)) as u8);
         } else if (SETTINGS.get::<WindowSettings>().transparency - 1.0).abs() > f32::EPSILON
@@ -1,9 +1,12 @@
 pub mod animation_utils;
+mod background;
 pub mod cursor_renderer;
+mod external_grids;
 pub mod fonts;
 pub mod grid_renderer;
 pub mod profiler;
 mod rendered_window;
+mod window_decorations;
 
 use std::{
     cmp::Ordering,
@@ -13,32 +16,57 @@ use std::{
 
 use glutin::event::Event;
 use log::error;
-use skia_safe::Canvas;
-use tokio::sync::mpsc::UnboundedReceiver;
+use skia_safe::{BlendMode, Canvas, Color, Paint, Rect, SamplingOptions, Surface};
 
 use crate::{
     bridge::EditorMode,
     editor::{Cursor, Style},
-    event_aggregator::EVENT_AGGREGATOR,
+    event_aggregator::{EventReceiver, EVENT_AGGREGATOR},
+    redraw_scheduler::REDRAW_SCHEDULER,
     settings::*,
     WindowSettings,
 };
 
+use animation_utils::Easing;
+use background::{Background, BackgroundGradient, BackgroundImageMode};
 use cursor_renderer::CursorRenderer;
+use external_grids::ExternalGrids;
 pub use fonts::caching_shaper::CachingShaper;
 pub use grid_renderer::GridRenderer;
+use rendered_window::{build_window_surface, ClosingWindow};
 pub use rendered_window::{LineFragment, RenderedWindow, WindowDrawCommand, WindowDrawDetails};
+use window_decorations::ShadowColor;
 
-#[derive(SettingGroup, Clone)]
+#[derive(SettingGroup, Clone, PartialEq)]
 pub struct RendererSettings {
     position_animation_length: f32,
+    position_animation_easing: Easing,
     scroll_animation_length: f32,
+    scroll_animation_easing: Easing,
+    scroll_animation_far_lines: u32,
+    window_resize_animation_length: f32,
+    window_resize_animation_easing: Easing,
+    window_close_animation_length: f32,
+    window_close_animation_easing: Easing,
     floating_opacity: f32,
     floating_blur: bool,
     floating_blur_amount_x: f32,
     floating_blur_amount_y: f32,
+    floating_shadow: bool,
+    floating_shadow_offset_x: f32,
+    floating_shadow_offset_y: f32,
+    floating_shadow_blur_radius: f32,
+    floating_shadow_color: ShadowColor,
+    floating_corner_radius: f32,
+    floating_decorations_min_zindex: u64,
+    background_image: String,
+    background_image_mode: BackgroundImageMode,
+    background_image_dim: f32,
+    background_gradient: BackgroundGradient,
+    background_gradient_angle: f32,
     debug_renderer: bool,
     profiler: bool,
+    profiler_csv: String,
     underline_automatic_scaling: bool,
 }
 
@@ -46,13 +74,33 @@ impl Default for RendererSettings {
     fn default() -> Self {
         Self {
             position_animation_length: 0.15,
+            position_animation_easing: Easing::OutExpo,
             scroll_animation_length: 0.3,
+            scroll_animation_easing: Easing::OutExpo,
+            scroll_animation_far_lines: 1,
+            window_resize_animation_length: 0.15,
+            window_resize_animation_easing: Easing::OutExpo,
+            window_close_animation_length: 0.15,
+            window_close_animation_easing: Easing::OutCubic,
             floating_opacity: 0.7,
             floating_blur: true,
             floating_blur_amount_x: 2.0,
             floating_blur_amount_y: 2.0,
+            floating_shadow: false,
+            floating_shadow_offset_x: 0.0,
+            floating_shadow_offset_y: 4.0,
+            floating_shadow_blur_radius: 12.0,
+            floating_shadow_color: ShadowColor(Color::from_argb(128, 0, 0, 0)),
+            floating_corner_radius: 0.0,
+            floating_decorations_min_zindex: 0,
+            background_image: "".to_string(),
+            background_image_mode: BackgroundImageMode::Cover,
+            background_image_dim: 0.0,
+            background_gradient: BackgroundGradient::default(),
+            background_gradient_angle: 180.0,
             debug_renderer: false,
             profiler: false,
+            profiler_csv: "".to_string(),
             underline_automatic_scaling: false,
         }
     }
@@ -66,6 +114,10 @@ pub enum DrawCommand {
         command: WindowDrawCommand,
     },
     UpdateCursor(Cursor),
+    SecondaryCursors {
+        grid: u64,
+        positions: Vec<(u64, u64)>,
+    },
     FontChanged(String),
     DefaultStyleChanged(Style),
     ModeChanged(EditorMode),
@@ -77,14 +129,55 @@ pub struct Renderer {
     current_mode: EditorMode,
 
     rendered_windows: HashMap<u64, RenderedWindow>,
+    closing_windows: Vec<ClosingWindow>,
     pub window_regions: Vec<WindowDrawDetails>,
+    pub separator_highlight: Option<Rect>,
+
+    batched_draw_command_receiver: Option<EventReceiver<Vec<DrawCommand>>>,
+    queued_draw_commands: Vec<DrawCommand>,
+    pub profiler: profiler::Profiler,
+
+    // Grids drawn by top-level windows of their own. For the renderer of such a window,
+    // `external_grid` is the grid it draws.
+    external_grids: ExternalGrids,
+    external_grid: Option<u64>,
+    guifont: Option<String>,
 
-    pub batched_draw_command_receiver: UnboundedReceiver<Vec<DrawCommand>>,
-    profiler: profiler::Profiler,
+    background: Background,
+
+    // Windows are composited into this surface and only the damaged parts of it are drawn again
+    composite_surface: Option<Surface>,
+    pending_damage: Option<Rect>,
+    full_damage: bool,
+    composited_settings: Option<(RendererSettings, f32)>,
 }
 
 impl Renderer {
     pub fn new(scale_factor: f64) -> Self {
+        let batched_draw_command_receiver = EVENT_AGGREGATOR.register_event::<Vec<DrawCommand>>();
+        Renderer::build(scale_factor, Some(batched_draw_command_receiver), None)
+    }
+
+    /// Creates the renderer for the top-level window of an external grid. It starts out with the
+    /// font, colors and cursor of this renderer and receives its draw commands from it.
+    pub fn new_external(&self, grid_id: u64, scale_factor: f64) -> Self {
+        let mut renderer = Renderer::build(scale_factor, None, Some(grid_id));
+        if let Some(guifont) = &self.guifont {
+            renderer.queue_draw_command(DrawCommand::FontChanged(guifont.clone()));
+        }
+        renderer.grid_renderer.default_style = self.grid_renderer.default_style.clone();
+        renderer.current_mode = self.current_mode.clone();
+        renderer
+            .cursor_renderer
+            .update_cursor(self.cursor_renderer.cursor().clone());
+        renderer
+    }
+
+    fn build(
+        scale_factor: f64,
+        batched_draw_command_receiver: Option<EventReceiver<Vec<DrawCommand>>>,
+        external_grid: Option<u64>,
+    ) -> Self {
         let cursor_renderer = CursorRenderer::new();
         let grid_renderer = GridRenderer::new(scale_factor);
         let current_mode = EditorMode::Unknown(String::from(""));
@@ -92,17 +185,27 @@ impl Renderer {
         let rendered_windows = HashMap::new();
         let window_regions = Vec::new();
 
-        let batched_draw_command_receiver = EVENT_AGGREGATOR.register_event::<Vec<DrawCommand>>();
         let profiler = profiler::Profiler::new(12.0);
 
         Renderer {
             rendered_windows,
+            closing_windows: Vec::new(),
             cursor_renderer,
             grid_renderer,
             current_mode,
             window_regions,
+            separator_highlight: None,
             batched_draw_command_receiver,
+            queued_draw_commands: Vec::new(),
             profiler,
+            external_grids: ExternalGrids::default(),
+            external_grid,
+            guifont: None,
+            background: Background::default(),
+            composite_surface: None,
+            pending_damage: None,
+            full_damage: true,
+            composited_settings: None,
         }
     }
 
@@ -114,20 +217,41 @@ impl Renderer {
         self.grid_renderer.font_names()
     }
 
+    /// Queues a draw command for the next frame, in addition to those sent by the editor.
+    pub fn queue_draw_command(&mut self, draw_command: DrawCommand) {
+        self.queued_draw_commands.push(draw_command);
+    }
+
+    /// Takes the draw commands meant for the top-level windows of external grids.
+    pub fn take_external_draw_commands(&mut self) -> Vec<DrawCommand> {
+        self.external_grids.take_commands()
+    }
+
     /// Draws frame
     ///
     /// # Returns
     /// `bool` indicating whether or not font was changed during this frame.
     #[allow(clippy::needless_collect)]
     pub fn draw_frame(&mut self, root_canvas: &mut Canvas, dt: f32) -> bool {
-        let mut draw_commands = Vec::new();
-        while let Ok(draw_command) = self.batched_draw_command_receiver.try_recv() {
-            draw_commands.extend(draw_command);
+        let mut draw_commands = std::mem::take(&mut self.queued_draw_commands);
+        if let Some(receiver) = self.batched_draw_command_receiver.as_mut() {
+            while let Ok(draw_command) = receiver.try_recv() {
+                draw_commands.extend(draw_command);
+            }
         }
 
         let mut font_changed = false;
 
         for draw_command in draw_commands.into_iter() {
+            let draw_command = if self.external_grid.is_none() {
+                match self.external_grids.route(draw_command) {
+                    Some(draw_command) => draw_command,
+                    None => continue,
+                }
+            } else {
+                draw_command
+            };
+
             if let DrawCommand::FontChanged(_) = draw_command {
                 font_changed = true;
             }
@@ -138,14 +262,56 @@ impl Renderer {
         let font_dimensions = self.grid_renderer.font_dimensions;
 
         let transparency = { SETTINGS.get::<WindowSettings>().transparency };
-        root_canvas.clear(default_background.with_a((255.0 * transparency) as u8));
-        root_canvas.save();
-        root_canvas.reset_matrix();
+        let settings = SETTINGS.get::<RendererSettings>();
+
+        let canvas_size = root_canvas.image_info().dimensions();
+        let surface_matches = self.composite_surface.as_ref().map_or(false, |surface| {
+            surface.width() == canvas_size.width && surface.height() == canvas_size.height
+        });
+        if !surface_matches {
+            self.composite_surface = Some(build_window_surface(
+                root_canvas,
+                (canvas_size.width, canvas_size.height),
+            ));
+            self.full_damage = true;
+        }
+
+        // Settings apply to every window, so changing any of them redraws everything
+        let composited_settings = Some((settings.clone(), transparency));
+        if self.composited_settings != composited_settings {
+            self.composited_settings = composited_settings;
+            self.full_damage = true;
+        }
+        self.background.update(&settings);
+
+        let mut animating = false;
+        for window in self.rendered_windows.values_mut() {
+            if !window.hidden {
+                animating |= window.update(&settings, dt);
+            }
+        }
+        if animating {
+            REDRAW_SCHEDULER.queue_next_frame();
+        }
 
-        if let Some(root_window) = self.rendered_windows.get(&1) {
-            let clip_rect = root_window.pixel_region(font_dimensions);
-            root_canvas.clip_rect(&clip_rect, None, Some(false));
+        let mut damage = self.pending_damage.take();
+        for window in self.rendered_windows.values_mut() {
+            if let Some(region) = window.take_damage(&settings, font_dimensions) {
+                add_damage(&mut damage, region);
+            }
         }
+        if self.full_damage {
+            self.full_damage = false;
+            damage = Some(Rect::from_wh(
+                canvas_size.width as f32,
+                canvas_size.height as f32,
+            ));
+        }
+
+        let root_clip = self
+            .rendered_windows
+            .get(&1)
+            .map(|root_window| root_window.pixel_region(font_dimensions));
 
         let windows: Vec<&mut RenderedWindow> = {
             let (mut root_windows, mut floating_windows): (
@@ -168,28 +334,114 @@ impl Renderer {
                 .collect()
         };
 
-        let settings = SETTINGS.get::<RendererSettings>();
+        let composite_surface = self.composite_surface.as_mut().unwrap();
+        let mut composited_windows = 0;
+        if let Some(damage) = damage {
+            // Everything within the damaged area is drawn again, so windows overlapping a changed
+            // window are composited on top of it in the same order as before
+            let canvas = composite_surface.canvas();
+            canvas.save();
+            canvas.clip_rect(damage, None, Some(false));
+            canvas.clear(default_background.with_a((255.0 * transparency) as u8));
+
+            // The default background covers the background image, so that the transparency
+            // setting controls how much of it shines through
+            let canvas_rect = Rect::from_wh(canvas_size.width as f32, canvas_size.height as f32);
+            if self.background.draw(canvas, canvas_rect, &settings) {
+                let mut paint = Paint::default();
+                paint.set_color(default_background.with_a((255.0 * transparency) as u8));
+                canvas.draw_paint(&paint);
+            }
+
+            if let Some(root_clip) = root_clip {
+                canvas.clip_rect(&root_clip, None, Some(false));
+            }
+
+            for window in windows.iter_mut() {
+                let intersects_damage = window
+                    .composited_region()
+                    .map_or(false, |region| region.intersects(damage));
+                if intersects_damage {
+                    window.draw(
+                        canvas,
+                        &settings,
+                        default_background.with_a((255.0 * transparency) as u8),
+                        font_dimensions,
+                    );
+                    composited_windows += 1;
+                }
+            }
+
+            canvas.restore();
+        }
+
         self.window_regions = windows
-            .into_iter()
-            .map(|window| {
-                window.draw(
-                    root_canvas,
-                    &settings,
-                    default_background.with_a((255.0 * transparency) as u8),
-                    font_dimensions,
-                    dt,
-                )
-            })
+            .iter()
+            .map(|window| window.draw_details(font_dimensions))
             .collect();
 
+        root_canvas.save();
+        root_canvas.reset_matrix();
+
+        let mut paint = Paint::default();
+        paint.set_blend_mode(BlendMode::Src);
+        composite_surface.draw(
+            root_canvas,
+            (0.0, 0.0),
+            SamplingOptions::default(),
+            Some(&paint),
+        );
+
+        if let Some(root_clip) = root_clip {
+            root_canvas.clip_rect(&root_clip, None, Some(false));
+        }
+
+        // Closed windows fade out on top of the windows taking over their space
+        for closing_window in self.closing_windows.iter_mut() {
+            closing_window.update(&settings, dt);
+        }
+        self.closing_windows
+            .retain(|closing_window| !closing_window.is_finished());
+        for closing_window in self.closing_windows.iter() {
+            closing_window.draw(root_canvas, &settings);
+        }
+        if !self.closing_windows.is_empty() {
+            REDRAW_SCHEDULER.queue_next_frame();
+        }
+
+        if let Some(separator_highlight) = self.separator_highlight {
+            let mut paint = Paint::default();
+            paint.set_color(self.grid_renderer.get_default_foreground().with_a(100));
+            root_canvas.draw_rect(separator_highlight, &paint);
+        }
+
         let windows = &self.rendered_windows;
         self.cursor_renderer
             .update_cursor_destination(font_dimensions.into(), windows);
 
-        self.cursor_renderer
-            .draw(&mut self.grid_renderer, &self.current_mode, root_canvas, dt);
-
-        self.profiler.draw(root_canvas, dt);
+        // The cursor is only drawn by the top-level window showing its grid
+        let cursor_grid = self.cursor_renderer.cursor().parent_window_id;
+        let draws_cursor = match self.external_grid {
+            Some(external_grid) => cursor_grid == external_grid,
+            None => !self.external_grids.contains(cursor_grid),
+        };
+        if draws_cursor {
+            self.cursor_renderer
+                .draw(&mut self.grid_renderer, &self.current_mode, root_canvas, dt);
+        }
+        // Every top-level window draws the secondary cursors of its own grids
+        self.cursor_renderer.draw_secondary_cursors(&self.grid_renderer, root_canvas, dt);
+
+        // Taking the frame stats resets them and the csv export truncates its file, so only the
+        // editor window runs the profiler
+        if self.external_grid.is_none() {
+            self.profiler.set_composited_windows(composited_windows);
+            self.profiler.set_cache_stats(
+                self.grid_renderer.shaper.blob_cache_stats(),
+                self.grid_renderer.shaper.font_cache_stats(),
+            );
+            self.profiler.draw(root_canvas, dt);
+        }
 
         root_canvas.restore();
 
@@ -198,11 +450,33 @@ impl Renderer {
 
     fn handle_draw_command(&mut self, root_canvas: &mut Canvas, draw_command: DrawCommand) {
         match draw_command {
+            DrawCommand::Window {
+                grid_id,
+                command: WindowDrawCommand::External { .. },
+            } => {
+                // The grid is drawn by its own top-level window from now on
+                if let Some(rendered_window) = self.rendered_windows.remove(&grid_id) {
+                    if let Some(region) = rendered_window.composited_region() {
+                        add_damage(&mut self.pending_damage, region);
+                    }
+                }
+            }
             DrawCommand::Window {
                 grid_id,
                 command: WindowDrawCommand::Close,
             } => {
-                self.rendered_windows.remove(&grid_id);
+                self.cursor_renderer.remove_secondary_cursors(grid_id);
+                if let Some(mut rendered_window) = self.rendered_windows.remove(&grid_id) {
+                    if let Some(region) = rendered_window.composited_region() {
+                        add_damage(&mut self.pending_damage, region);
+                    }
+                    let settings = SETTINGS.get::<RendererSettings>();
+                    if !rendered_window.hidden && settings.window_close_animation_length > 0.0 {
+                        self.closing_windows.push(
+                            rendered_window.start_closing(self.grid_renderer.font_dimensions),
+                        );
+                    }
+                }
             }
             DrawCommand::Window { grid_id, command } => {
                 match self.rendered_windows.entry(grid_id) {
@@ -235,11 +509,17 @@ impl Renderer {
             DrawCommand::UpdateCursor(new_cursor) => {
                 self.cursor_renderer.update_cursor(new_cursor);
             }
+            DrawCommand::SecondaryCursors { grid, positions } => {
+                self.cursor_renderer.set_secondary_cursors(grid, positions);
+            }
             DrawCommand::FontChanged(new_font) => {
                 self.grid_renderer.update_font(&new_font);
+                self.guifont = Some(new_font);
+                self.full_damage = true;
             }
             DrawCommand::DefaultStyleChanged(new_style) => {
                 self.grid_renderer.default_style = Arc::new(new_style);
+                self.full_damage = true;
             }
             DrawCommand::ModeChanged(new_mode) => {
                 self.current_mode = new_mode;
@@ -249,6 +529,14 @@ impl Renderer {
     }
 }
 
+/// Grows the damaged area to also cover the given region.
+fn add_damage(damage: &mut Option<Rect>, region: Rect) {
+    match damage {
+        Some(existing) => existing.join(region),
+        None => *damage = Some(region),
+    }
+}
+
 /// Defines how floating windows are sorted.
 fn floating_sort(window_a: &&mut RenderedWindow, window_b: &&mut RenderedWindow) -> Ordering {
     // First, compare floating order
@@ -1,20 +1,119 @@
 use crate::renderer::animation_utils::lerp;
 use crate::settings::SETTINGS;
 use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use log::error;
+
+use crate::event_aggregator::EVENT_AGGREGATOR;
+use crate::performance_stats::{FrameStats, PERFORMANCE_STATS};
 use crate::renderer::{fonts::font_loader::*, RendererSettings};
 use skia_safe::{Canvas, Color, Paint, Point, Rect, Size};
 
 const FRAMETIMES_COUNT: usize = 48;
 
+const CSV_HEADER: &str = "time_ms,frame_time_ms,refresh_rate,missed_frames,composited_windows,\
+redraw_events,parse_time_ms,rpc_latency_ms,event_queue_depth,\
+blob_cache_hits,blob_cache_misses,blob_cache_size,font_cache_hits,font_cache_misses,font_cache_size";
+
+/// Everything the profiler shows about a single frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HudStats {
+    pub frame_time: f32,
+    pub refresh_rate: f32,
+    pub missed_frames: u64,
+    pub composited_windows: usize,
+    pub frame: FrameStats,
+    pub event_queue_depth: usize,
+    pub blob_cache: CacheStats,
+    pub font_cache: CacheStats,
+}
+
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+fn format_cache_stats(name: &str, stats: &CacheStats) -> String {
+    let hit_rate = match stats.hit_rate() {
+        Some(hit_rate) => format!("{:.0}%", hit_rate * 100.0),
+        None => "-".to_owned(),
+    };
+    format!(
+        "{}: {} hits, {}/{}",
+        name, hit_rate, stats.size, stats.capacity
+    )
+}
+
+fn csv_row(time: Duration, stats: &HudStats) -> String {
+    format!(
+        "{:.3},{:.3},{:.1},{},{},{},{:.3},{:.3},{},{},{},{},{},{},{}",
+        milliseconds(time),
+        stats.frame_time * 1000.0,
+        stats.refresh_rate,
+        stats.missed_frames,
+        stats.composited_windows,
+        stats.frame.redraw_events,
+        milliseconds(stats.frame.parse_time),
+        milliseconds(stats.frame.rpc_latency),
+        stats.event_queue_depth,
+        stats.blob_cache.hits,
+        stats.blob_cache.misses,
+        stats.blob_cache.size,
+        stats.font_cache.hits,
+        stats.font_cache.misses,
+        stats.font_cache.size,
+    )
+}
+
+struct CsvExport {
+    path: String,
+    // Missing when the file couldn't be created, so that it isn't retried every frame
+    writer: Option<BufWriter<File>>,
+    start: Instant,
+}
+
+impl CsvExport {
+    fn create(path: &str) -> CsvExport {
+        let writer = File::create(path)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                writeln!(writer, "{}", CSV_HEADER)?;
+                Ok(writer)
+            })
+            .map_err(|error| error!("Could not create profiler csv file {}: {}", path, error))
+            .ok();
+
+        CsvExport {
+            path: path.to_owned(),
+            writer,
+            start: Instant::now(),
+        }
+    }
+
+    fn write(&mut self, stats: &HudStats) {
+        if let Some(writer) = &mut self.writer {
+            // Flushed every frame, as Neovide exits without running destructors
+            let result = writeln!(writer, "{}", csv_row(self.start.elapsed(), stats))
+                .and_then(|_| writer.flush());
+            if let Err(error) = result {
+                error!("Could not write profiler csv file {}: {}", self.path, error);
+                self.writer = None;
+            }
+        }
+    }
+}
+
 pub struct Profiler {
     pub font: Arc<FontPair>,
     pub position: Point,
     pub size: Size,
     pub last_draw: Instant,
     pub frametimes: VecDeque<f32>,
+    pub stats: HudStats,
+    csv_export: Option<CsvExport>,
 }
 
 impl Profiler {
@@ -25,14 +124,57 @@ impl Profiler {
         Self {
             font,
             position: Point::new(32.0, 32.0),
-            size: Size::new(200.0, 120.0),
+            size: Size::new(220.0, 260.0),
             last_draw: Instant::now(),
             frametimes: VecDeque::with_capacity(FRAMETIMES_COUNT),
+            stats: HudStats::default(),
+            csv_export: None,
+        }
+    }
+
+    pub fn set_frame_pacing(&mut self, refresh_rate: f32, missed_frames: u64) {
+        self.stats.refresh_rate = refresh_rate;
+        self.stats.missed_frames = missed_frames;
+    }
+
+    pub fn set_composited_windows(&mut self, composited_windows: usize) {
+        self.stats.composited_windows = composited_windows;
+    }
+
+    pub fn set_cache_stats(&mut self, blob_cache: CacheStats, font_cache: CacheStats) {
+        self.stats.blob_cache = blob_cache;
+        self.stats.font_cache = font_cache;
+    }
+
+    fn export_csv(&mut self, path: &str) {
+        if path.is_empty() {
+            self.csv_export = None;
+            return;
+        }
+
+        if self
+            .csv_export
+            .as_ref()
+            .map_or(true, |export| export.path != path)
+        {
+            self.csv_export = Some(CsvExport::create(path));
+        }
+
+        if let Some(csv_export) = &mut self.csv_export {
+            csv_export.write(&self.stats);
         }
     }
 
     pub fn draw(&mut self, root_canvas: &mut Canvas, dt: f32) {
-        if !SETTINGS.get::<RendererSettings>().profiler {
+        let settings = SETTINGS.get::<RendererSettings>();
+
+        // Collected even while hidden, so that the counters only ever cover the last frame
+        self.stats.frame_time = dt;
+        self.stats.frame = PERFORMANCE_STATS.take_frame_stats();
+        self.stats.event_queue_depth = EVENT_AGGREGATOR.queue_depth();
+        self.export_csv(&settings.profiler_csv);
+
+        if !settings.profiler {
             return;
         }
 
@@ -47,17 +189,33 @@ impl Profiler {
         paint.set_color(color);
         root_canvas.draw_paint(&paint);
 
-        // Draw FPS
+        // Draw stats
         let color = Color::from_argb(255, 0, 255, 0);
         paint.set_color(color);
+        let stats = &self.stats;
+        let lines = [
+            format!(
+                "{:.0}FPS / {:.0}Hz",
+                1.0 / dt.max(f32::EPSILON),
+                stats.refresh_rate
+            ),
+            format!("missed frames: {}", stats.missed_frames),
+            format!("composited windows: {}", stats.composited_windows),
+            format!("redraw events: {}", stats.frame.redraw_events),
+            format!("parse time: {:.2}ms", milliseconds(stats.frame.parse_time)),
+            format!(
+                "rpc latency: {:.1}ms",
+                milliseconds(stats.frame.rpc_latency)
+            ),
+            format!("event queue: {}", stats.event_queue_depth),
+            format_cache_stats("blobs", &stats.blob_cache),
+            format_cache_stats("fonts", &stats.font_cache),
+        ];
         let mut text_postion = self.position;
-        text_postion.y += self.font.skia_font.size();
-        root_canvas.draw_str(
-            format!("{:.0}FPS", 1.0 / dt.max(f32::EPSILON)),
-            text_postion,
-            &self.font.skia_font,
-            &paint,
-        );
+        for line in lines.iter() {
+            text_postion.y += self.font.skia_font.size() + 4.0;
+            root_canvas.draw_str(line, text_postion, &self.font.skia_font, &paint);
+        }
 
         self.frametimes.push_back(dt * 1000.0); // to msecs
         while self.frametimes.len() > FRAMETIMES_COUNT {
@@ -142,3 +300,58 @@ impl Profiler {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_matches_header() {
+        let stats = HudStats {
+            frame_time: 0.016,
+            refresh_rate: 60.0,
+            missed_frames: 2,
+            composited_windows: 3,
+            frame: FrameStats {
+                redraw_events: 40,
+                parse_time: Duration::from_micros(250),
+                rpc_latency: Duration::from_millis(4),
+            },
+            event_queue_depth: 5,
+            blob_cache: CacheStats {
+                hits: 90,
+                misses: 10,
+                size: 100,
+                capacity: 10000,
+            },
+            font_cache: CacheStats {
+                hits: 7,
+                misses: 1,
+                size: 1,
+                capacity: 20,
+            },
+        };
+
+        let row = csv_row(Duration::from_millis(1500), &stats);
+        assert_eq!(
+            row,
+            "1500.000,16.000,60.0,2,3,40,0.250,4.000,5,90,10,100,7,1,1"
+        );
+        assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn test_cache_stats_format() {
+        let stats = CacheStats {
+            hits: 3,
+            misses: 1,
+            size: 4,
+            capacity: 20,
+        };
+        assert_eq!(format_cache_stats("fonts", &stats), "fonts: 75% hits, 4/20");
+        assert_eq!(
+            format_cache_stats("fonts", &CacheStats::default()),
+            "fonts: - hits, 0/0"
+        );
+    }
+}
@@ -1,18 +1,21 @@
-use std::{collections::VecDeque, sync::Arc};
+use std::sync::Arc;
 
 use skia_safe::{
     canvas::{SaveLayerRec, SrcRectConstraint},
     gpu::SurfaceOrigin,
     image_filters::blur,
-    BlendMode, Budgeted, Canvas, Color, Image, ImageInfo, Paint, Point, Rect, SamplingOptions,
-    Surface, SurfaceProps, SurfacePropsFlags,
+    BlendMode, Budgeted, Canvas, Color, IRect, Image, ImageInfo, Paint, Point, Rect,
+    SamplingOptions, Surface, SurfaceProps, SurfacePropsFlags,
 };
 
 use crate::{
     dimensions::Dimensions,
-    editor::Style,
-    redraw_scheduler::REDRAW_SCHEDULER,
-    renderer::{animation_utils::*, GridRenderer, RendererSettings},
+    editor::{Style, WindowBlend},
+    renderer::{
+        add_damage, animation_utils::*, window_decorations::WindowDecorations, GridRenderer,
+        RendererSettings,
+    },
+    settings::SETTINGS,
 };
 
 #[derive(Clone, Debug)]
@@ -48,9 +51,14 @@ pub enum WindowDrawCommand {
         top_line: f64,
         bottom_line: f64,
     },
+    Blend(WindowBlend),
+    /// The window moved out into its own top-level window, which draws it from now on
+    External {
+        window_handle: u64,
+    },
 }
 
-fn build_window_surface(parent_canvas: &mut Canvas, pixel_size: (i32, i32)) -> Surface {
+pub fn build_window_surface(parent_canvas: &mut Canvas, pixel_size: (i32, i32)) -> Surface {
     let mut context = parent_canvas.recording_context().unwrap();
     let budgeted = Budgeted::Yes;
     let parent_image_info = parent_canvas.image_info();
@@ -90,14 +98,14 @@ fn build_window_surface_with_grid_size(
     surface
 }
 
-pub struct LocatedSnapshot {
-    image: Image,
-    top_line: u64,
-}
+// Rows kept above and below the grid so that lines scrolled out of view can animate back in. The
+// surface is limited in size, so very tall windows keep fewer rows than a full screen.
+const MAX_SCROLLBACK_ROWS: u64 = 64;
 
 pub struct LocatedSurface {
     surface: Surface,
     pub top_line: u64,
+    scrollback_rows: u64,
 }
 
 impl LocatedSurface {
@@ -107,34 +115,101 @@ impl LocatedSurface {
         grid_size: Dimensions,
         top_line: u64,
     ) -> LocatedSurface {
-        let surface = build_window_surface_with_grid_size(parent_canvas, grid_renderer, grid_size);
+        let scrollback_rows = grid_size.height.min(MAX_SCROLLBACK_ROWS);
+        let surface_size = Dimensions {
+            width: grid_size.width,
+            height: grid_size.height + 2 * scrollback_rows,
+        };
+        let surface =
+            build_window_surface_with_grid_size(parent_canvas, grid_renderer, surface_size);
 
-        LocatedSurface { surface, top_line }
+        LocatedSurface {
+            surface,
+            top_line,
+            scrollback_rows,
+        }
     }
 
-    fn snapshot(&mut self) -> LocatedSnapshot {
-        let image = self.surface.image_snapshot();
-        LocatedSnapshot {
-            image,
-            top_line: self.top_line,
-        }
+    /// Vertical pixel offset of the grid within the surface.
+    fn scrollback_offset(&self, font_dimensions: Dimensions) -> f32 {
+        (self.scrollback_rows * font_dimensions.height) as f32
+    }
+
+    /// Snapshot of the grid, without the scrollback.
+    fn snapshot(&mut self, grid_size: Dimensions, font_dimensions: Dimensions) -> Image {
+        let (width, height): (i32, i32) = (grid_size * font_dimensions).into();
+        let bounds = IRect::from_xywh(
+            0,
+            self.scrollback_offset(font_dimensions) as i32,
+            width,
+            height,
+        );
+
+        self.surface
+            .image_snapshot_with_bounds(bounds)
+            .unwrap_or_else(|| self.surface.image_snapshot())
+    }
+
+    /// Clears the rows above and below the grid, which no longer match the lines next to it.
+    fn clear_scrollback(&mut self, grid_renderer: &GridRenderer, grid_size: Dimensions) {
+        let font_dimensions = grid_renderer.font_dimensions;
+        let scrollback_offset = self.scrollback_offset(font_dimensions);
+        let (width, height): (f32, f32) = (grid_size * font_dimensions).into();
+
+        let mut paint = Paint::default();
+        paint.set_blend_mode(BlendMode::Src);
+        paint.set_color(grid_renderer.get_default_background());
+
+        let canvas = self.surface.canvas();
+        canvas.draw_rect(Rect::from_xywh(0.0, 0.0, width, scrollback_offset), &paint);
+        canvas.draw_rect(
+            Rect::from_xywh(0.0, scrollback_offset + height, width, scrollback_offset),
+            &paint,
+        );
     }
 }
 
-#[derive(Copy, Clone)]
-struct PositionOverride {
-    top_line: u64,
-    current_scroll: f32,
+/// The last snapshot of a closed window, kept around while it fades and scales out.
+pub struct ClosingWindow {
+    snapshot: Image,
+    region: Rect,
+    t: f32,
+}
+
+impl ClosingWindow {
+    pub fn update(&mut self, settings: &RendererSettings, dt: f32) {
+        self.t = (self.t + dt / settings.window_close_animation_length).min(1.0);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        1.0 - self.t < std::f32::EPSILON
+    }
+
+    pub fn draw(&self, root_canvas: &mut Canvas, settings: &RendererSettings) {
+        let progress = settings.window_close_animation_easing.apply(self.t);
+        let scale = lerp(1.0, 0.9, progress);
+        let center = self.region.center();
+
+        root_canvas.save();
+        root_canvas.translate(center);
+        root_canvas.scale((scale, scale));
+        root_canvas.translate(-center);
+
+        let mut paint = Paint::default();
+        paint.set_alpha_f(1.0 - progress);
+        root_canvas.draw_image_rect(&self.snapshot, None, self.region, &paint);
+
+        root_canvas.restore();
+    }
 }
 
 pub struct RenderedWindow {
-    snapshots: VecDeque<LocatedSnapshot>,
     pub current_surface: LocatedSurface,
-    position_override: Option<PositionOverride>,
 
     pub id: u64,
     pub hidden: bool,
     pub floating_order: Option<u64>,
+    blend: WindowBlend,
 
     pub grid_size: Dimensions,
 
@@ -143,10 +218,23 @@ pub struct RenderedWindow {
     grid_destination: Point,
     position_t: f32,
 
+    grid_start_size: Point,
+    grid_current_size: Point,
+    grid_destination_size: Point,
+    size_t: f32,
+
     start_scroll: f32,
     pub current_scroll: f32,
     scroll_destination: f32,
     scroll_t: f32,
+    // Rows the whole window scrolled by since the last viewport update
+    pending_scroll_rows: i64,
+    // Total rows of viewport jumps that were skipped instead of animated
+    pub jumped_rows: i64,
+
+    // Whether the window changed since it was last composited, and where it was composited
+    dirty: bool,
+    composited_region: Option<Rect>,
 }
 
 #[derive(Clone, Debug)]
@@ -165,14 +253,14 @@ impl RenderedWindow {
         grid_size: Dimensions,
     ) -> RenderedWindow {
         let current_surface = LocatedSurface::new(parent_canvas, grid_renderer, grid_size, 0);
+        let grid_size_point = Point::new(grid_size.width as f32, grid_size.height as f32);
 
         RenderedWindow {
-            snapshots: VecDeque::new(),
             current_surface,
-            position_override: None,
             id,
             hidden: false,
             floating_order: None,
+            blend: WindowBlend::default(),
 
             grid_size,
 
@@ -181,10 +269,20 @@ impl RenderedWindow {
             grid_destination: grid_position,
             position_t: 2.0, // 2.0 is out of the 0.0 to 1.0 range and stops animation
 
+            grid_start_size: grid_size_point,
+            grid_current_size: grid_size_point,
+            grid_destination_size: grid_size_point,
+            size_t: 2.0, // 2.0 is out of the 0.0 to 1.0 range and stops animation
+
             start_scroll: 0.0,
             current_scroll: 0.0,
             scroll_destination: 0.0,
             scroll_t: 2.0, // 2.0 is out of the 0.0 to 1.0 range and stops animation
+            pending_scroll_rows: 0,
+            jumped_rows: 0,
+
+            dirty: true,
+            composited_region: None,
         }
     }
 
@@ -194,9 +292,77 @@ impl RenderedWindow {
             self.grid_current_position.y * font_dimensions.height as f32,
         );
 
-        let image_size: (i32, i32) = (self.grid_size * font_dimensions).into();
+        let current_pixel_size = (
+            self.grid_current_size.x * font_dimensions.width as f32,
+            self.grid_current_size.y * font_dimensions.height as f32,
+        );
+
+        Rect::from_point_and_size(current_pixel_position, current_pixel_size)
+    }
+
+    /// Keeps the last snapshot of the window so that it can be animated out after closing.
+    pub fn start_closing(&mut self, font_dimensions: Dimensions) -> ClosingWindow {
+        let snapshot = self
+            .current_surface
+            .snapshot(self.grid_size, font_dimensions);
+        let pixel_region = self.pixel_region(font_dimensions);
+        let region = Rect::from_point_and_size(
+            (pixel_region.left, pixel_region.top),
+            (snapshot.width() as f32, snapshot.height() as f32),
+        );
+
+        ClosingWindow {
+            snapshot,
+            region,
+            t: 0.0,
+        }
+    }
+
+    /// Screen area the window covers when composited, including its shadow.
+    pub fn composited_bounds(
+        &self,
+        settings: &RendererSettings,
+        font_dimensions: Dimensions,
+    ) -> Rect {
+        WindowDecorations::for_window(settings, self.floating_order)
+            .bounds(self.pixel_region(font_dimensions))
+    }
+
+    pub fn composited_region(&self) -> Option<Rect> {
+        self.composited_region
+    }
+
+    /// Returns the screen area that has to be composited again because the window changed,
+    /// covering both where it was last composited and where it is now.
+    pub fn take_damage(
+        &mut self,
+        settings: &RendererSettings,
+        font_dimensions: Dimensions,
+    ) -> Option<Rect> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+
+        let mut damage = self.composited_region;
+        self.composited_region = if self.hidden {
+            None
+        } else {
+            Some(self.composited_bounds(settings, font_dimensions))
+        };
+        if let Some(region) = self.composited_region {
+            add_damage(&mut damage, region);
+        }
+
+        damage
+    }
 
-        Rect::from_point_and_size(current_pixel_position, image_size)
+    pub fn draw_details(&self, font_dimensions: Dimensions) -> WindowDrawDetails {
+        WindowDrawDetails {
+            id: self.id,
+            region: self.pixel_region(font_dimensions),
+            floating_order: self.floating_order,
+        }
     }
 
     pub fn update(&mut self, settings: &RendererSettings, dt: f32) -> bool {
@@ -212,32 +378,46 @@ impl RenderedWindow {
                     (self.position_t + dt / settings.position_animation_length).min(1.0);
             }
 
-            self.grid_current_position = ease_point(
-                ease_out_expo,
+            self.grid_current_position = settings.position_animation_easing.ease_point(
                 self.grid_start_position,
                 self.grid_destination,
                 self.position_t,
             );
         }
 
+        {
+            if 1.0 - self.size_t < std::f32::EPSILON {
+                // We are at destination, move t out of 0-1 range to stop the animation
+                self.size_t = 2.0;
+            } else {
+                animating = true;
+                self.size_t = (self.size_t + dt / settings.window_resize_animation_length).min(1.0);
+            }
+
+            self.grid_current_size = settings.window_resize_animation_easing.ease_point(
+                self.grid_start_size,
+                self.grid_destination_size,
+                self.size_t,
+            );
+        }
+
         {
             if 1.0 - self.scroll_t < std::f32::EPSILON {
                 // We are at destination, move t out of 0-1 range to stop the animation
                 self.scroll_t = 2.0;
-                self.snapshots.clear();
             } else {
                 animating = true;
                 self.scroll_t = (self.scroll_t + dt / settings.scroll_animation_length).min(1.0);
             }
 
-            self.current_scroll = ease(
-                ease_out_expo,
+            self.current_scroll = settings.scroll_animation_easing.ease(
                 self.start_scroll,
                 self.scroll_destination,
                 self.scroll_t,
             );
         }
 
+        self.dirty |= animating;
         animating
     }
 
@@ -247,32 +427,28 @@ impl RenderedWindow {
         settings: &RendererSettings,
         default_background: Color,
         font_dimensions: Dimensions,
-        dt: f32,
-    ) -> WindowDrawDetails {
-        if self.update(settings, dt) {
-            REDRAW_SCHEDULER.queue_next_frame();
-        }
-
+    ) {
         let pixel_region = self.pixel_region(font_dimensions);
+        let decorations = WindowDecorations::for_window(settings, self.floating_order);
 
-        root_canvas.save();
-        root_canvas.clip_rect(&pixel_region, None, Some(false));
+        decorations.draw_shadow(root_canvas, pixel_region);
 
-        if self.floating_order.is_none() {
-            root_canvas.clear(default_background);
+        root_canvas.save();
+        decorations.clip(root_canvas, pixel_region);
+
+        // The window's own blur takes precedence, otherwise only floating windows are blurred
+        let blur_amount = match self.blend.blur {
+            Some(amount) => Some((amount, amount)),
+            None if self.floating_order.is_some() && settings.floating_blur => Some((
+                settings.floating_blur_amount_x,
+                settings.floating_blur_amount_y,
+            )),
+            None => None,
         }
+        .filter(|(amount_x, amount_y)| *amount_x > 0.0 || *amount_y > 0.0);
 
-        if self.floating_order.is_some() && settings.floating_blur {
-            let blur = blur(
-                (
-                    settings.floating_blur_amount_x,
-                    settings.floating_blur_amount_y,
-                ),
-                None,
-                None,
-                None,
-            )
-            .unwrap();
+        if let Some(blur_amount) = blur_amount {
+            let blur = blur(blur_amount, None, None, None).unwrap();
             let save_layer_rec = SaveLayerRec::default()
                 .backdrop(&blur)
                 .bounds(&pixel_region);
@@ -286,10 +462,14 @@ impl RenderedWindow {
         paint.set_blend_mode(BlendMode::Src);
         paint.set_anti_alias(false);
 
-        // Save layer so that setting the blend mode doesn't effect the blur
+        // Save layer so that setting the blend mode doesn't effect the blur. The window's own
+        // opacity only applies to the backgrounds, the same way winblend does, so the cell
+        // backgrounds already carry it and the text stays opaque
         root_canvas.save_layer(&SaveLayerRec::default());
         let mut a = 255;
-        if self.floating_order.is_some() {
+        if let Some(opacity) = self.blend.opacity {
+            a = (opacity.min(1.0).max(0.0) * 255.0) as u8;
+        } else if self.floating_order.is_some() {
             a = (settings.floating_opacity.min(1.0).max(0.0) * 255.0) as u8;
         }
 
@@ -298,50 +478,27 @@ impl RenderedWindow {
 
         paint.set_color(Color::from_argb(255, 255, 255, 255));
 
-        let font_height = font_dimensions.height;
-
-        // Draw scrolling snapshots
-        for snapshot in self.snapshots.iter_mut().rev() {
-            let scroll_offset = (snapshot.top_line * font_height) as f32
-                - (self.current_scroll * font_height as f32);
-            let image = &mut snapshot.image;
-            root_canvas.draw_image_rect(
-                image,
-                None,
-                pixel_region.with_offset((0.0, scroll_offset as f32)),
-                &paint,
-            );
-        }
-
-        let (top_line, current_scroll) = self
-            .position_override
-            .as_ref()
-            .map(|&pos| (pos.top_line, pos.current_scroll))
-            .unwrap_or((self.current_surface.top_line, self.current_scroll));
-        let scroll_offset = (top_line * font_height) as f32 - (current_scroll * font_height as f32);
-
-        // Draw current surface
+        // The scrollback above and below the grid is clipped away, except while the scroll
+        // animation moves it into view. The surface already has the final size while the window
+        // is resizing, so it's drawn at its own size and clipped to the animated region instead
+        // of being stretched
+        let scroll_offset = (self.current_surface.top_line as f32 - self.current_scroll)
+            * font_dimensions.height as f32
+            - self.current_surface.scrollback_offset(font_dimensions);
         let snapshot = self.current_surface.surface.image_snapshot();
-        root_canvas.draw_image_rect(
-            snapshot,
-            None,
-            pixel_region.with_offset((0.0, scroll_offset as f32)),
-            &paint,
+        let image_region = Rect::from_point_and_size(
+            (pixel_region.left, pixel_region.top + scroll_offset),
+            (snapshot.width() as f32, snapshot.height() as f32),
         );
+        root_canvas.draw_image_rect(&snapshot, None, image_region, &paint);
 
         root_canvas.restore();
 
-        if self.floating_order.is_some() {
+        if blur_amount.is_some() {
             root_canvas.restore();
         }
 
         root_canvas.restore();
-
-        WindowDrawDetails {
-            id: self.id,
-            region: pixel_region,
-            floating_order: self.floating_order,
-        }
     }
 
     pub fn handle_window_draw_command(
@@ -349,6 +506,8 @@ impl RenderedWindow {
         grid_renderer: &mut GridRenderer,
         draw_command: WindowDrawCommand,
     ) {
+        self.dirty = true;
+
         match draw_command {
             WindowDrawCommand::Position {
                 grid_position: (grid_left, grid_top),
@@ -375,20 +534,40 @@ impl RenderedWindow {
                     self.grid_destination = new_destination;
                 }
 
+                let new_size_destination =
+                    Point::new(new_grid_size.width as f32, new_grid_size.height as f32);
+                if self.grid_destination_size != new_size_destination {
+                    if floating_order.is_none() && !self.hidden {
+                        self.size_t = 0.0; // Reset animation as we have a new size.
+                        self.grid_start_size = self.grid_current_size;
+                    } else {
+                        // Floating windows follow their content, so their size isn't animated
+                        self.size_t = 2.0;
+                        self.grid_start_size = new_size_destination;
+                        self.grid_current_size = new_size_destination;
+                    }
+                    self.grid_destination_size = new_size_destination;
+                }
+
                 if self.grid_size != new_grid_size {
-                    let mut new_surface = build_window_surface_with_grid_size(
+                    let font_dimensions = grid_renderer.font_dimensions;
+                    let mut new_surface = LocatedSurface::new(
                         self.current_surface.surface.canvas(),
                         grid_renderer,
                         new_grid_size,
+                        self.current_surface.top_line,
                     );
+                    // Keep the grid rows aligned, the scrollback may have changed size
+                    let offset = new_surface.scrollback_offset(font_dimensions)
+                        - self.current_surface.scrollback_offset(font_dimensions);
                     self.current_surface.surface.draw(
-                        new_surface.canvas(),
-                        (0.0, 0.0),
+                        new_surface.surface.canvas(),
+                        (0.0, offset),
                         SamplingOptions::default(),
                         None,
                     );
 
-                    self.current_surface.surface = new_surface;
+                    self.current_surface = new_surface;
                     self.grid_size = new_grid_size;
                 }
 
@@ -402,9 +581,13 @@ impl RenderedWindow {
                 }
             }
             WindowDrawCommand::DrawLine(line_fragments) => {
+                let scrollback_offset = self
+                    .current_surface
+                    .scrollback_offset(grid_renderer.font_dimensions);
                 let canvas = self.current_surface.surface.canvas();
 
                 canvas.save();
+                canvas.translate((0.0, scrollback_offset));
                 for line_fragment in line_fragments.iter() {
                     let LineFragment {
                         window_left,
@@ -419,7 +602,9 @@ impl RenderedWindow {
                         grid_position,
                         *width,
                         style,
-                        self.floating_order.is_some(),
+                        // Highlight blending would apply winblend a second time
+                        self.floating_order.is_some() && self.blend.opacity.is_none(),
+                        self.blend.opacity,
                     );
                 }
 
@@ -435,10 +620,6 @@ impl RenderedWindow {
                     grid_renderer.draw_foreground(canvas, text, grid_position, width, &style);
                 }
                 canvas.restore();
-
-                if self.position_override.is_some() {
-                    self.position_override = None;
-                }
             }
             WindowDrawCommand::Scroll {
                 top,
@@ -452,42 +633,65 @@ impl RenderedWindow {
                     width: font_width,
                     height: font_height,
                 } = grid_renderer.font_dimensions;
-                let scrolled_region = Rect::new(
-                    (left * font_width) as f32,
-                    (top * font_height) as f32,
-                    (right * font_width) as f32,
-                    (bottom * font_height) as f32,
-                );
+                let scrollback_offset = self
+                    .current_surface
+                    .scrollback_offset(grid_renderer.font_dimensions);
+                let snapshot = self.current_surface.surface.image_snapshot();
+
+                if top == 0
+                    && bottom == self.grid_size.height
+                    && left == 0
+                    && right == self.grid_size.width
+                    && cols == 0
+                {
+                    // The whole window scrolled, so the lines leaving the grid move into the
+                    // scrollback instead of being dropped. The following viewport event then
+                    // animates the scroll without losing them
+                    self.pending_scroll_rows += rows;
+
+                    let canvas = self.current_surface.surface.canvas();
+                    canvas.clear(grid_renderer.get_default_background());
+                    canvas.draw_image(
+                        snapshot,
+                        (0.0, -rows as f32 * font_height as f32),
+                        Some(&grid_renderer.paint),
+                    );
+                } else {
+                    let scrolled_region = Rect::new(
+                        (left * font_width) as f32,
+                        (top * font_height) as f32 + scrollback_offset,
+                        (right * font_width) as f32,
+                        (bottom * font_height) as f32 + scrollback_offset,
+                    );
 
-                let mut translated_region = scrolled_region;
-                translated_region.offset((
-                    -cols as f32 * font_width as f32,
-                    -rows as f32 * font_height as f32,
-                ));
+                    let mut translated_region = scrolled_region;
+                    translated_region.offset((
+                        -cols as f32 * font_width as f32,
+                        -rows as f32 * font_height as f32,
+                    ));
 
-                let snapshot = self.current_surface.surface.image_snapshot();
-                let canvas = self.current_surface.surface.canvas();
+                    let canvas = self.current_surface.surface.canvas();
 
-                canvas.save();
+                    canvas.save();
 
-                canvas.clip_rect(scrolled_region, None, Some(false));
-                canvas.draw_image_rect(
-                    snapshot,
-                    Some((&scrolled_region, SrcRectConstraint::Fast)),
-                    translated_region,
-                    &grid_renderer.paint,
-                );
+                    canvas.clip_rect(scrolled_region, None, Some(false));
+                    canvas.draw_image_rect(
+                        snapshot,
+                        Some((&scrolled_region, SrcRectConstraint::Fast)),
+                        translated_region,
+                        &grid_renderer.paint,
+                    );
 
-                canvas.restore();
+                    canvas.restore();
+                }
             }
             WindowDrawCommand::Clear => {
-                self.current_surface.surface = build_window_surface_with_grid_size(
+                self.current_surface = LocatedSurface::new(
                     self.current_surface.surface.canvas(),
                     grid_renderer,
                     self.grid_size,
+                    self.current_surface.top_line,
                 );
-
-                self.snapshots.clear();
             }
             WindowDrawCommand::Show => {
                 if self.hidden {
@@ -497,29 +701,49 @@ impl RenderedWindow {
                 }
             }
             WindowDrawCommand::Hide => self.hidden = true,
+            WindowDrawCommand::Blend(blend) => self.blend = blend,
             WindowDrawCommand::Viewport { top_line, .. } => {
-                if self.current_surface.top_line != top_line as u64 {
-                    let new_snapshot = self.current_surface.snapshot();
-                    self.snapshots.push_back(new_snapshot);
-
-                    if self.snapshots.len() > 5 {
-                        self.snapshots.pop_front();
-                    }
-
-                    if self.position_override.is_none() {
-                        self.position_override = Some(PositionOverride {
-                            top_line: self.current_surface.top_line,
-                            current_scroll: self.current_scroll,
-                        });
+                let top_line = top_line as u64;
+                let old_top_line = self.current_surface.top_line;
+
+                if old_top_line != top_line {
+                    let delta = top_line as i64 - old_top_line as i64;
+                    let scrollback_rows = self.current_surface.scrollback_rows as f32;
+                    let destination = top_line as f32;
+
+                    if delta == self.pending_scroll_rows {
+                        // The scrollback holds the lines that moved, so the animation continues
+                        // from where it currently is, as far as the scrollback reaches
+                        self.start_scroll = self
+                            .current_scroll
+                            .max(destination - scrollback_rows)
+                            .min(destination + scrollback_rows);
+                    } else {
+                        // A jump. The lines in between are unknown, so only a short distance is
+                        // animated to hint at the direction
+                        self.current_surface
+                            .clear_scrollback(grid_renderer, self.grid_size);
+
+                        let far_lines = SETTINGS
+                            .get::<RendererSettings>()
+                            .scroll_animation_far_lines;
+                        let distance = delta
+                            .unsigned_abs()
+                            .min(far_lines as u64)
+                            .min(self.current_surface.scrollback_rows);
+                        self.start_scroll = destination - delta.signum() as f32 * distance as f32;
+                        self.jumped_rows += delta - delta.signum() * distance as i64;
                     }
 
-                    self.current_surface.top_line = top_line as u64;
+                    self.current_surface.top_line = top_line;
 
                     // Set new target viewport position and initialize animation timer
-                    self.start_scroll = self.current_scroll;
-                    self.scroll_destination = top_line as f32;
+                    self.current_scroll = self.start_scroll;
+                    self.scroll_destination = destination;
                     self.scroll_t = 0.0;
                 }
+
+                self.pending_scroll_rows = 0;
             }
             _ => {}
         };
@@ -0,0 +1,367 @@
+use log::error;
+use nvim_rs::Value;
+use skia_safe::{BlurStyle, Canvas, ClipOp, Color, MaskFilter, Paint, RRect, Rect};
+
+use crate::{editor::MESSAGE_GRID_SORT_ORDER, renderer::RendererSettings, settings::*};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowColor(pub Color);
+
+impl ParseFromValue for ShadowColor {
+    fn parse_from_value(&mut self, value: Value) {
+        if value.is_str() {
+            match csscolorparser::parse(value.as_str().unwrap()) {
+                Ok(color) => {
+                    let [red, green, blue, alpha] = color.to_rgba8();
+                    *self = ShadowColor(Color::from_argb(alpha, red, green, blue));
+                }
+                Err(error) => error!("Expected a color, but received {:?}: {}", value, error),
+            }
+        } else {
+            error!("Expected a color string, but received {:?}", value);
+        }
+    }
+}
+
+impl From<ShadowColor> for Value {
+    fn from(ShadowColor(color): ShadowColor) -> Self {
+        Value::from(format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            color.r(),
+            color.g(),
+            color.b(),
+            color.a()
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    pub offset: (f32, f32),
+    pub blur_radius: f32,
+    pub color: Color,
+}
+
+/// Shadow and corner rounding drawn around a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowDecorations {
+    pub shadow: Option<Shadow>,
+    pub corner_radius: f32,
+}
+
+impl WindowDecorations {
+    /// Picks the decorations for a window. Only floating windows are decorated, and only if their
+    /// zindex is at least `floating_decorations_min_zindex`, which allows opting single windows in
+    /// or out by giving them a different zindex. The message grid floats above everything, but is
+    /// docked to the bottom of the editor and never decorated.
+    pub fn for_window(settings: &RendererSettings, floating_order: Option<u64>) -> Self {
+        match floating_order {
+            Some(zindex)
+                if zindex != MESSAGE_GRID_SORT_ORDER
+                    && zindex >= settings.floating_decorations_min_zindex =>
+            {
+                let shadow = settings.floating_shadow.then(|| Shadow {
+                    offset: (
+                        settings.floating_shadow_offset_x,
+                        settings.floating_shadow_offset_y,
+                    ),
+                    blur_radius: settings.floating_shadow_blur_radius.max(0.0),
+                    color: settings.floating_shadow_color.0,
+                });
+
+                WindowDecorations {
+                    shadow,
+                    corner_radius: settings.floating_corner_radius.max(0.0),
+                }
+            }
+            _ => WindowDecorations::default(),
+        }
+    }
+
+    fn rounded_region(&self, region: Rect) -> RRect {
+        RRect::new_rect_xy(region, self.corner_radius, self.corner_radius)
+    }
+
+    /// Area covered by the window region together with its shadow.
+    pub fn bounds(&self, region: Rect) -> Rect {
+        let mut bounds = region;
+        if let Some(shadow) = &self.shadow {
+            let mut shadow_bounds = region.with_offset(shadow.offset);
+            shadow_bounds.outset((shadow.blur_radius, shadow.blur_radius));
+            bounds.join(shadow_bounds);
+        }
+        bounds
+    }
+
+    /// Restricts drawing to the window region, cutting off the rounded corners.
+    pub fn clip(&self, canvas: &mut Canvas, region: Rect) {
+        if self.corner_radius > 0.0 {
+            canvas.clip_rrect(self.rounded_region(region), None, Some(true));
+        } else {
+            canvas.clip_rect(&region, None, Some(false));
+        }
+    }
+
+    /// Draws the shadow beneath the window region. Has to be called before clipping to the region.
+    pub fn draw_shadow(&self, canvas: &mut Canvas, region: Rect) {
+        let shadow = match &self.shadow {
+            Some(shadow) => shadow,
+            None => return,
+        };
+
+        canvas.save();
+        // Transparent windows shouldn't be darkened by their own shadow
+        canvas.clip_rrect(self.rounded_region(region), ClipOp::Difference, Some(true));
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        paint.set_color(shadow.color);
+        if shadow.blur_radius > 0.0 {
+            // Skia blurs by standard deviation, which is roughly half of the visible radius
+            paint.set_mask_filter(MaskFilter::blur(
+                BlurStyle::Normal,
+                shadow.blur_radius / 2.0,
+                None,
+            ));
+        }
+
+        canvas.draw_rrect(
+            self.rounded_region(region.with_offset(shadow.offset)),
+            &paint,
+        );
+        canvas.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::PathBuf};
+
+    use skia_safe::{
+        image::CachingHint, AlphaType, ColorType, Data, EncodedImageFormat, IPoint, Image,
+        ImageInfo, Surface,
+    };
+
+    const SHADOW: Shadow = Shadow {
+        offset: (4.0, 6.0),
+        blur_radius: 8.0,
+        color: Color::from_argb(128, 0, 0, 0),
+    };
+
+    fn golden_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/renderer/goldens")
+            .join(format!("{}.png", name))
+    }
+
+    fn unpremultiplied_pixels(image: &Image) -> Vec<u8> {
+        let info = ImageInfo::new(
+            image.dimensions(),
+            ColorType::RGBA8888,
+            AlphaType::Unpremul,
+            None,
+        );
+        let mut pixels = vec![0u8; info.compute_min_byte_size()];
+        assert!(image.read_pixels(
+            &info,
+            pixels.as_mut_slice(),
+            info.min_row_bytes(),
+            IPoint::new(0, 0),
+            CachingHint::Allow,
+        ));
+        pixels
+    }
+
+    // Draws a window the way RenderedWindow does, on a raster surface
+    fn render(decorations: &WindowDecorations) -> Image {
+        let mut surface = Surface::new_raster_n32_premul((64, 64)).unwrap();
+        let canvas = surface.canvas();
+        canvas.clear(Color::WHITE);
+
+        let region = Rect::from_xywh(12.0, 12.0, 32.0, 24.0);
+        decorations.draw_shadow(canvas, region);
+
+        canvas.save();
+        decorations.clip(canvas, region);
+        let mut paint = Paint::default();
+        paint.set_color(Color::from_rgb(40, 80, 160));
+        canvas.draw_rect(region, &paint);
+        canvas.restore();
+
+        surface.image_snapshot()
+    }
+
+    // Compares the rendered image with the stored golden image. Setting NEOVIDE_UPDATE_GOLDENS
+    // records the goldens instead, after an intended change or for a new test.
+    fn assert_matches_golden(name: &str, image: &Image) {
+        let path = golden_path(name);
+
+        if env::var_os("NEOVIDE_UPDATE_GOLDENS").is_some() {
+            let data = image.encode_to_data(EncodedImageFormat::PNG).unwrap();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, data.as_bytes()).unwrap();
+            return;
+        }
+
+        assert!(
+            path.exists(),
+            "{} has no golden image at {}, run the tests with NEOVIDE_UPDATE_GOLDENS=1 to record it",
+            name,
+            path.display()
+        );
+
+        let golden_data = Data::new_copy(&fs::read(&path).unwrap());
+        let golden = Image::from_encoded(golden_data).unwrap();
+        assert_eq!(golden.dimensions(), image.dimensions(), "{}", name);
+
+        // Allow for rounding differences between skia versions and platforms
+        let differing_pixels = unpremultiplied_pixels(&golden)
+            .chunks(4)
+            .zip(unpremultiplied_pixels(image).chunks(4))
+            .filter(|(expected, actual)| {
+                expected
+                    .iter()
+                    .zip(actual.iter())
+                    .any(|(expected, actual)| (*expected as i16 - *actual as i16).abs() > 2)
+            })
+            .count();
+        assert_eq!(
+            differing_pixels, 0,
+            "{} differs from its golden image",
+            name
+        );
+    }
+
+    #[test]
+    fn test_golden_undecorated() {
+        assert_matches_golden("undecorated", &render(&WindowDecorations::default()));
+    }
+
+    #[test]
+    fn test_golden_shadow() {
+        let decorations = WindowDecorations {
+            shadow: Some(SHADOW),
+            corner_radius: 0.0,
+        };
+        assert_matches_golden("shadow", &render(&decorations));
+    }
+
+    #[test]
+    fn test_golden_rounded_corners() {
+        let decorations = WindowDecorations {
+            shadow: None,
+            corner_radius: 6.0,
+        };
+        assert_matches_golden("rounded_corners", &render(&decorations));
+    }
+
+    #[test]
+    fn test_golden_shadow_with_rounded_corners() {
+        let decorations = WindowDecorations {
+            shadow: Some(SHADOW),
+            corner_radius: 6.0,
+        };
+        assert_matches_golden("shadow_with_rounded_corners", &render(&decorations));
+    }
+
+    #[test]
+    fn test_shadow_stays_outside_of_window() {
+        let decorations = WindowDecorations {
+            shadow: Some(SHADOW),
+            corner_radius: 0.0,
+        };
+        let pixels = unpremultiplied_pixels(&render(&decorations));
+        let pixel = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..(y * 64 + x) * 4 + 4];
+
+        // Inside the window the color is untouched
+        assert_eq!(pixel(40, 32), &[40, 80, 160, 255]);
+        // Just below the window the shadow darkens the background
+        assert!(pixel(28, 38)[0] < 255);
+        // Far away from the window nothing is drawn
+        assert_eq!(pixel(2, 2), &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_decorations_only_for_floating_windows() {
+        let settings = RendererSettings {
+            floating_shadow: true,
+            floating_corner_radius: 4.0,
+            ..RendererSettings::default()
+        };
+
+        assert_eq!(
+            WindowDecorations::for_window(&settings, None),
+            WindowDecorations::default()
+        );
+        assert_eq!(
+            WindowDecorations::for_window(&settings, Some(50)).corner_radius,
+            4.0
+        );
+        assert!(WindowDecorations::for_window(&settings, Some(50))
+            .shadow
+            .is_some());
+    }
+
+    #[test]
+    fn test_decorations_min_zindex() {
+        let settings = RendererSettings {
+            floating_shadow: true,
+            floating_corner_radius: 4.0,
+            floating_decorations_min_zindex: 100,
+            ..RendererSettings::default()
+        };
+
+        assert_eq!(
+            WindowDecorations::for_window(&settings, Some(50)),
+            WindowDecorations::default()
+        );
+        assert_eq!(
+            WindowDecorations::for_window(&settings, Some(100)).corner_radius,
+            4.0
+        );
+    }
+
+    #[test]
+    fn test_message_grid_is_not_decorated() {
+        let settings = RendererSettings {
+            floating_shadow: true,
+            floating_corner_radius: 4.0,
+            ..RendererSettings::default()
+        };
+
+        assert_eq!(
+            WindowDecorations::for_window(&settings, Some(MESSAGE_GRID_SORT_ORDER)),
+            WindowDecorations::default()
+        );
+    }
+
+    #[test]
+    fn test_bounds_include_shadow() {
+        let region = Rect::from_xywh(10.0, 10.0, 20.0, 20.0);
+
+        assert_eq!(WindowDecorations::default().bounds(region), region);
+
+        let decorations = WindowDecorations {
+            shadow: Some(SHADOW),
+            corner_radius: 0.0,
+        };
+        assert_eq!(decorations.bounds(region), Rect::new(6.0, 8.0, 42.0, 44.0));
+    }
+
+    #[test]
+    fn test_parse_shadow_color() {
+        let mut color = ShadowColor(Color::BLACK);
+
+        color.parse_from_value(Value::from("#ff000080"));
+        assert_eq!(color, ShadowColor(Color::from_argb(128, 255, 0, 0)));
+
+        color.parse_from_value(Value::from("not a color"));
+        assert_eq!(color, ShadowColor(Color::from_argb(128, 255, 0, 0)));
+
+        color.parse_from_value(Value::from(1));
+        assert_eq!(color, ShadowColor(Color::from_argb(128, 255, 0, 0)));
+
+        assert_eq!(Value::from(color), Value::from("#ff000080"));
+    }
+}
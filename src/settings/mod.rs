@@ -14,8 +14,8 @@ use std::{
 use crate::{bridge::TxWrapper, error_handling::ResultPanicExplanation};
 pub use from_value::ParseFromValue;
 pub use window_geometry::{
-    load_last_window_settings, parse_window_geometry, save_window_geometry,
-    PersistentWindowSettings, DEFAULT_WINDOW_GEOMETRY,
+    load_last_window_settings, parse_profile, parse_window_geometry, restore_last_session,
+    save_window_geometry, PersistentSession, PersistentWindowSettings, DEFAULT_WINDOW_GEOMETRY,
 };
 
 lazy_static! {
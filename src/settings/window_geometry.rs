@@ -1,11 +1,18 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use glutin::dpi::PhysicalPosition;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::{dimensions::Dimensions, settings::SETTINGS, window::WindowSettings};
+use crate::{
+    cmd_line::CmdLineSettings, dimensions::Dimensions, settings::SETTINGS, window::WindowSettings,
+};
+
+const SETTINGS_FILE: &str = "neovide-settings";
 
-const SETTINGS_FILE: &str = "neovide-settings.json";
+// Version 1 only held the window geometry and had no version field. Version 2 added the session,
+// version 3 the window placement for each monitor layout.
+const SETTINGS_VERSION: u64 = 3;
 
 pub const DEFAULT_WINDOW_GEOMETRY: Dimensions = Dimensions {
     width: 100,
@@ -27,9 +34,32 @@ fn default_size() -> Dimensions {
     DEFAULT_WINDOW_GEOMETRY
 }
 
-#[derive(Serialize, Deserialize)]
+/// Where the window was the last time Neovide ran on an arrangement of monitors.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum PersistentWindowPlacement {
+    Maximized,
+    Windowed { position: PhysicalPosition<i32> },
+}
+
+/// The state of the last session besides the window geometry. Only the parts enabled by the
+/// `remember_*` settings are saved.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct PersistentSession {
+    pub working_directory: Option<PathBuf>,
+    pub zoom: Option<f32>,
+    pub fullscreen: bool,
+    pub monitor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 struct PersistentSettings {
+    version: u64,
     window: PersistentWindowSettings,
+    #[serde(default)]
+    monitor_layouts: BTreeMap<String, PersistentWindowPlacement>,
+    #[serde(default)]
+    session: PersistentSession,
 }
 
 #[cfg(windows)]
@@ -45,20 +75,102 @@ fn neovim_std_datapath() -> PathBuf {
     xdg_dirs.get_data_home()
 }
 
-fn settings_path() -> PathBuf {
+fn settings_file_name(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{}-{}.json", SETTINGS_FILE, profile),
+        None => format!("{}.json", SETTINGS_FILE),
+    }
+}
+
+fn settings_path(profile: Option<&str>) -> PathBuf {
     let mut settings_path = neovim_std_datapath();
-    settings_path.push(SETTINGS_FILE);
+    settings_path.push(settings_file_name(profile));
     settings_path
 }
 
-fn load_settings() -> Result<PersistentSettings, String> {
-    let settings_path = settings_path();
+fn parse_settings(json: &str) -> Result<PersistentSettings, String> {
+    let mut settings: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let version = match settings.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("Invalid settings version: {}", version))?,
+        None => 1,
+    };
+
+    match version {
+        1 | 2 => {
+            if let Value::Object(fields) = &mut settings {
+                fields.insert("version".to_owned(), Value::from(SETTINGS_VERSION));
+            }
+        }
+        SETTINGS_VERSION => {}
+        _ => return Err(format!("Unsupported settings version: {}", version)),
+    }
+
+    serde_json::from_value(settings).map_err(|e| e.to_string())
+}
+
+fn load_settings(profile: Option<&str>) -> Result<PersistentSettings, String> {
+    let settings_path = settings_path(profile);
     let json = std::fs::read_to_string(&settings_path).map_err(|e| e.to_string())?;
-    serde_json::from_str(&json).map_err(|e| e.to_string())
+    parse_settings(&json)
 }
 
-pub fn load_last_window_settings() -> Result<PersistentWindowSettings, String> {
-    let settings = load_settings()?;
+fn load_current_profile_settings() -> Result<PersistentSettings, String> {
+    load_settings(SETTINGS.get::<CmdLineSettings>().profile.as_deref())
+}
+
+/// Loads what the last session of the profile saved, so the bridge and the window can restore it.
+/// Has to run before the bridge starts, which hands the fullscreen state over to Neovim.
+pub fn restore_last_session() {
+    let session = load_current_profile_settings()
+        .map(|settings| settings.session)
+        .unwrap_or_default();
+    log::debug!("Loaded session: {:?}", session);
+
+    if session.fullscreen {
+        let mut window_settings = SETTINGS.get::<WindowSettings>();
+        window_settings.fullscreen = true;
+        SETTINGS.set(&window_settings);
+    }
+
+    SETTINGS.set(&session);
+}
+
+/// Loads the window geometry of the last session, placed where the window last was on the given
+/// monitor layout.
+pub fn load_last_window_settings(monitor_layout: &str) -> Result<PersistentWindowSettings, String> {
+    let settings = load_current_profile_settings()?;
+    let placement = settings.monitor_layouts.get(monitor_layout).copied();
+    let window_settings = load_window_settings(settings)?;
+
+    Ok(place_window(window_settings, placement))
+}
+
+// The size in characters follows the last session on any layout, so it matches the size
+// Neovim was started with
+fn place_window(
+    window_settings: PersistentWindowSettings,
+    placement: Option<PersistentWindowPlacement>,
+) -> PersistentWindowSettings {
+    match (placement, window_settings) {
+        (Some(PersistentWindowPlacement::Maximized), _) => PersistentWindowSettings::Maximized,
+        (
+            Some(PersistentWindowPlacement::Windowed { position }),
+            PersistentWindowSettings::Windowed { size, .. },
+        ) => PersistentWindowSettings::Windowed { position, size },
+        (
+            Some(PersistentWindowPlacement::Windowed { position }),
+            PersistentWindowSettings::Maximized,
+        ) => PersistentWindowSettings::Windowed {
+            position,
+            size: DEFAULT_WINDOW_GEOMETRY,
+        },
+        (None, window_settings) => window_settings,
+    }
+}
+
+fn load_window_settings(settings: PersistentSettings) -> Result<PersistentWindowSettings, String> {
     let mut loaded_settings = settings.window;
     log::debug!("Loaded window settings: {:?}", loaded_settings);
 
@@ -75,10 +187,32 @@ pub fn save_window_geometry(
     maximized: bool,
     grid_size: Option<Dimensions>,
     position: Option<PhysicalPosition<i32>>,
+    monitor_layout: Option<String>,
+    session: PersistentSession,
 ) {
     let window_settings = SETTINGS.get::<WindowSettings>();
 
+    // Keep the placements on the other monitor layouts
+    let mut monitor_layouts = load_current_profile_settings()
+        .map(|settings| settings.monitor_layouts)
+        .unwrap_or_default();
+    if let Some(monitor_layout) = monitor_layout {
+        let placement = if maximized && window_settings.remember_window_size {
+            Some(PersistentWindowPlacement::Maximized)
+        } else if window_settings.remember_window_position {
+            position.map(|position| PersistentWindowPlacement::Windowed { position })
+        } else {
+            None
+        };
+
+        match placement {
+            Some(placement) => monitor_layouts.insert(monitor_layout, placement),
+            None => monitor_layouts.remove(&monitor_layout),
+        };
+    }
+
     let settings = PersistentSettings {
+        version: SETTINGS_VERSION,
         window: if maximized && window_settings.remember_window_size {
             PersistentWindowSettings::Maximized
         } else {
@@ -99,17 +233,47 @@ pub fn save_window_geometry(
                 },
             }
         },
+        monitor_layouts,
+        session: PersistentSession {
+            working_directory: session
+                .working_directory
+                .filter(|_| window_settings.remember_working_directory),
+            zoom: session.zoom.filter(|_| window_settings.remember_zoom),
+            fullscreen: session.fullscreen && window_settings.remember_fullscreen,
+            monitor: session.monitor.filter(|_| window_settings.remember_monitor),
+        },
     };
 
-    let settings_path = settings_path();
+    let settings_path = settings_path(SETTINGS.get::<CmdLineSettings>().profile.as_deref());
     std::fs::create_dir_all(neovim_std_datapath()).unwrap();
     let json = serde_json::to_string(&settings).unwrap();
     log::debug!("Saved Window Settings: {}", json);
     std::fs::write(settings_path, json).unwrap();
 }
 
-pub fn parse_window_geometry(geometry: Option<String>) -> Result<Dimensions, String> {
-    let saved_window_size = load_last_window_settings()
+pub fn parse_profile(profile: Option<String>) -> Result<Option<String>, String> {
+    match profile {
+        Some(profile)
+            if profile.is_empty()
+                || !profile
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            Err(format!(
+                "Invalid profile: {}\nProfiles may only contain letters, digits, '-' and '_'",
+                profile
+            ))
+        }
+        profile => Ok(profile),
+    }
+}
+
+pub fn parse_window_geometry(
+    geometry: Option<String>,
+    profile: Option<&str>,
+) -> Result<Dimensions, String> {
+    let saved_window_size = load_settings(profile)
+        .and_then(load_window_settings)
         .and_then(|window_settings| {
             if let PersistentWindowSettings::Windowed { size, .. } = window_settings {
                 Ok(size)
@@ -150,3 +314,130 @@ pub fn parse_window_geometry(geometry: Option<String>) -> Result<Dimensions, Str
             .map_err(|msg| msg.to_owned())
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrates_unversioned_settings() {
+        let settings = parse_settings(
+            r#"{"window":{"Windowed":{"position":{"x":10,"y":20},"size":{"width":80,"height":24}}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.session, PersistentSession::default());
+        assert!(matches!(
+            settings.window,
+            PersistentWindowSettings::Windowed {
+                position: PhysicalPosition { x: 10, y: 20 },
+                size: Dimensions {
+                    width: 80,
+                    height: 24
+                },
+            }
+        ));
+    }
+
+    #[test]
+    fn test_round_trips_session() {
+        let session = PersistentSession {
+            working_directory: Some(PathBuf::from("/home/neovide/project")),
+            zoom: Some(1.5),
+            fullscreen: true,
+            monitor: Some("DP-1".to_owned()),
+        };
+        let mut monitor_layouts = BTreeMap::new();
+        monitor_layouts.insert(
+            "1920x1080+0+0".to_owned(),
+            PersistentWindowPlacement::Windowed {
+                position: PhysicalPosition::new(10, 20),
+            },
+        );
+        let json = serde_json::to_string(&PersistentSettings {
+            version: SETTINGS_VERSION,
+            window: PersistentWindowSettings::Maximized,
+            monitor_layouts: monitor_layouts.clone(),
+            session: session.clone(),
+        })
+        .unwrap();
+
+        let settings = parse_settings(&json).unwrap();
+        assert_eq!(settings.session, session);
+        assert_eq!(settings.monitor_layouts, monitor_layouts);
+        assert!(matches!(
+            settings.window,
+            PersistentWindowSettings::Maximized
+        ));
+    }
+
+    #[test]
+    fn test_migrates_settings_without_monitor_layouts() {
+        let settings =
+            parse_settings(r#"{"version":2,"window":"Maximized","session":{"zoom":1.5}}"#).unwrap();
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(settings.monitor_layouts.is_empty());
+        assert_eq!(settings.session.zoom, Some(1.5));
+    }
+
+    #[test]
+    fn test_places_window_for_monitor_layout() {
+        let windowed = || PersistentWindowSettings::Windowed {
+            position: PhysicalPosition::new(2000, 0),
+            size: Dimensions {
+                width: 80,
+                height: 24,
+            },
+        };
+
+        assert!(matches!(
+            place_window(windowed(), None),
+            PersistentWindowSettings::Windowed {
+                position: PhysicalPosition { x: 2000, y: 0 },
+                ..
+            }
+        ));
+        assert!(matches!(
+            place_window(windowed(), Some(PersistentWindowPlacement::Maximized)),
+            PersistentWindowSettings::Maximized
+        ));
+        assert!(matches!(
+            place_window(
+                windowed(),
+                Some(PersistentWindowPlacement::Windowed {
+                    position: PhysicalPosition::new(10, 20)
+                })
+            ),
+            PersistentWindowSettings::Windowed {
+                position: PhysicalPosition { x: 10, y: 20 },
+                size: Dimensions {
+                    width: 80,
+                    height: 24
+                },
+            }
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        assert!(parse_settings(r#"{"version":99,"window":"Maximized"}"#).is_err());
+        assert!(parse_settings(r#"{"version":"2","window":"Maximized"}"#).is_err());
+    }
+
+    #[test]
+    fn test_profile_file_names() {
+        assert_eq!(settings_file_name(None), "neovide-settings.json");
+        assert_eq!(
+            settings_file_name(Some("work")),
+            "neovide-settings-work.json"
+        );
+        assert_eq!(
+            parse_profile(Some("work_2-a".to_owned())),
+            Ok(Some("work_2-a".to_owned()))
+        );
+        assert!(parse_profile(Some("../work".to_owned())).is_err());
+        assert!(parse_profile(Some(String::new())).is_err());
+    }
+}
@@ -5,26 +5,58 @@ use cocoa::appkit::{NSColor, NSWindow};
 use cocoa::base::{id, nil};
 use csscolorparser::Color;
 use glutin::platform::macos::WindowExtMacOS;
-use objc::{rc::autoreleasepool, runtime::YES};
+use objc::{
+    rc::autoreleasepool,
+    runtime::{NO, YES},
+};
 
-pub fn draw_background(window: &WindowedContext<PossiblyCurrent>) {
-    if let Ok(color) = &SETTINGS
-        .get::<WindowSettings>()
-        .background_color
-        .parse::<Color>()
-    {
-        autoreleasepool(|| unsafe {
-            let [red, green, blue, alpha] = color.to_array();
-            let ns_window: id = window.window().ns_window() as id;
-            let ns_background = NSColor::colorWithSRGBRed_green_blue_alpha_(
-                nil,
-                red.into(),
-                green.into(),
-                blue.into(),
-                alpha.into(),
-            );
-            ns_window.setBackgroundColor_(ns_background);
-            ns_window.setTitlebarAppearsTransparent_(YES);
-        });
+/// The color of the whole window, title bar included, as last applied to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowBackground {
+    rgba: [f64; 4],
+    transparent_titlebar: bool,
+}
+
+/// Computes the window background from `g:neovide_background_color` if it is set, or from the
+/// default background of the colorscheme and `g:neovide_transparency` otherwise.
+pub fn window_background(default_background: skia_safe::Color) -> WindowBackground {
+    let settings = SETTINGS.get::<WindowSettings>();
+    let (color, transparent_titlebar) = match settings.background_color.parse::<Color>() {
+        Ok(color) => (color, true),
+        Err(_) => (
+            Color::from_rgba8(
+                default_background.r(),
+                default_background.g(),
+                default_background.b(),
+                (settings.transparency * 255.0) as u8,
+            ),
+            false,
+        ),
     };
+
+    WindowBackground {
+        rgba: color.to_array(),
+        transparent_titlebar,
+    }
+}
+
+pub fn draw_background(window: &WindowedContext<PossiblyCurrent>, background: &WindowBackground) {
+    autoreleasepool(|| unsafe {
+        let [red, green, blue, alpha] = background.rgba;
+        let ns_window: id = window.window().ns_window() as id;
+        let ns_background = NSColor::colorWithSRGBRed_green_blue_alpha_(
+            nil,
+            red.into(),
+            green.into(),
+            blue.into(),
+            alpha.into(),
+        );
+        ns_window.setBackgroundColor_(ns_background);
+        // Give the title bar back when g:neovide_background_color is cleared
+        ns_window.setTitlebarAppearsTransparent_(if background.transparent_titlebar {
+            YES
+        } else {
+            NO
+        });
+    });
 }
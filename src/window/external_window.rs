@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use glutin::{
+    dpi::PhysicalSize,
+    event::{Event, WindowEvent},
+    event_loop::EventLoopWindowTarget,
+    window::{self, WindowId},
+    ContextBuilder, GlProfile,
+};
+use log::error;
+
+use super::{
+    gl_context::GlContext, keyboard_manager::KeyboardManager, load_icon,
+    mouse_manager::MouseManager, renderer::SkiaRenderer,
+};
+use crate::{
+    bridge::{ParallelCommand, UiCommand},
+    cmd_line::CmdLineSettings,
+    dimensions::Dimensions,
+    editor::EditorCommand,
+    event_aggregator::EVENT_AGGREGATOR,
+    redraw_scheduler::REDRAW_SCHEDULER,
+    renderer::{DrawCommand, Renderer, WindowDrawCommand},
+    settings::SETTINGS,
+};
+
+/// A Neovim window moved out of the editor with `nvim_win_set_config`, shown in a top-level
+/// window of its own.
+pub struct ExternalWindow {
+    grid_id: u64,
+    window_handle: u64,
+    // Declared before the context they were created in, so that they are dropped first
+    renderer: Renderer,
+    skia_renderer: SkiaRenderer,
+    windowed_context: GlContext,
+    keyboard_manager: KeyboardManager,
+    mouse_manager: MouseManager,
+    surface_size: PhysicalSize<u32>,
+    saved_inner_size: PhysicalSize<u32>,
+    saved_grid_size: Option<Dimensions>,
+    requested_grid_size: Option<Dimensions>,
+}
+
+impl Drop for ExternalWindow {
+    fn drop(&mut self) {
+        // The renderers free their GPU resources when dropped, which has to happen in the context
+        // they belong to rather than whichever window was drawn last
+        self.windowed_context.make_current();
+    }
+}
+
+impl ExternalWindow {
+    fn new(
+        grid_id: u64,
+        window_handle: u64,
+        editor_renderer: &Renderer,
+        window_target: &EventLoopWindowTarget<()>,
+    ) -> Option<ExternalWindow> {
+        let cmd_line_settings = SETTINGS.get::<CmdLineSettings>();
+
+        let winit_window_builder = window::WindowBuilder::new()
+            .with_title("Neovide")
+            .with_window_icon(Some(load_icon()))
+            .with_transparent(true);
+
+        // Every window swaps its buffers each frame, so waiting for vsync in more than one of
+        // them would slow down all of them
+        let windowed_context = ContextBuilder::new()
+            .with_pixel_format(24, 8)
+            .with_stencil_buffer(8)
+            .with_gl_profile(GlProfile::Core)
+            .with_vsync(false)
+            .with_srgb(cmd_line_settings.srgb)
+            .build_windowed(winit_window_builder, window_target)
+            .map_err(|error| error!("Could not create external window: {}", error))
+            .ok()?;
+        let windowed_context = unsafe { windowed_context.make_current() }
+            .map_err(|(_, error)| error!("Could not use external window: {}", error))
+            .ok()?;
+
+        let window = windowed_context.window();
+        let surface_size = window.inner_size();
+        let renderer = editor_renderer.new_external(grid_id, window.scale_factor());
+        let skia_renderer = SkiaRenderer::new(&windowed_context);
+
+        Some(ExternalWindow {
+            grid_id,
+            window_handle,
+            renderer,
+            skia_renderer,
+            windowed_context: GlContext::new(windowed_context),
+            keyboard_manager: KeyboardManager::new(),
+            mouse_manager: MouseManager::new(),
+            surface_size,
+            saved_inner_size: surface_size,
+            saved_grid_size: None,
+            requested_grid_size: None,
+        })
+    }
+
+    fn window_id(&self) -> WindowId {
+        self.windowed_context.window().id()
+    }
+
+    fn handle_window_draw_command(&mut self, command: WindowDrawCommand) {
+        match &command {
+            WindowDrawCommand::Position { grid_size, .. } => {
+                self.requested_grid_size = Some((*grid_size).into());
+            }
+            WindowDrawCommand::Show => self.windowed_context.window().set_visible(true),
+            WindowDrawCommand::Hide => self.windowed_context.window().set_visible(false),
+            _ => {}
+        }
+
+        self.renderer.queue_draw_command(DrawCommand::Window {
+            grid_id: self.grid_id,
+            command,
+        });
+    }
+
+    fn handle_event(&mut self, event: &Event<()>) {
+        self.keyboard_manager.handle_event(event);
+        self.mouse_manager.handle_event(
+            event,
+            &self.keyboard_manager,
+            &self.renderer,
+            &self.windowed_context,
+        );
+        self.renderer.separator_highlight = self.mouse_manager.separator_highlight();
+        if let Some(font_zoom) = self.mouse_manager.take_font_zoom() {
+            self.handle_font_zoom_update(font_zoom);
+        }
+        self.renderer.handle_event(event);
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                // The window goes away once Neovim closes it
+                EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::CloseWindow {
+                    window_handle: self.window_handle,
+                }));
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { scale_factor, .. },
+                ..
+            } => {
+                self.renderer
+                    .grid_renderer
+                    .handle_scale_factor_update(*scale_factor);
+                EVENT_AGGREGATOR.send(EditorCommand::RedrawScreen);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } => {
+                let file_path = path.to_string_lossy().into_owned();
+                EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::FileDrop(file_path)));
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focus),
+                ..
+            } => {
+                if *focus {
+                    EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::FocusGained));
+                    REDRAW_SCHEDULER.queue_next_frame();
+                } else {
+                    EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::FocusLost));
+                }
+            }
+            Event::RedrawRequested(..) | Event::WindowEvent { .. } => {
+                REDRAW_SCHEDULER.queue_next_frame()
+            }
+            _ => {}
+        }
+    }
+
+    // Zooming only changes the font size in this window, which keeps its size and fits a grid of a
+    // different size into it instead
+    fn handle_font_zoom_update(&mut self, font_zoom: f32) {
+        self.renderer.grid_renderer.handle_zoom_update(font_zoom);
+        self.handle_new_grid_size(self.saved_inner_size);
+        EVENT_AGGREGATOR.send(EditorCommand::RedrawScreen);
+    }
+
+    fn handle_new_grid_size(&mut self, new_size: PhysicalSize<u32>) {
+        let grid_size = self
+            .renderer
+            .grid_renderer
+            .convert_physical_to_grid(new_size);
+        if self.saved_grid_size != Some(grid_size) {
+            self.saved_grid_size = Some(grid_size);
+            EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::ResizeGrid {
+                grid: self.grid_id,
+                width: grid_size.width,
+                height: grid_size.height,
+            }));
+        }
+    }
+
+    fn draw_frame(&mut self, dt: f32, should_draw: bool) {
+        self.windowed_context.make_current();
+
+        let mut font_changed = false;
+        if should_draw {
+            font_changed = self.renderer.draw_frame(self.skia_renderer.canvas(), dt);
+            self.skia_renderer.gr_context.flush(None);
+            self.windowed_context.swap_buffers().unwrap();
+        }
+
+        let new_size = self.windowed_context.window().inner_size();
+        if self.surface_size != new_size {
+            self.surface_size = new_size;
+            self.skia_renderer.resize(&self.windowed_context);
+        }
+
+        if !self.renderer.grid_renderer.is_ready {
+            return;
+        }
+
+        // Neovim decides the size of the window, unless it is resized by hand afterwards
+        if let Some(requested_grid_size) = self.requested_grid_size.take() {
+            if self.saved_grid_size != Some(requested_grid_size) {
+                let requested_size = self
+                    .renderer
+                    .grid_renderer
+                    .convert_grid_to_physical(requested_grid_size);
+                self.saved_grid_size = Some(requested_grid_size);
+                self.saved_inner_size = requested_size;
+                self.windowed_context
+                    .window()
+                    .set_inner_size(requested_size);
+                return;
+            }
+        }
+
+        if self.saved_inner_size != new_size || font_changed {
+            self.saved_inner_size = new_size;
+            self.handle_new_grid_size(new_size);
+        }
+    }
+}
+
+/// The top-level windows of every external grid, by grid id.
+#[derive(Default)]
+pub struct ExternalWindows {
+    windows: HashMap<u64, ExternalWindow>,
+}
+
+impl ExternalWindows {
+    /// Opens and closes windows as grids move in and out of the editor, and passes the draw
+    /// commands on to the windows they are meant for.
+    pub fn handle_draw_commands(
+        &mut self,
+        draw_commands: Vec<DrawCommand>,
+        editor_renderer: &Renderer,
+        window_target: &EventLoopWindowTarget<()>,
+    ) {
+        for draw_command in draw_commands {
+            match draw_command {
+                DrawCommand::Window {
+                    grid_id,
+                    command: WindowDrawCommand::External { window_handle },
+                } => {
+                    if self.windows.contains_key(&grid_id) {
+                        continue;
+                    }
+                    if let Some(window) =
+                        ExternalWindow::new(grid_id, window_handle, editor_renderer, window_target)
+                    {
+                        self.windows.insert(grid_id, window);
+                    }
+                }
+                DrawCommand::Window {
+                    grid_id,
+                    command: WindowDrawCommand::Close,
+                } => {
+                    // Dropping the window makes its own context current
+                    self.windows.remove(&grid_id);
+                }
+                DrawCommand::Window { grid_id, command } => {
+                    if let Some(window) = self.windows.get_mut(&grid_id) {
+                        window.handle_window_draw_command(command);
+                    }
+                }
+                draw_command => {
+                    for window in self.windows.values_mut() {
+                        window.renderer.queue_draw_command(draw_command.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles the events of the external windows. Returns the event back if it belongs to the
+    /// editor window instead.
+    pub fn handle_event<'a>(&mut self, event: Event<'a, ()>) -> Option<Event<'a, ()>> {
+        let window_id = match &event {
+            Event::WindowEvent { window_id, .. } | Event::RedrawRequested(window_id) => {
+                Some(*window_id)
+            }
+            _ => None,
+        };
+
+        match window_id {
+            Some(window_id) => {
+                match self
+                    .windows
+                    .values_mut()
+                    .find(|window| window.window_id() == window_id)
+                {
+                    Some(window) => {
+                        window.handle_event(&event);
+                        None
+                    }
+                    None => Some(event),
+                }
+            }
+            None => {
+                // Input is sent and gestures are updated once all events have been handled
+                if let Event::MainEventsCleared = event {
+                    for window in self.windows.values_mut() {
+                        window.handle_event(&event);
+                    }
+                }
+                Some(event)
+            }
+        }
+    }
+
+    pub fn draw_frame(&mut self, dt: f32, should_draw: bool) {
+        for window in self.windows.values_mut() {
+            window.draw_frame(dt, should_draw);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Picks the rate frames are drawn at while focused. With vsync the monitor's own rate is used,
+/// so that every refresh gets exactly one frame, otherwise the configured rate.
+pub fn target_refresh_rate(
+    monitor_refresh_rate: Option<f32>,
+    vsync: bool,
+    configured_refresh_rate: u64,
+) -> f32 {
+    match monitor_refresh_rate {
+        Some(monitor_refresh_rate) if vsync && monitor_refresh_rate > 0.0 => monitor_refresh_rate,
+        _ => configured_refresh_rate as f32,
+    }
+    .max(1.0)
+}
+
+/// Picks an upper bound for the refresh rate of a monitor from the rates of its video modes at its
+/// current resolution. Winit doesn't say which of them is in use, so a 144Hz panel running at 60Hz
+/// reports both. With vsync pacing at the fastest one never throttles, since the buffer swap waits
+/// for the real vertical blank anyway, and the pacer then measures the actual rate from the swaps.
+pub fn fastest_refresh_rate(refresh_rates: impl IntoIterator<Item = u16>) -> Option<f32> {
+    refresh_rates
+        .into_iter()
+        .filter(|rate| *rate > 0)
+        .max()
+        .map(f32::from)
+}
+
+// Enough swaps to ride out a few late frames, while still following a mode change within a second
+const MEASURED_SWAPS: usize = 30;
+
+pub struct FramePacer {
+    vsync: bool,
+    frame_length: Duration,
+    last_frame: Instant,
+    // A late frame only counts as missed when the previous frame asked for the next one right
+    // away. Otherwise nothing was animating and the gap is just idle time.
+    continuous: bool,
+    missed_frames: u64,
+    // Time between consecutive buffer swaps while animating. With vsync each swap blocks until the
+    // next vertical blank, so these are the refresh interval of the mode the monitor really uses.
+    swap_intervals: VecDeque<Duration>,
+    last_swap: Option<Instant>,
+}
+
+impl FramePacer {
+    pub fn new(vsync: bool, refresh_rate: f32, now: Instant) -> FramePacer {
+        FramePacer {
+            vsync,
+            frame_length: Duration::from_secs_f32(1.0 / refresh_rate.max(1.0)),
+            last_frame: now,
+            continuous: false,
+            missed_frames: 0,
+            swap_intervals: VecDeque::with_capacity(MEASURED_SWAPS),
+            last_swap: None,
+        }
+    }
+
+    pub fn set_refresh_rate(&mut self, refresh_rate: f32) {
+        self.frame_length = Duration::from_secs_f32(1.0 / refresh_rate.max(1.0));
+    }
+
+    pub fn refresh_rate(&self) -> f32 {
+        1.0 / self.frame_length.as_secs_f32()
+    }
+
+    pub fn missed_frames(&self) -> u64 {
+        self.missed_frames
+    }
+
+    /// The refresh rate measured from the buffer swaps, once enough frames were drawn back to back
+    /// with vsync. Uses the median interval so that an occasional missed vertical blank doesn't
+    /// halve it.
+    pub fn measured_refresh_rate(&self) -> Option<f32> {
+        if !self.vsync || self.swap_intervals.len() < MEASURED_SWAPS / 2 {
+            return None;
+        }
+
+        let mut intervals: Vec<Duration> = self.swap_intervals.iter().copied().collect();
+        intervals.sort_unstable();
+        let median = intervals[intervals.len() / 2].as_secs_f32();
+
+        (median > 0.0).then(|| 1.0 / median)
+    }
+
+    /// Forgets the measured refresh rate, for example after the window moved to another monitor.
+    pub fn reset_measured_refresh_rate(&mut self) {
+        self.swap_intervals.clear();
+        self.last_swap = None;
+    }
+
+    // With vsync the buffer swap blocks until the next vertical blank. Starting the frame a bit
+    // early makes sure it is ready for that blank, instead of waking up just after it and waiting
+    // a whole extra refresh.
+    fn slack(&self) -> Duration {
+        if self.vsync {
+            self.frame_length / 4
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+
+    pub fn should_start_frame(&self, now: Instant) -> bool {
+        now + self.slack() >= self.last_frame + self.frame_length
+    }
+
+    pub fn next_frame_deadline(&self) -> Instant {
+        self.last_frame + self.frame_length - self.slack()
+    }
+
+    /// Starts a new frame and returns the time since the previous one, in seconds.
+    pub fn start_frame(&mut self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.last_frame);
+
+        if self.continuous {
+            let frames = (elapsed.as_secs_f32() / self.frame_length.as_secs_f32()).round() as u64;
+            self.missed_frames += frames.saturating_sub(1);
+        }
+
+        self.last_frame = now;
+        elapsed.as_secs_f32()
+    }
+
+    /// Records whether the frame that just finished wants to be followed by another one, and when
+    /// its buffers were swapped, if it drew anything.
+    pub fn finish_frame(&mut self, continuous: bool, swapped_at: Option<Instant>) {
+        if let Some(swapped_at) = swapped_at {
+            // Only swaps of frames drawn back to back are a refresh apart
+            if let Some(last_swap) = self.last_swap.filter(|_| self.continuous) {
+                if self.swap_intervals.len() == MEASURED_SWAPS {
+                    self.swap_intervals.pop_front();
+                }
+                self.swap_intervals.push_back(swapped_at.saturating_duration_since(last_swap));
+            }
+            self.last_swap = Some(swapped_at);
+        }
+
+        self.continuous = continuous;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn test_monitor_rate_with_vsync() {
+        assert_eq!(target_refresh_rate(Some(144.0), true, 60), 144.0);
+    }
+
+    #[test]
+    fn test_configured_rate_without_vsync() {
+        assert_eq!(target_refresh_rate(Some(144.0), false, 60), 60.0);
+    }
+
+    #[test]
+    fn test_configured_rate_without_monitor_rate() {
+        assert_eq!(target_refresh_rate(None, true, 60), 60.0);
+        assert_eq!(target_refresh_rate(Some(0.0), true, 60), 60.0);
+        assert_eq!(target_refresh_rate(None, true, 0), 1.0);
+    }
+
+    fn swap_continuously(pacer: &mut FramePacer, start: Instant, interval: Duration, count: u32) {
+        for frame in 0..count {
+            let now = start + interval * frame;
+            pacer.start_frame(now);
+            pacer.finish_frame(true, Some(now));
+        }
+    }
+
+    #[test]
+    fn test_fastest_refresh_rate() {
+        assert_eq!(fastest_refresh_rate([60, 75, 120, 144, 60]), Some(144.0));
+        assert_eq!(fastest_refresh_rate([0, 60]), Some(60.0));
+        assert_eq!(fastest_refresh_rate([0u16; 0]), None);
+        assert_eq!(fastest_refresh_rate([0]), None);
+    }
+
+    #[test]
+    fn test_measures_refresh_rate_of_mixed_rate_monitor() {
+        // A 144Hz panel running at 60Hz lists both rates, so pacing starts at the fastest one and
+        // the vsync'd swaps reveal the real rate
+        let start = Instant::now();
+        let monitor_refresh_rate = fastest_refresh_rate([60, 100, 120, 144]);
+        let mut pacer = FramePacer::new(
+            true,
+            target_refresh_rate(monitor_refresh_rate, true, 60),
+            start,
+        );
+        assert!((pacer.refresh_rate() - 144.0).abs() < 0.01);
+        assert_eq!(pacer.measured_refresh_rate(), None);
+
+        let interval = Duration::from_micros(16_667);
+        swap_continuously(&mut pacer, start, interval, MEASURED_SWAPS as u32);
+        let measured = pacer.measured_refresh_rate();
+        assert!((measured.unwrap() - 60.0).abs() < 0.1);
+
+        pacer.set_refresh_rate(target_refresh_rate(measured.or(monitor_refresh_rate), true, 60));
+        assert!((pacer.refresh_rate() - 60.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_measured_refresh_rate_ignores_missed_blanks_and_idle_gaps() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(true, 144.0, start);
+
+        swap_continuously(&mut pacer, start, ms(10), 10);
+        // One missed vertical blank, then an idle pause that isn't a refresh interval at all
+        pacer.finish_frame(false, Some(start + ms(110)));
+        pacer.finish_frame(true, Some(start + ms(2000)));
+        swap_continuously(&mut pacer, start + ms(2010), ms(10), 10);
+
+        assert!((pacer.measured_refresh_rate().unwrap() - 100.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_no_measured_refresh_rate_without_vsync() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(false, 60.0, start);
+
+        swap_continuously(&mut pacer, start, ms(10), MEASURED_SWAPS as u32);
+        assert_eq!(pacer.measured_refresh_rate(), None);
+    }
+
+    #[test]
+    fn test_reset_measured_refresh_rate() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(true, 144.0, start);
+
+        swap_continuously(&mut pacer, start, ms(10), MEASURED_SWAPS as u32);
+        pacer.reset_measured_refresh_rate();
+        assert_eq!(pacer.measured_refresh_rate(), None);
+    }
+
+    #[test]
+    fn test_waits_for_frame_length_without_vsync() {
+        let start = Instant::now();
+        let pacer = FramePacer::new(false, 100.0, start);
+
+        assert!(!pacer.should_start_frame(start + ms(9)));
+        assert!(pacer.should_start_frame(start + ms(10)));
+        assert!(pacer.next_frame_deadline() > start + ms(9));
+        assert!(pacer.next_frame_deadline() <= start + ms(10));
+    }
+
+    #[test]
+    fn test_starts_early_with_vsync() {
+        let start = Instant::now();
+        let pacer = FramePacer::new(true, 100.0, start);
+
+        assert!(!pacer.should_start_frame(start + ms(7)));
+        assert!(pacer.should_start_frame(start + ms(8)));
+        assert!(pacer.next_frame_deadline() < start + ms(10));
+    }
+
+    #[test]
+    fn test_start_frame_returns_dt() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(false, 100.0, start);
+
+        let dt = pacer.start_frame(start + ms(10));
+        assert!((dt - 0.01).abs() < 0.0001);
+        assert!(!pacer.should_start_frame(start + ms(15)));
+    }
+
+    #[test]
+    fn test_counts_missed_frames_while_animating() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(false, 100.0, start);
+        pacer.finish_frame(true, None);
+
+        pacer.start_frame(start + ms(10));
+        assert_eq!(pacer.missed_frames(), 0);
+
+        pacer.finish_frame(true, None);
+        pacer.start_frame(start + ms(40));
+        assert_eq!(pacer.missed_frames(), 2);
+    }
+
+    #[test]
+    fn test_idle_gaps_are_not_missed_frames() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(false, 100.0, start);
+        pacer.finish_frame(false, None);
+
+        pacer.start_frame(start + ms(500));
+        assert_eq!(pacer.missed_frames(), 0);
+    }
+
+    #[test]
+    fn test_refresh_rate_change() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(false, 60.0, start);
+        pacer.set_refresh_rate(144.0);
+
+        assert!((pacer.refresh_rate() - 144.0).abs() < 0.01);
+        assert!(pacer.should_start_frame(start + ms(7)));
+    }
+}
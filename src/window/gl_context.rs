@@ -0,0 +1,46 @@
+use std::ops::Deref;
+
+use glutin::{PossiblyCurrent, WindowedContext};
+use log::error;
+
+/// The OpenGL context of a top-level window. Every window has its own context, so the one about
+/// to be drawn to is made current first.
+pub struct GlContext {
+    windowed_context: Option<WindowedContext<PossiblyCurrent>>,
+}
+
+impl GlContext {
+    pub fn new(windowed_context: WindowedContext<PossiblyCurrent>) -> GlContext {
+        GlContext {
+            windowed_context: Some(windowed_context),
+        }
+    }
+
+    pub fn make_current(&mut self) {
+        let windowed_context = self.windowed_context.take().unwrap();
+        if windowed_context.is_current() {
+            self.windowed_context = Some(windowed_context);
+            return;
+        }
+
+        let windowed_context = match unsafe { windowed_context.make_current() } {
+            Ok(windowed_context) => windowed_context,
+            Err((windowed_context, context_error)) => {
+                error!(
+                    "Could not make the OpenGL context current: {}",
+                    context_error
+                );
+                windowed_context
+            }
+        };
+        self.windowed_context = Some(windowed_context);
+    }
+}
+
+impl Deref for GlContext {
+    type Target = WindowedContext<PossiblyCurrent>;
+
+    fn deref(&self) -> &Self::Target {
+        self.windowed_context.as_ref().unwrap()
+    }
+}
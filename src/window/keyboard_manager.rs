@@ -21,6 +21,7 @@ pub struct KeyboardManager {
     prev_dead_key: Option<char>,
     logo: bool,
     ignore_input_this_frame: bool,
+    sent_input_this_frame: bool,
     queued_input_events: Vec<InputEvent>,
 }
 
@@ -33,10 +34,21 @@ impl KeyboardManager {
             prev_dead_key: None,
             logo: false,
             ignore_input_this_frame: false,
+            sent_input_this_frame: false,
             queued_input_events: Vec::new(),
         }
     }
 
+    pub fn is_shift_pressed(&self) -> bool {
+        self.shift
+    }
+
+    /// Whether any keyboard input was sent to neovim while handling the last
+    /// `MainEventsCleared` event.
+    pub fn sent_input_this_frame(&self) -> bool {
+        self.sent_input_this_frame
+    }
+
     pub fn handle_event(&mut self, event: &Event<()>) {
         match event {
             Event::WindowEvent {
@@ -78,6 +90,7 @@ impl KeyboardManager {
                 self.logo = modifiers.super_key();
             }
             Event::MainEventsCleared => {
+                self.sent_input_this_frame = false;
                 // If the window wasn't just focused.
                 if !self.should_ignore_input() {
                     // And we have a keyboard event this frame
@@ -91,6 +104,7 @@ impl KeyboardManager {
                                         EVENT_AGGREGATOR.send(UiCommand::Serial(
                                             SerialCommand::Keyboard(keybinding),
                                         ));
+                                        self.sent_input_this_frame = true;
                                     }
                                     next_dead_key = None;
                                 } else if key_event.state == ElementState::Released {
@@ -106,6 +120,7 @@ impl KeyboardManager {
                                     EVENT_AGGREGATOR.send(UiCommand::Serial(
                                         SerialCommand::Keyboard(raw_input.to_string()),
                                     ));
+                                    self.sent_input_this_frame = true;
                                 }
                             }
                         }
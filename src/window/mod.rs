@@ -1,35 +1,50 @@
+mod external_window;
+mod frame_pacing;
+mod gl_context;
 mod keyboard_manager;
+mod monitor_layout;
 mod mouse_manager;
+mod mouse_visibility;
 mod renderer;
 mod settings;
+mod touch_gestures;
 
 #[cfg(target_os = "macos")]
 mod draw_background;
+#[cfg(target_os = "linux")]
+mod x11_background;
 
-use std::time::{Duration, Instant};
+use std::{path::PathBuf, time::Instant};
 
 use glutin::{
     self,
-    dpi::PhysicalSize,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
+    monitor::MonitorHandle,
     window::{self, Fullscreen, Icon},
-    ContextBuilder, GlProfile, WindowedContext,
+    ContextBuilder, GlProfile,
 };
 use log::trace;
-use tokio::sync::mpsc::UnboundedReceiver;
 
 #[cfg(target_os = "macos")]
 use glutin::platform::macos::WindowBuilderExtMacOS;
 
 #[cfg(target_os = "macos")]
-use draw_background::draw_background;
+use draw_background::{draw_background, window_background, WindowBackground};
 
 #[cfg(target_os = "linux")]
 use glutin::platform::unix::WindowBuilderExtUnix;
 
+#[cfg(target_os = "linux")]
+use x11_background::set_x11_background;
+
+use external_window::ExternalWindows;
+use frame_pacing::{fastest_refresh_rate, target_refresh_rate, FramePacer};
+use gl_context::GlContext;
 use image::{load_from_memory, GenericImageView, Pixel};
 use keyboard_manager::KeyboardManager;
+use monitor_layout::{clamp_to_monitors, monitor_bounds, monitor_layout_key};
 use mouse_manager::MouseManager;
 use renderer::SkiaRenderer;
 
@@ -38,13 +53,14 @@ use crate::{
     cmd_line::CmdLineSettings,
     dimensions::Dimensions,
     editor::EditorCommand,
-    event_aggregator::EVENT_AGGREGATOR,
+    event_aggregator::{EventReceiver, EVENT_AGGREGATOR},
     frame::Frame,
     redraw_scheduler::REDRAW_SCHEDULER,
     renderer::Renderer,
     running_tracker::*,
     settings::{
-        load_last_window_settings, save_window_geometry, PersistentWindowSettings, SETTINGS,
+        load_last_window_settings, save_window_geometry, PersistentSession,
+        PersistentWindowSettings, SETTINGS,
     },
 };
 pub use settings::{KeyboardSettings, WindowSettings};
@@ -59,10 +75,16 @@ pub enum WindowCommand {
     TitleChanged(String),
     SetMouseEnabled(bool),
     ListAvailableFonts,
+    WorkingDirectoryChanged(PathBuf),
 }
 
+#[cfg(target_os = "macos")]
+type ChromeBackground = WindowBackground;
+#[cfg(not(target_os = "macos"))]
+type ChromeBackground = skia_safe::Color;
+
 pub struct GlutinWindowWrapper {
-    windowed_context: WindowedContext<glutin::PossiblyCurrent>,
+    windowed_context: GlContext,
     skia_renderer: SkiaRenderer,
     renderer: Renderer,
     keyboard_manager: KeyboardManager,
@@ -72,7 +94,14 @@ pub struct GlutinWindowWrapper {
     saved_inner_size: PhysicalSize<u32>,
     saved_grid_size: Option<Dimensions>,
     size_at_startup: PhysicalSize<u32>,
-    window_command_receiver: UnboundedReceiver<WindowCommand>,
+    current_monitor: Option<MonitorHandle>,
+    monitor_refresh_rate: Option<f32>,
+    // Set when the window moved to another monitor, so that the frame pacer measures its refresh
+    // rate again
+    monitor_changed: bool,
+    chrome_background: Option<ChromeBackground>,
+    working_directory: Option<PathBuf>,
+    window_command_receiver: EventReceiver<WindowCommand>,
 }
 
 impl GlutinWindowWrapper {
@@ -96,6 +125,37 @@ impl GlutinWindowWrapper {
         }
     }
 
+    /// Keeps the parts of the window outside the editor surface in the colorscheme's default
+    /// background, so they don't flash in another color on startup or while resizing.
+    pub fn synchronize_chrome_background(&mut self) {
+        let background = self.renderer.grid_renderer.get_default_background();
+        // The macOS background also depends on g:neovide_background_color and
+        // g:neovide_transparency, which can change without the colorscheme changing
+        #[cfg(target_os = "macos")]
+        let background = window_background(background);
+
+        if self.chrome_background.as_ref() == Some(&background) {
+            return;
+        }
+
+        #[cfg(target_os = "macos")]
+        draw_background(&self.windowed_context, &background);
+
+        #[cfg(target_os = "linux")]
+        set_x11_background(self.windowed_context.window(), background);
+
+        self.chrome_background = Some(background);
+    }
+
+    /// Fills the window with the default background before the first frame arrives.
+    pub fn draw_startup_background(&mut self) {
+        let background = self.renderer.grid_renderer.get_default_background();
+        self.skia_renderer.canvas().clear(background);
+        self.skia_renderer.gr_context.flush(None);
+        self.windowed_context.swap_buffers().unwrap();
+        self.synchronize_chrome_background();
+    }
+
     #[allow(clippy::needless_collect)]
     pub fn handle_window_commands(&mut self) {
         while let Ok(window_command) = self.window_command_receiver.try_recv() {
@@ -105,6 +165,9 @@ impl GlutinWindowWrapper {
                     self.mouse_manager.enabled = mouse_enabled
                 }
                 WindowCommand::ListAvailableFonts => self.send_font_names(),
+                WindowCommand::WorkingDirectoryChanged(working_directory) => {
+                    self.working_directory = Some(working_directory)
+                }
             }
         }
     }
@@ -146,6 +209,10 @@ impl GlutinWindowWrapper {
             &self.renderer,
             &self.windowed_context,
         );
+        self.renderer.separator_highlight = self.mouse_manager.separator_highlight();
+        if let Some(font_zoom) = self.mouse_manager.take_font_zoom() {
+            self.handle_font_zoom_update(font_zoom);
+        }
         self.renderer.handle_event(&event);
         match event {
             Event::LoopDestroyed => {
@@ -165,6 +232,15 @@ impl GlutinWindowWrapper {
                 ..
             } => {
                 self.handle_scale_factor_update(scale_factor);
+                self.handle_monitor_update();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Moved(_),
+                ..
+            } => {
+                // The window may have moved to another monitor
+                self.handle_monitor_update();
+                REDRAW_SCHEDULER.queue_next_frame();
             }
             Event::WindowEvent {
                 event: WindowEvent::DroppedFile(path),
@@ -190,11 +266,23 @@ impl GlutinWindowWrapper {
         }
     }
 
-    pub fn draw_frame(&mut self, dt: f32) {
+    fn handle_monitor_update(&mut self) {
+        let window = self.windowed_context.window();
+        let current_monitor = window.current_monitor();
+        self.monitor_refresh_rate = monitor_refresh_rate(window);
+
+        if current_monitor != self.current_monitor {
+            self.current_monitor = current_monitor;
+            self.monitor_changed = true;
+        }
+    }
+
+    pub fn draw_frame(&mut self, dt: f32, should_draw: bool) {
+        self.windowed_context.make_current();
         let window = self.windowed_context.window();
         let mut font_changed = false;
 
-        if REDRAW_SCHEDULER.should_draw() || SETTINGS.get::<WindowSettings>().no_idle {
+        if should_draw {
             font_changed = self.renderer.draw_frame(self.skia_renderer.canvas(), dt);
             self.skia_renderer.gr_context.flush(None);
             self.windowed_context.swap_buffers().unwrap();
@@ -219,11 +307,13 @@ impl GlutinWindowWrapper {
         log::trace!("Inner size: {:?}", new_size);
 
         if self.saved_grid_size.is_none() && !resized_at_startup {
-            window.set_inner_size(
+            let startup_size = fit_to_monitors(
+                window,
                 self.renderer
                     .grid_renderer
                     .convert_grid_to_physical(settings.geometry),
             );
+            window.set_inner_size(startup_size);
             self.saved_grid_size = Some(settings.geometry);
             // Font change at startup is ignored, so grid size (and startup screen) could be preserved.
             // But only when not resized yet. With maximized or resized window we should redraw grid.
@@ -266,29 +356,123 @@ impl GlutinWindowWrapper {
         EVENT_AGGREGATOR.send(EditorCommand::RedrawScreen);
     }
 
+    fn handle_font_zoom_update(&mut self, font_zoom: f32) {
+        self.renderer.grid_renderer.handle_zoom_update(font_zoom);
+        self.handle_new_grid_size(self.saved_inner_size);
+        EVENT_AGGREGATOR.send(EditorCommand::RedrawScreen);
+    }
+
     fn has_been_resized(&self) -> bool {
         self.windowed_context.window().inner_size() != self.size_at_startup
     }
+
+    fn save_window_settings(&self) {
+        let window = self.windowed_context.window();
+        let monitors = monitor_bounds(window.available_monitors(), window.primary_monitor());
+        save_window_geometry(
+            window.is_maximized(),
+            self.saved_grid_size,
+            window.outer_position().ok(),
+            (!monitors.is_empty()).then(|| monitor_layout_key(&monitors)),
+            PersistentSession {
+                working_directory: self.working_directory.clone(),
+                zoom: Some(self.mouse_manager.font_zoom()),
+                fullscreen: self.fullscreen,
+                monitor: window.current_monitor().and_then(|monitor| monitor.name()),
+            },
+        );
+    }
 }
 
-pub fn create_window() {
-    let icon = {
-        let icon = load_from_memory(ICON).expect("Failed to parse icon data");
-        let (width, height) = icon.dimensions();
-        let mut rgba = Vec::with_capacity((width * height) as usize * 4);
-        for (_, _, pixel) in icon.pixels() {
-            rgba.extend_from_slice(&pixel.to_rgba().0);
-        }
-        Icon::from_rgba(rgba, width, height).expect("Failed to create icon object")
+// Winit doesn't expose the refresh rate of the monitor's current video mode, so this is only an
+// upper bound. With vsync the frame pacer narrows it down by measuring the buffer swaps.
+fn monitor_refresh_rate(window: &window::Window) -> Option<f32> {
+    let monitor = window.current_monitor()?;
+    let size = monitor.size();
+
+    fastest_refresh_rate(
+        monitor
+            .video_modes()
+            .filter(|video_mode| video_mode.size() == size)
+            .map(|video_mode| video_mode.refresh_rate()),
+    )
+}
+
+// Moves the window onto the monitor it is mostly on, for windows restored on monitors that have
+// been disconnected or rearranged since. Returns the largest inner size up to the given one that
+// fits there.
+fn fit_to_monitors(window: &window::Window, inner_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    // Wayland doesn't tell windows where they are
+    let position = match window.outer_position() {
+        Ok(position) => position,
+        Err(_) => return inner_size,
     };
 
+    let current_outer_size = window.outer_size();
+    let current_inner_size = window.inner_size();
+    let frame_width = current_outer_size
+        .width
+        .saturating_sub(current_inner_size.width);
+    let frame_height = current_outer_size
+        .height
+        .saturating_sub(current_inner_size.height);
+    let outer_size = PhysicalSize::new(
+        inner_size.width + frame_width,
+        inner_size.height + frame_height,
+    );
+
+    let monitors = monitor_bounds(window.available_monitors(), window.primary_monitor());
+    match clamp_to_monitors(position, outer_size, &monitors) {
+        Some((fitted_position, fitted_size)) => {
+            if fitted_position != position {
+                log::trace!("Moved window onto monitor: {:?}", fitted_position);
+                window.set_outer_position(fitted_position);
+            }
+            PhysicalSize::new(
+                fitted_size.width.saturating_sub(frame_width),
+                fitted_size.height.saturating_sub(frame_height),
+            )
+        }
+        None => inner_size,
+    }
+}
+
+fn monitor_contains(monitor: &MonitorHandle, position: PhysicalPosition<i32>) -> bool {
+    let monitor_position = monitor.position();
+    let monitor_size = monitor.size();
+
+    position.x >= monitor_position.x
+        && position.y >= monitor_position.y
+        && position.x < monitor_position.x + monitor_size.width as i32
+        && position.y < monitor_position.y + monitor_size.height as i32
+}
+
+fn load_icon() -> Icon {
+    let icon = load_from_memory(ICON).expect("Failed to parse icon data");
+    let (width, height) = icon.dimensions();
+    let mut rgba = Vec::with_capacity((width * height) as usize * 4);
+    for (_, _, pixel) in icon.pixels() {
+        rgba.extend_from_slice(&pixel.to_rgba().0);
+    }
+    Icon::from_rgba(rgba, width, height).expect("Failed to create icon object")
+}
+
+pub fn create_window() {
+    let icon = load_icon();
+
     let event_loop = EventLoop::new();
 
     let cmd_line_settings = SETTINGS.get::<CmdLineSettings>();
+    let session = SETTINGS.get::<PersistentSession>();
+
+    let monitor_layout = monitor_layout_key(&monitor_bounds(
+        event_loop.available_monitors(),
+        event_loop.primary_monitor(),
+    ));
 
     let mut maximized = cmd_line_settings.maximized;
     let mut previous_position = None;
-    if let Ok(last_window_settings) = load_last_window_settings() {
+    if let Ok(last_window_settings) = load_last_window_settings(&monitor_layout) {
         match last_window_settings {
             PersistentWindowSettings::Maximized => {
                 maximized = true;
@@ -328,12 +512,24 @@ pub fn create_window() {
             .with_fullsize_content_view(true),
     };
 
-    if let Some(previous_position) = previous_position {
-        if !maximized {
-            winit_window_builder = winit_window_builder.with_position(previous_position);
+    let mut window_position = previous_position.filter(|_| !maximized);
+
+    // Open on the monitor of the last session, which a maximized window has no position for
+    let last_monitor = session.monitor.as_ref().and_then(|monitor_name| {
+        event_loop
+            .available_monitors()
+            .find(|monitor| monitor.name().as_ref() == Some(monitor_name))
+    });
+    if let Some(last_monitor) = last_monitor {
+        if !window_position.map_or(false, |position| monitor_contains(&last_monitor, position)) {
+            window_position = Some(last_monitor.position());
         }
     }
 
+    if let Some(window_position) = window_position {
+        winit_window_builder = winit_window_builder.with_position(window_position);
+    }
+
     #[cfg(target_os = "linux")]
     let winit_window_builder = winit_window_builder
         .with_app_id(cmd_line_settings.wayland_app_id)
@@ -346,7 +542,7 @@ pub fn create_window() {
         .with_pixel_format(24, 8)
         .with_stencil_buffer(8)
         .with_gl_profile(GlProfile::Core)
-        .with_vsync(false)
+        .with_vsync(cmd_line_settings.vsync)
         .with_srgb(cmd_line_settings.srgb)
         .build_windowed(winit_window_builder, &event_loop)
         .unwrap();
@@ -355,39 +551,23 @@ pub fn create_window() {
     let window = windowed_context.window();
     let initial_size = window.inner_size();
 
-    // Check that window is visible in some monitor, and reposition it if not.
-    let did_reposition = window
-        .current_monitor()
-        .and_then(|current_monitor| {
-            let monitor_position = current_monitor.position();
-            let monitor_size = current_monitor.size();
-            let monitor_width = monitor_size.width as i32;
-            let monitor_height = monitor_size.height as i32;
-
-            let window_position = window.outer_position().ok()?;
-            let window_size = window.outer_size();
-            let window_width = window_size.width as i32;
-            let window_height = window_size.height as i32;
-
-            if window_position.x + window_width < monitor_position.x
-                || window_position.y + window_height < monitor_position.y
-                || window_position.x > monitor_position.x + monitor_width
-                || window_position.y > monitor_position.y + monitor_height
-            {
-                window.set_outer_position(monitor_position);
-            }
-
-            Some(())
-        })
-        .is_some();
-
-    log::trace!("repositioned window: {}", did_reposition);
+    // The size is only known once the font is loaded, but the window should not flash up on a
+    // monitor that isn't there anymore
+    fit_to_monitors(window, initial_size);
 
     let scale_factor = windowed_context.window().scale_factor();
-    let renderer = Renderer::new(scale_factor);
+    let mut renderer = Renderer::new(scale_factor);
+    let mut mouse_manager = MouseManager::new();
+    if let Some(zoom) = session.zoom {
+        let zoom = mouse_manager.restore_font_zoom(zoom);
+        renderer.grid_renderer.shaper.update_zoom(zoom);
+    }
     let saved_inner_size = window.inner_size();
 
     let skia_renderer = SkiaRenderer::new(&windowed_context);
+    let current_monitor = windowed_context.window().current_monitor();
+    let monitor_refresh_rate = monitor_refresh_rate(windowed_context.window());
+    log::info!("monitor refresh rate: {:?}", monitor_refresh_rate);
 
     let window_command_receiver = EVENT_AGGREGATOR.register_event::<WindowCommand>();
 
@@ -398,20 +578,36 @@ pub fn create_window() {
     );
 
     let mut window_wrapper = GlutinWindowWrapper {
-        windowed_context,
+        windowed_context: GlContext::new(windowed_context),
         skia_renderer,
         renderer,
         keyboard_manager: KeyboardManager::new(),
-        mouse_manager: MouseManager::new(),
+        mouse_manager,
         title: String::from("Neovide"),
         fullscreen: false,
         size_at_startup: initial_size,
+        current_monitor,
+        monitor_refresh_rate,
+        monitor_changed: false,
+        chrome_background: None,
+        working_directory: None,
         saved_inner_size,
         saved_grid_size: None,
         window_command_receiver,
     };
 
-    let mut previous_frame_start = Instant::now();
+    window_wrapper.draw_startup_background();
+
+    let vsync = cmd_line_settings.vsync;
+    let mut frame_pacer = FramePacer::new(
+        vsync,
+        target_refresh_rate(
+            monitor_refresh_rate,
+            vsync,
+            SETTINGS.get::<WindowSettings>().refresh_rate,
+        ),
+        Instant::now(),
+    );
 
     enum FocusedState {
         Focused,
@@ -419,8 +615,9 @@ pub fn create_window() {
         Unfocused,
     }
     let mut focused = FocusedState::Focused;
+    let mut external_windows = ExternalWindows::default();
 
-    event_loop.run(move |e, _window_target, control_flow| {
+    event_loop.run(move |e, window_target, control_flow| {
         // Window focus changed
         if let Event::WindowEvent {
             event: WindowEvent::Focused(focused_event),
@@ -435,44 +632,61 @@ pub fn create_window() {
         }
 
         if !RUNNING_TRACKER.is_running() {
-            let window = window_wrapper.windowed_context.window();
-            save_window_geometry(
-                window.is_maximized(),
-                window_wrapper.saved_grid_size,
-                window.outer_position().ok(),
-            );
+            window_wrapper.save_window_settings();
 
             std::process::exit(RUNNING_TRACKER.exit_code());
         }
 
-        let frame_start = Instant::now();
-
         window_wrapper.handle_window_commands();
         window_wrapper.synchronize_settings();
-        window_wrapper.handle_event(e);
+        if let Some(e) = external_windows.handle_event(e) {
+            window_wrapper.handle_event(e);
+        }
 
-        let refresh_rate = match focused {
-            FocusedState::Focused | FocusedState::UnfocusedNotDrawn => {
-                SETTINGS.get::<WindowSettings>().refresh_rate as f32
-            }
-            FocusedState::Unfocused => SETTINGS.get::<WindowSettings>().refresh_rate_idle as f32,
+        if std::mem::take(&mut window_wrapper.monitor_changed) {
+            frame_pacer.reset_measured_refresh_rate();
         }
-        .max(1.0);
 
-        let expected_frame_length_seconds = 1.0 / refresh_rate;
-        let frame_duration = Duration::from_secs_f32(expected_frame_length_seconds);
+        let refresh_rate = match focused {
+            FocusedState::Focused | FocusedState::UnfocusedNotDrawn => target_refresh_rate(
+                frame_pacer
+                    .measured_refresh_rate()
+                    .or(window_wrapper.monitor_refresh_rate),
+                vsync,
+                SETTINGS.get::<WindowSettings>().refresh_rate,
+            ),
+            FocusedState::Unfocused => {
+                (SETTINGS.get::<WindowSettings>().refresh_rate_idle as f32).max(1.0)
+            }
+        };
+        frame_pacer.set_refresh_rate(refresh_rate);
 
-        if frame_start - previous_frame_start > frame_duration {
-            let dt = previous_frame_start.elapsed().as_secs_f32();
-            window_wrapper.draw_frame(dt);
+        let frame_start = Instant::now();
+        if frame_pacer.should_start_frame(frame_start) {
+            let dt = frame_pacer.start_frame(frame_start);
+            window_wrapper
+                .renderer
+                .profiler
+                .set_frame_pacing(frame_pacer.refresh_rate(), frame_pacer.missed_frames());
+            let should_draw =
+                REDRAW_SCHEDULER.should_draw() || SETTINGS.get::<WindowSettings>().no_idle;
+            window_wrapper.draw_frame(dt, should_draw);
+            let swapped_at = should_draw.then(Instant::now);
+            external_windows.handle_draw_commands(
+                window_wrapper.renderer.take_external_draw_commands(),
+                &window_wrapper.renderer,
+                window_target,
+            );
+            external_windows.draw_frame(dt, should_draw);
+            // The external windows leave their own contexts current
+            window_wrapper.windowed_context.make_current();
+            frame_pacer.finish_frame(REDRAW_SCHEDULER.is_frame_queued(), swapped_at);
             if let FocusedState::UnfocusedNotDrawn = focused {
                 focused = FocusedState::Unfocused;
             }
-            previous_frame_start = frame_start;
-            #[cfg(target_os = "macos")]
-            draw_background(&window_wrapper.windowed_context);
+            window_wrapper.synchronize_chrome_background();
         }
 
-        *control_flow = ControlFlow::WaitUntil(previous_frame_start + frame_duration)
+        *control_flow = ControlFlow::WaitUntil(frame_pacer.next_frame_deadline())
     });
 }
@@ -0,0 +1,196 @@
+use glutin::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    monitor::MonitorHandle,
+};
+
+/// The area a monitor covers on the desktop, in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MonitorBounds {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+}
+
+impl MonitorBounds {
+    pub fn new(position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> MonitorBounds {
+        MonitorBounds { position, size }
+    }
+
+    fn right(&self) -> i64 {
+        self.position.x as i64 + self.size.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.position.y as i64 + self.size.height as i64
+    }
+
+    fn overlap(&self, position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> i64 {
+        let width = self.right().min(position.x as i64 + size.width as i64)
+            - (self.position.x as i64).max(position.x as i64);
+        let height = self.bottom().min(position.y as i64 + size.height as i64)
+            - (self.position.y as i64).max(position.y as i64);
+        width.max(0) * height.max(0)
+    }
+}
+
+/// The bounds of the connected monitors, with the primary monitor first.
+pub fn monitor_bounds(
+    monitors: impl Iterator<Item = MonitorHandle>,
+    primary_monitor: Option<MonitorHandle>,
+) -> Vec<MonitorBounds> {
+    let primary_position = primary_monitor.map(|monitor| monitor.position());
+    let mut bounds: Vec<MonitorBounds> = monitors
+        .map(|monitor| MonitorBounds::new(monitor.position(), monitor.size()))
+        .collect();
+    if let Some(primary_index) = bounds
+        .iter()
+        .position(|bounds| Some(bounds.position) == primary_position)
+    {
+        bounds[..=primary_index].rotate_right(1);
+    }
+    bounds
+}
+
+/// Names the arrangement of the connected monitors, whichever order they are listed in, so the
+/// window geometry can be remembered for each of them.
+pub fn monitor_layout_key(monitors: &[MonitorBounds]) -> String {
+    let mut monitors = monitors.to_vec();
+    monitors.sort_by_key(|monitor| (monitor.position.x, monitor.position.y));
+
+    monitors
+        .iter()
+        .map(|monitor| {
+            format!(
+                "{}x{}{:+}{:+}",
+                monitor.size.width, monitor.size.height, monitor.position.x, monitor.position.y
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Moves a window fully onto the monitor it overlaps the most, shrinking it if it doesn't fit.
+/// Windows outside of every monitor go to the first one. Returns the new position and size, or
+/// None without any monitors.
+pub fn clamp_to_monitors(
+    position: PhysicalPosition<i32>,
+    size: PhysicalSize<u32>,
+    monitors: &[MonitorBounds],
+) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+    let first_monitor = monitors.first()?;
+    let monitor = monitors
+        .iter()
+        .rev()
+        .max_by_key(|monitor| monitor.overlap(position, size))
+        .filter(|monitor| monitor.overlap(position, size) > 0)
+        .unwrap_or(first_monitor);
+
+    let size = PhysicalSize::new(
+        size.width.min(monitor.size.width),
+        size.height.min(monitor.size.height),
+    );
+    let position = PhysicalPosition::new(
+        (position.x as i64).clamp(
+            monitor.position.x as i64,
+            monitor.right() - size.width as i64,
+        ) as i32,
+        (position.y as i64).clamp(
+            monitor.position.y as i64,
+            monitor.bottom() - size.height as i64,
+        ) as i32,
+    );
+
+    Some((position, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: i32, y: i32, width: u32, height: u32) -> MonitorBounds {
+        MonitorBounds::new(
+            PhysicalPosition::new(x, y),
+            PhysicalSize::new(width, height),
+        )
+    }
+
+    fn laptop() -> MonitorBounds {
+        monitor(0, 0, 1920, 1080)
+    }
+
+    fn docked() -> Vec<MonitorBounds> {
+        vec![laptop(), monitor(1920, -360, 2560, 1440)]
+    }
+
+    #[test]
+    fn test_layout_key_ignores_monitor_order() {
+        let mut monitors = docked();
+        let key = monitor_layout_key(&monitors);
+        assert_eq!(key, "1920x1080+0+0,2560x1440+1920-360");
+
+        monitors.reverse();
+        assert_eq!(monitor_layout_key(&monitors), key);
+        assert_ne!(monitor_layout_key(&[laptop()]), key);
+    }
+
+    #[test]
+    fn test_keeps_visible_windows() {
+        let position = PhysicalPosition::new(2000, 0);
+        let size = PhysicalSize::new(800, 600);
+
+        assert_eq!(
+            clamp_to_monitors(position, size, &docked()),
+            Some((position, size))
+        );
+    }
+
+    #[test]
+    fn test_moves_windows_of_disconnected_monitors() {
+        // Saved on the external monitor, restored with only the laptop screen
+        let (position, size) = clamp_to_monitors(
+            PhysicalPosition::new(2000, 0),
+            PhysicalSize::new(800, 600),
+            &[laptop()],
+        )
+        .unwrap();
+
+        assert_eq!(position, PhysicalPosition::new(1120, 0));
+        assert_eq!(size, PhysicalSize::new(800, 600));
+    }
+
+    #[test]
+    fn test_moves_windows_onto_the_monitor_they_overlap_most() {
+        let (position, _) = clamp_to_monitors(
+            PhysicalPosition::new(1800, -100),
+            PhysicalSize::new(800, 600),
+            &docked(),
+        )
+        .unwrap();
+
+        assert_eq!(position, PhysicalPosition::new(1920, -100));
+    }
+
+    #[test]
+    fn test_shrinks_windows_larger_than_the_monitor() {
+        let (position, size) = clamp_to_monitors(
+            PhysicalPosition::new(1920, -360),
+            PhysicalSize::new(2560, 1440),
+            &[laptop()],
+        )
+        .unwrap();
+
+        assert_eq!(position, PhysicalPosition::new(0, 0));
+        assert_eq!(size, PhysicalSize::new(1920, 1080));
+    }
+
+    #[test]
+    fn test_without_monitors() {
+        assert_eq!(
+            clamp_to_monitors(
+                PhysicalPosition::new(0, 0),
+                PhysicalSize::new(800, 600),
+                &[]
+            ),
+            None
+        );
+    }
+}
@@ -11,16 +11,19 @@ use glutin::{
         DeviceId, ElementState, Event, MouseButton, MouseScrollDelta, Touch, TouchPhase,
         WindowEvent,
     },
+    window::CursorIcon,
     PossiblyCurrent, WindowedContext,
 };
 use skia_safe::Rect;
 
 use crate::{
-    bridge::{SerialCommand, UiCommand},
+    bridge::{ParallelCommand, SerialCommand, UiCommand},
     event_aggregator::EVENT_AGGREGATOR,
     renderer::{Renderer, WindowDrawDetails},
     settings::SETTINGS,
     window::keyboard_manager::KeyboardManager,
+    window::mouse_visibility::MouseVisibility,
+    window::touch_gestures::{Gesture, GestureRecognizer, GestureSettings},
     window::WindowSettings,
 };
 
@@ -60,30 +63,218 @@ fn mouse_button_to_button_text(mouse_button: &MouseButton) -> Option<String> {
     }
 }
 
+// The gap between two adjacent splits. In multigrid mode the vertical separators and the status
+// lines are drawn on the base grid, so they show up as empty space between the window regions.
+#[derive(Clone, Debug)]
+struct SplitSeparator {
+    vertical: bool,
+    // Region of the window which is resized when the separator is dragged. That is the window to
+    // the left of a vertical separator, or the one above a status line.
+    window_region: Rect,
+    font_dimensions: (u64, u64),
+}
+
+impl SplitSeparator {
+    fn grid_position(&self) -> (u64, u64) {
+        let (font_width, font_height) = self.font_dimensions;
+        (
+            (self.window_region.left / font_width as f32).round() as u64,
+            (self.window_region.top / font_height as f32).round() as u64,
+        )
+    }
+
+    // Current size in cells of the resized window along the dragged axis
+    fn size(&self) -> u64 {
+        let (font_width, font_height) = self.font_dimensions;
+        if self.vertical {
+            (self.window_region.width() / font_width as f32).round() as u64
+        } else {
+            (self.window_region.height() / font_height as f32).round() as u64
+        }
+    }
+
+    // Size in cells the window should have if the separator was moved under the given position
+    fn size_at(&self, position: PhysicalPosition<f32>) -> u64 {
+        let (grid_left, grid_top) = self.grid_position();
+        let grid_position = to_grid_coords(position, self.font_dimensions);
+        if self.vertical {
+            (grid_position.x as u64).saturating_sub(grid_left).max(1)
+        } else {
+            (grid_position.y as u64).saturating_sub(grid_top).max(1)
+        }
+    }
+
+    // Pixel region of the separator if the resized window had the given size
+    fn region_for_size(&self, size: u64) -> Rect {
+        let (font_width, font_height) = self.font_dimensions;
+        let region = self.window_region;
+        if self.vertical {
+            let left = region.left + (size * font_width) as f32;
+            Rect::new(left, region.top, left + font_width as f32, region.bottom)
+        } else {
+            let top = region.top + (size * font_height) as f32;
+            Rect::new(region.left, top, region.right, top + font_height as f32)
+        }
+    }
+}
+
+fn find_split_separator(
+    position: PhysicalPosition<f32>,
+    window_regions: &[WindowDrawDetails],
+    font_dimensions: (u64, u64),
+) -> Option<SplitSeparator> {
+    let font_width = font_dimensions.0 as f32;
+    let font_height = font_dimensions.1 as f32;
+    // Only the splits of the editor can be resized. The base grid and floating windows (which
+    // includes the message grid) are skipped.
+    let splits: Vec<Rect> = window_regions
+        .iter()
+        .filter(|details| details.id != 1 && details.floating_order.is_none())
+        .map(|details| details.region)
+        .collect();
+
+    splits.iter().find_map(|region| {
+        let vertical_overlap =
+            |other: &Rect| other.top < region.bottom && other.bottom > region.top;
+        let horizontal_overlap =
+            |other: &Rect| other.left < region.right && other.right > region.left;
+
+        let on_vertical_separator = position.x >= region.right
+            && position.x < region.right + font_width
+            && position.y >= region.top
+            && position.y < region.bottom
+            && splits.iter().any(|other| {
+                other.left >= region.right
+                    && other.left <= region.right + font_width
+                    && vertical_overlap(other)
+            });
+
+        let on_status_line = position.y >= region.bottom
+            && position.y < region.bottom + font_height
+            && position.x >= region.left
+            && position.x < region.right
+            && splits.iter().any(|other| {
+                other.top >= region.bottom
+                    && other.top <= region.bottom + font_height
+                    && horizontal_overlap(other)
+            });
+
+        if on_vertical_separator || on_status_line {
+            Some(SplitSeparator {
+                vertical: on_vertical_separator,
+                window_region: *region,
+                font_dimensions,
+            })
+        } else {
+            None
+        }
+    })
+}
+
+#[derive(Debug)]
+struct SeparatorDrag {
+    separator: SplitSeparator,
+    size: u64,
+}
+
+// Neovim only distinguishes up to four clicks in a row, after which the count starts over.
+const MAX_CLICK_COUNT: u8 = 4;
+
+#[derive(Debug, Default)]
+struct ClickTracker {
+    last_press: Option<(Instant, PhysicalPosition<f32>, String)>,
+    count: u8,
+}
+
+impl ClickTracker {
+    // Registers a button press and returns how many clicks in a row it completes
+    fn press(
+        &mut self,
+        button: &str,
+        position: PhysicalPosition<f32>,
+        now: Instant,
+        max_interval: Duration,
+        max_distance: f32,
+    ) -> u8 {
+        let continues_previous = matches!(
+            &self.last_press,
+            Some((time, previous_position, previous_button))
+                if previous_button == button
+                    && now.saturating_duration_since(*time) <= max_interval
+                    && ((previous_position.x - position.x).powi(2)
+                        + (previous_position.y - position.y).powi(2))
+                    .sqrt()
+                        <= max_distance
+        );
+
+        self.count = if continues_previous && self.count < MAX_CLICK_COUNT {
+            self.count + 1
+        } else {
+            1
+        };
+        self.last_press = Some((now, position, button.to_owned()));
+        self.count
+    }
+}
+
+// Collects fractional scroll amounts along one axis until they add up to whole lines. Positive
+// amounts follow winit's convention of moving the content right and down, which reveals the
+// content to the left and above.
+#[derive(Debug, Default)]
+struct ScrollAccumulator {
+    position: f32,
+}
+
+impl ScrollAccumulator {
+    // Returns the number of whole lines crossed by the delta
+    fn accumulate(&mut self, delta: f32) -> i64 {
+        let previous = self.position.floor() as i64;
+        self.position += delta;
+        self.position.floor() as i64 - previous
+    }
+}
+
 #[derive(Debug)]
 struct TouchTrace {
     start_time: Instant,
     start: PhysicalPosition<f32>,
     last: PhysicalPosition<f32>,
     left_deadzone_once: bool,
+    long_pressed: bool,
 }
 
+const MIN_FONT_ZOOM: f32 = 0.25;
+const MAX_FONT_ZOOM: f32 = 4.0;
+// Reloading the font is expensive, so a pinch only changes the font size in steps
+const FONT_ZOOM_STEP: f32 = 0.05;
+
 pub struct MouseManager {
     dragging: Option<String>,
     drag_position: PhysicalPosition<u32>,
 
     has_moved: bool,
+    pixel_position: PhysicalPosition<f32>,
     position: PhysicalPosition<u32>,
     relative_position: PhysicalPosition<u32>,
 
-    scroll_position: PhysicalPosition<f32>,
+    vertical_scroll: ScrollAccumulator,
+    horizontal_scroll: ScrollAccumulator,
 
     // the tuple allows to keep track of different fingers per device
     touch_position: HashMap<(DeviceId, u64), TouchTrace>,
+    gesture_recognizer: GestureRecognizer<(DeviceId, u64)>,
+
+    font_zoom: f32,
+    applied_font_zoom: f32,
 
     window_details_under_mouse: Option<WindowDrawDetails>,
 
-    mouse_hidden: bool,
+    hovered_separator: Option<SplitSeparator>,
+    separator_drag: Option<SeparatorDrag>,
+
+    click_tracker: ClickTracker,
+
+    visibility: MouseVisibility,
     pub enabled: bool,
 }
 
@@ -92,17 +283,94 @@ impl MouseManager {
         MouseManager {
             dragging: None,
             has_moved: false,
+            pixel_position: PhysicalPosition::new(0.0, 0.0),
             position: PhysicalPosition::new(0, 0),
             relative_position: PhysicalPosition::new(0, 0),
             drag_position: PhysicalPosition::new(0, 0),
-            scroll_position: PhysicalPosition::new(0.0, 0.0),
+            vertical_scroll: ScrollAccumulator::default(),
+            horizontal_scroll: ScrollAccumulator::default(),
             touch_position: HashMap::new(),
+            gesture_recognizer: GestureRecognizer::new(),
+            font_zoom: 1.0,
+            applied_font_zoom: 1.0,
             window_details_under_mouse: None,
-            mouse_hidden: false,
+            hovered_separator: None,
+            separator_drag: None,
+            click_tracker: ClickTracker::default(),
+            visibility: MouseVisibility::new(Instant::now()),
             enabled: true,
         }
     }
 
+    fn is_dragging(&self) -> bool {
+        self.dragging.is_some() || self.separator_drag.is_some()
+    }
+
+    fn apply_visibility(&self, changed: bool, windowed_context: &WindowedContext<PossiblyCurrent>) {
+        if changed {
+            windowed_context
+                .window()
+                .set_cursor_visible(!self.visibility.is_hidden());
+        }
+    }
+
+    fn handle_pointer_activity(&mut self, windowed_context: &WindowedContext<PossiblyCurrent>) {
+        let changed = self.visibility.handle_pointer_activity(Instant::now());
+        self.apply_visibility(changed, windowed_context);
+    }
+
+    fn update_visibility(
+        &mut self,
+        keyboard_manager: &KeyboardManager,
+        windowed_context: &WindowedContext<PossiblyCurrent>,
+    ) {
+        let settings = SETTINGS.get::<WindowSettings>();
+        let dragging = self.is_dragging();
+
+        let mut changed = false;
+        if keyboard_manager.sent_input_this_frame() {
+            changed |= self
+                .visibility
+                .handle_typing(settings.hide_mouse_when_typing, dragging);
+        }
+
+        let hide_after = (settings.hide_mouse_after_idle > 0.0)
+            .then(|| Duration::from_secs_f32(settings.hide_mouse_after_idle));
+        changed |= self.visibility.update(Instant::now(), hide_after, dragging);
+
+        self.apply_visibility(changed, windowed_context);
+    }
+
+    /// Font zoom requested by pinch gestures, if it changed enough since it was last applied.
+    pub fn take_font_zoom(&mut self) -> Option<f32> {
+        if (self.font_zoom / self.applied_font_zoom - 1.0).abs() >= FONT_ZOOM_STEP {
+            self.applied_font_zoom = self.font_zoom;
+            Some(self.font_zoom)
+        } else {
+            None
+        }
+    }
+
+    /// The font zoom last applied to the window.
+    pub fn font_zoom(&self) -> f32 {
+        self.applied_font_zoom
+    }
+
+    /// Continues pinch gestures from a zoom restored from the last session. Returns the zoom to
+    /// apply to the window.
+    pub fn restore_font_zoom(&mut self, font_zoom: f32) -> f32 {
+        self.font_zoom = font_zoom.clamp(MIN_FONT_ZOOM, MAX_FONT_ZOOM);
+        self.applied_font_zoom = self.font_zoom;
+        self.font_zoom
+    }
+
+    /// Region of the split separator currently being dragged, if any.
+    pub fn separator_highlight(&self) -> Option<Rect> {
+        self.separator_drag
+            .as_ref()
+            .map(|drag| drag.separator.region_for_size(drag.size))
+    }
+
     fn handle_pointer_motion(
         &mut self,
         x: i32,
@@ -117,6 +385,35 @@ impl MouseManager {
         }
 
         let position: PhysicalPosition<f32> = PhysicalPosition::new(x as f32, y as f32);
+        let font_dimensions = renderer.grid_renderer.font_dimensions.into();
+        self.pixel_position = position;
+
+        if let Some(separator_drag) = &mut self.separator_drag {
+            let size = separator_drag.separator.size_at(position);
+            if size != separator_drag.size {
+                separator_drag.size = size;
+                EVENT_AGGREGATOR.send(UiCommand::Parallel(ParallelCommand::ResizeSplit {
+                    grid_position: separator_drag.separator.grid_position(),
+                    vertical: separator_drag.separator.vertical,
+                    size,
+                }));
+            }
+            return;
+        }
+
+        if self.dragging.is_none() {
+            let hovered_separator =
+                find_split_separator(position, &renderer.window_regions, font_dimensions);
+            let cursor_icon = match &hovered_separator {
+                Some(SplitSeparator { vertical: true, .. }) => CursorIcon::ColResize,
+                Some(SplitSeparator {
+                    vertical: false, ..
+                }) => CursorIcon::RowResize,
+                None => CursorIcon::Default,
+            };
+            windowed_context.window().set_cursor_icon(cursor_icon);
+            self.hovered_separator = hovered_separator;
+        }
 
         // If dragging, the relevant window (the one which we send all commands to) is the one
         // which the mouse drag started on. Otherwise its the top rendered window
@@ -147,26 +444,16 @@ impl MouseManager {
         let global_bounds = relevant_window_details
             .map(|details| details.region)
             .unwrap_or_else(|| Rect::from_wh(size.width as f32, size.height as f32));
-        let clamped_position = clamp_position(
-            position,
-            global_bounds,
-            renderer.grid_renderer.font_dimensions.into(),
-        );
+        let clamped_position = clamp_position(position, global_bounds, font_dimensions);
 
-        self.position = to_grid_coords(
-            clamped_position,
-            renderer.grid_renderer.font_dimensions.into(),
-        );
+        self.position = to_grid_coords(clamped_position, font_dimensions);
 
         if let Some(relevant_window_details) = relevant_window_details {
             let relative_position = PhysicalPosition::new(
                 clamped_position.x - relevant_window_details.region.left,
                 clamped_position.y - relevant_window_details.region.top,
             );
-            self.relative_position = to_grid_coords(
-                relative_position,
-                renderer.grid_renderer.font_dimensions.into(),
-            );
+            self.relative_position = to_grid_coords(relative_position, font_dimensions);
 
             let previous_position = self.drag_position;
             self.drag_position = self.relative_position;
@@ -194,12 +481,25 @@ impl MouseManager {
         &mut self,
         mouse_button: &MouseButton,
         down: bool,
+        from_touch: bool,
         keyboard_manager: &KeyboardManager,
     ) {
         // For some reason pointer down is handled differently from pointer up and drag.
         // Floating windows: relative coordinates are great.
         // Non floating windows: rather than global coordinates, relative are needed
         if self.enabled {
+            if *mouse_button == MouseButton::Left {
+                if down {
+                    if let Some(separator) = self.hovered_separator.clone() {
+                        let size = separator.size();
+                        self.separator_drag = Some(SeparatorDrag { separator, size });
+                        return;
+                    }
+                } else if self.separator_drag.take().is_some() {
+                    return;
+                }
+            }
+
             if let Some(button_text) = mouse_button_to_button_text(mouse_button) {
                 if let Some(details) = &self.window_details_under_mouse {
                     let action = if down {
@@ -214,12 +514,37 @@ impl MouseManager {
                         self.relative_position
                     };
 
+                    let mut modifier_string = keyboard_manager.format_modifier_string(true);
+                    if down {
+                        let settings = SETTINGS.get::<WindowSettings>();
+                        // Fingers are a lot less precise than a mouse, so taps are allowed to be as
+                        // far apart as the touch deadzone
+                        let max_distance = if from_touch {
+                            settings.multi_click_distance.max(settings.touch_deadzone)
+                        } else {
+                            settings.multi_click_distance
+                        };
+                        let click_count = self.click_tracker.press(
+                            &button_text,
+                            self.pixel_position,
+                            Instant::now(),
+                            Duration::from_secs_f32(settings.multi_click_interval.max(0.0)),
+                            max_distance,
+                        );
+
+                        // Neovim reads the click count as a modifier, so a double click is sent
+                        // as a "2-" press which neovim then maps to <2-LeftMouse>
+                        if click_count > 1 {
+                            modifier_string = format!("{}-{}", click_count, modifier_string);
+                        }
+                    }
+
                     EVENT_AGGREGATOR.send(UiCommand::Serial(SerialCommand::MouseButton {
                         button: button_text.clone(),
                         action,
                         grid_id: details.id,
                         position: position.into(),
-                        modifier_string: keyboard_manager.format_modifier_string(true),
+                        modifier_string,
                     }));
                 }
 
@@ -237,62 +562,60 @@ impl MouseManager {
     }
 
     fn handle_line_scroll(&mut self, x: f32, y: f32, keyboard_manager: &KeyboardManager) {
+        self.scroll_lines(x, y, keyboard_manager.format_modifier_string(true));
+    }
+
+    fn handle_wheel_scroll(&mut self, x: f32, y: f32, keyboard_manager: &KeyboardManager) {
+        // Regular mouse wheels only have a vertical axis, so holding shift turns it into a
+        // horizontal one. Shift is consumed by that and not sent along.
+        if keyboard_manager.is_shift_pressed() && x == 0.0 {
+            self.scroll_lines(y, 0.0, keyboard_manager.format_modifier_string(false));
+        } else {
+            self.handle_line_scroll(x, y, keyboard_manager);
+        }
+    }
+
+    fn scroll_lines(&mut self, x: f32, y: f32, modifier_string: String) {
         if !self.enabled {
             return;
         }
 
-        let previous_y = self.scroll_position.y as i64;
-        self.scroll_position.y += y;
-        let new_y = self.scroll_position.y as i64;
-
-        let vertical_input_type = match new_y.partial_cmp(&previous_y) {
-            Some(Ordering::Greater) => Some("up"),
-            Some(Ordering::Less) => Some("down"),
-            _ => None,
-        };
+        let scroll_speed = SETTINGS.get::<WindowSettings>().scroll_speed_multiplier;
 
-        if let Some(input_type) = vertical_input_type {
-            let scroll_command: UiCommand = SerialCommand::Scroll {
-                direction: input_type.to_string(),
-                grid_id: self
-                    .window_details_under_mouse
-                    .as_ref()
-                    .map(|details| details.id)
-                    .unwrap_or(0),
-                position: self.drag_position.into(),
-                modifier_string: keyboard_manager.format_modifier_string(true),
-            }
-            .into();
-            for _ in 0..(new_y - previous_y).abs() {
-                EVENT_AGGREGATOR.send(scroll_command.clone());
-            }
-        }
+        let vertical_steps = self.vertical_scroll.accumulate(y * scroll_speed);
+        self.send_scroll(vertical_steps, ("up", "down"), &modifier_string);
 
-        let previous_x = self.scroll_position.x as i64;
-        self.scroll_position.x += x;
-        let new_x = self.scroll_position.x as i64;
+        let horizontal_steps = self.horizontal_scroll.accumulate(x * scroll_speed);
+        self.send_scroll(horizontal_steps, ("left", "right"), &modifier_string);
+    }
 
-        let horizontal_input_type = match new_x.partial_cmp(&previous_x) {
-            Some(Ordering::Greater) => Some("right"),
-            Some(Ordering::Less) => Some("left"),
-            _ => None,
+    // Sends the accumulated steps to the grid under the mouse. Positive steps are sent in the
+    // first direction of the pair, negative ones in the second.
+    fn send_scroll(
+        &self,
+        steps: i64,
+        (positive_direction, negative_direction): (&str, &str),
+        modifier_string: &str,
+    ) {
+        let direction = match steps.cmp(&0) {
+            Ordering::Greater => positive_direction,
+            Ordering::Less => negative_direction,
+            Ordering::Equal => return,
         };
 
-        if let Some(input_type) = horizontal_input_type {
-            let scroll_command: UiCommand = SerialCommand::Scroll {
-                direction: input_type.to_string(),
-                grid_id: self
-                    .window_details_under_mouse
-                    .as_ref()
-                    .map(|details| details.id)
-                    .unwrap_or(0),
-                position: self.drag_position.into(),
-                modifier_string: keyboard_manager.format_modifier_string(true),
-            }
-            .into();
-            for _ in 0..(new_x - previous_x).abs() {
-                EVENT_AGGREGATOR.send(scroll_command.clone());
-            }
+        let scroll_command: UiCommand = SerialCommand::Scroll {
+            direction: direction.to_string(),
+            grid_id: self
+                .window_details_under_mouse
+                .as_ref()
+                .map(|details| details.id)
+                .unwrap_or(0),
+            position: self.drag_position.into(),
+            modifier_string: modifier_string.to_owned(),
+        }
+        .into();
+        for _ in 0..steps.abs() {
+            EVENT_AGGREGATOR.send(scroll_command.clone());
         }
     }
 
@@ -309,6 +632,15 @@ impl MouseManager {
         );
     }
 
+    fn gesture_settings() -> GestureSettings {
+        let settings = SETTINGS.get::<WindowSettings>();
+        GestureSettings {
+            deadzone: settings.touch_deadzone.max(0.0),
+            long_press_duration: (settings.touch_long_press_duration > 0.0)
+                .then(|| Duration::from_secs_f32(settings.touch_long_press_duration)),
+        }
+    }
+
     fn handle_touch(
         &mut self,
         keyboard_manager: &KeyboardManager,
@@ -317,6 +649,107 @@ impl MouseManager {
         finger_id: (DeviceId, u64),
         location: PhysicalPosition<f32>,
         phase: &TouchPhase,
+    ) {
+        let gesture = self.gesture_recognizer.handle_touch(
+            finger_id,
+            location,
+            *phase,
+            Instant::now(),
+            &Self::gesture_settings(),
+        );
+
+        if self.gesture_recognizer.is_multi_touch() {
+            // A second finger turns the interaction into a gesture, so whatever the first finger
+            // started is abandoned
+            if self.dragging.is_some() {
+                self.handle_pointer_transition(&MouseButton::Left, false, true, keyboard_manager);
+            }
+            self.touch_position.clear();
+
+            if let Some(gesture) = gesture {
+                self.handle_gesture(gesture, keyboard_manager, renderer, windowed_context);
+            }
+        } else {
+            self.handle_single_touch(
+                keyboard_manager,
+                renderer,
+                windowed_context,
+                finger_id,
+                location,
+                phase,
+            );
+        }
+    }
+
+    fn handle_gesture(
+        &mut self,
+        gesture: Gesture,
+        keyboard_manager: &KeyboardManager,
+        renderer: &Renderer,
+        windowed_context: &WindowedContext<PossiblyCurrent>,
+    ) {
+        match gesture {
+            Gesture::Pan { center, delta } => {
+                // Scroll the window under the fingers
+                self.handle_pointer_motion(
+                    center.x.round() as i32,
+                    center.y.round() as i32,
+                    keyboard_manager,
+                    renderer,
+                    windowed_context,
+                );
+                let font_size = renderer.grid_renderer.font_dimensions.into();
+                self.handle_pixel_scroll(font_size, delta, keyboard_manager);
+            }
+            Gesture::Pinch { scale, .. } => {
+                self.font_zoom = (self.font_zoom * scale).clamp(MIN_FONT_ZOOM, MAX_FONT_ZOOM);
+            }
+            Gesture::LongPress { position } => {
+                // A drag started by holding the finger takes precedence
+                if self.dragging.is_some() {
+                    return;
+                }
+
+                for trace in self.touch_position.values_mut() {
+                    trace.long_pressed = true;
+                }
+
+                // Open the context menu like a right click would
+                self.handle_pointer_motion(
+                    position.x.round() as i32,
+                    position.y.round() as i32,
+                    keyboard_manager,
+                    renderer,
+                    windowed_context,
+                );
+                self.handle_pointer_transition(&MouseButton::Right, true, true, keyboard_manager);
+                self.handle_pointer_transition(&MouseButton::Right, false, true, keyboard_manager);
+            }
+        }
+    }
+
+    fn update_gestures(
+        &mut self,
+        keyboard_manager: &KeyboardManager,
+        renderer: &Renderer,
+        windowed_context: &WindowedContext<PossiblyCurrent>,
+    ) {
+        if let Some(gesture) = self
+            .gesture_recognizer
+            .update(Instant::now(), &Self::gesture_settings())
+        {
+            self.handle_gesture(gesture, keyboard_manager, renderer, windowed_context);
+        }
+    }
+
+    fn handle_single_touch(
+        &mut self,
+        keyboard_manager: &KeyboardManager,
+        renderer: &Renderer,
+        windowed_context: &WindowedContext<PossiblyCurrent>,
+        finger_id: (DeviceId, u64),
+        location: PhysicalPosition<f32>,
+        phase: &TouchPhase,
     ) {
         match phase {
             TouchPhase::Started => {
@@ -330,6 +763,7 @@ impl MouseManager {
                         start: location,
                         last: location,
                         left_deadzone_once: !enable_deadzone,
+                        long_pressed: false,
                     },
                 );
             }
@@ -337,6 +771,10 @@ impl MouseManager {
                 let mut dragging_just_now = false;
 
                 if let Some(trace) = self.touch_position.get_mut(&finger_id) {
+                    if trace.long_pressed {
+                        return;
+                    }
+
                     if !trace.left_deadzone_once {
                         let distance_to_start = ((trace.start.x - location.x).powi(2)
                             + (trace.start.y - location.y).powi(2))
@@ -368,7 +806,7 @@ impl MouseManager {
                     // the double check might seem useless, but the if branch above might set
                     // trace.left_deadzone_once - which urges to check again
                     else if trace.left_deadzone_once {
-                        let delta = (trace.last.x - location.x, location.y - trace.last.y);
+                        let delta = (location.x - trace.last.x, location.y - trace.last.y);
 
                         // not updating the position would cause the movement to "escalate" from the
                         // starting point
@@ -387,15 +825,25 @@ impl MouseManager {
                         renderer,
                         windowed_context,
                     );
-                    self.handle_pointer_transition(&MouseButton::Left, true, keyboard_manager);
+                    self.handle_pointer_transition(
+                        &MouseButton::Left,
+                        true,
+                        true,
+                        keyboard_manager,
+                    );
                 }
             }
             TouchPhase::Ended | TouchPhase::Cancelled => {
                 if let Some(trace) = self.touch_position.remove(&finger_id) {
                     if self.dragging.is_some() {
-                        self.handle_pointer_transition(&MouseButton::Left, false, keyboard_manager);
+                        self.handle_pointer_transition(
+                            &MouseButton::Left,
+                            false,
+                            true,
+                            keyboard_manager,
+                        );
                     }
-                    if !trace.left_deadzone_once {
+                    if !trace.left_deadzone_once && !trace.long_pressed {
                         self.handle_pointer_motion(
                             trace.start.x.round() as i32,
                             trace.start.y.round() as i32,
@@ -403,8 +851,18 @@ impl MouseManager {
                             renderer,
                             windowed_context,
                         );
-                        self.handle_pointer_transition(&MouseButton::Left, true, keyboard_manager);
-                        self.handle_pointer_transition(&MouseButton::Left, false, keyboard_manager);
+                        self.handle_pointer_transition(
+                            &MouseButton::Left,
+                            true,
+                            true,
+                            keyboard_manager,
+                        );
+                        self.handle_pointer_transition(
+                            &MouseButton::Left,
+                            false,
+                            true,
+                            keyboard_manager,
+                        );
                     }
                 }
             }
@@ -430,10 +888,7 @@ impl MouseManager {
                     renderer,
                     windowed_context,
                 );
-                if self.mouse_hidden {
-                    windowed_context.window().set_cursor_visible(true);
-                    self.mouse_hidden = false;
-                }
+                self.handle_pointer_activity(windowed_context);
             }
             Event::WindowEvent {
                 event:
@@ -442,7 +897,10 @@ impl MouseManager {
                         ..
                     },
                 ..
-            } => self.handle_line_scroll(*x, *y, keyboard_manager),
+            } => {
+                self.handle_wheel_scroll(*x, *y, keyboard_manager);
+                self.handle_pointer_activity(windowed_context);
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::MouseWheel {
@@ -450,11 +908,14 @@ impl MouseManager {
                         ..
                     },
                 ..
-            } => self.handle_pixel_scroll(
-                renderer.grid_renderer.font_dimensions.into(),
-                (delta.x as f32, delta.y as f32),
-                keyboard_manager,
-            ),
+            } => {
+                self.handle_pixel_scroll(
+                    renderer.grid_renderer.font_dimensions.into(),
+                    (delta.x as f32, delta.y as f32),
+                    keyboard_manager,
+                );
+                self.handle_pointer_activity(windowed_context);
+            }
             Event::WindowEvent {
                 event:
                     WindowEvent::Touch(Touch {
@@ -476,27 +937,257 @@ impl MouseManager {
             Event::WindowEvent {
                 event: WindowEvent::MouseInput { button, state, .. },
                 ..
-            } => self.handle_pointer_transition(
-                button,
-                state == &ElementState::Pressed,
-                keyboard_manager,
-            ),
-            Event::WindowEvent {
-                event:
-                    WindowEvent::KeyboardInput {
-                        event: key_event, ..
-                    },
-                ..
             } => {
-                if key_event.state == ElementState::Pressed {
-                    let window_settings = SETTINGS.get::<WindowSettings>();
-                    if window_settings.hide_mouse_when_typing && !self.mouse_hidden {
-                        windowed_context.window().set_cursor_visible(false);
-                        self.mouse_hidden = true;
-                    }
-                }
+                self.handle_pointer_transition(
+                    button,
+                    state == &ElementState::Pressed,
+                    false,
+                    keyboard_manager,
+                );
+                self.handle_pointer_activity(windowed_context);
+            }
+            Event::MainEventsCleared => {
+                self.update_gestures(keyboard_manager, renderer, windowed_context);
+                self.update_visibility(keyboard_manager, windowed_context);
             }
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLICK_INTERVAL: Duration = Duration::from_millis(500);
+    const CLICK_DISTANCE: f32 = 4.0;
+
+    const FONT_DIMENSIONS: (u64, u64) = (10, 20);
+
+    fn details(id: u64, left: f32, top: f32, right: f32, bottom: f32) -> WindowDrawDetails {
+        WindowDrawDetails {
+            id,
+            region: Rect::new(left, top, right, bottom),
+            floating_order: None,
+        }
+    }
+
+    // Two side by side splits over a horizontal split, all of them on top of the base grid:
+    //
+    //   +------+-+------+
+    //   |  2   |||  3   |
+    //   +------+-+------+ <- status lines
+    //   |       4       |
+    //   +---------------+ <- status line
+    fn window_regions() -> Vec<WindowDrawDetails> {
+        vec![
+            details(1, 0.0, 0.0, 150.0, 200.0),
+            details(2, 0.0, 0.0, 70.0, 80.0),
+            details(3, 80.0, 0.0, 150.0, 80.0),
+            details(4, 0.0, 100.0, 150.0, 180.0),
+        ]
+    }
+
+    #[test]
+    fn test_find_split_separator_vertical() {
+        let separator = find_split_separator(
+            PhysicalPosition::new(75.0, 40.0),
+            &window_regions(),
+            FONT_DIMENSIONS,
+        )
+        .expect("Should find the vertical separator");
+
+        assert!(separator.vertical);
+        assert_eq!(separator.grid_position(), (0, 0));
+        assert_eq!(separator.size(), 7);
+        assert_eq!(separator.size_at(PhysicalPosition::new(105.0, 40.0)), 10);
+        assert_eq!(
+            separator.region_for_size(10),
+            Rect::new(100.0, 0.0, 110.0, 80.0)
+        );
+    }
+
+    #[test]
+    fn test_find_split_separator_status_line() {
+        let separator = find_split_separator(
+            PhysicalPosition::new(100.0, 85.0),
+            &window_regions(),
+            FONT_DIMENSIONS,
+        )
+        .expect("Should find the status line of window 3");
+
+        assert!(!separator.vertical);
+        assert_eq!(separator.grid_position(), (8, 0));
+        assert_eq!(separator.size(), 4);
+        assert_eq!(separator.size_at(PhysicalPosition::new(100.0, 0.0)), 1);
+    }
+
+    #[test]
+    fn test_find_split_separator_ignores_outer_edges() {
+        // The last status line has no split below it, only the command line
+        assert!(find_split_separator(
+            PhysicalPosition::new(50.0, 185.0),
+            &window_regions(),
+            FONT_DIMENSIONS,
+        )
+        .is_none());
+
+        // Inside of a window
+        assert!(find_split_separator(
+            PhysicalPosition::new(30.0, 30.0),
+            &window_regions(),
+            FONT_DIMENSIONS,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_find_split_separator_ignores_floating_windows() {
+        let mut regions = window_regions();
+        for details in regions.iter_mut() {
+            details.floating_order = Some(details.id);
+        }
+
+        assert!(
+            find_split_separator(PhysicalPosition::new(75.0, 40.0), &regions, FONT_DIMENSIONS,)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_scroll_accumulator_whole_lines() {
+        let mut accumulator = ScrollAccumulator::default();
+
+        assert_eq!(accumulator.accumulate(1.0), 1);
+        assert_eq!(accumulator.accumulate(3.0), 3);
+        assert_eq!(accumulator.accumulate(-2.0), -2);
+    }
+
+    #[test]
+    fn test_scroll_accumulator_fractions() {
+        let mut accumulator = ScrollAccumulator::default();
+
+        assert_eq!(accumulator.accumulate(0.5), 0);
+        assert_eq!(accumulator.accumulate(0.25), 0);
+        assert_eq!(accumulator.accumulate(0.5), 1);
+        // Going back over the same distance yields the same amount of lines in the other
+        // direction
+        assert_eq!(accumulator.accumulate(-0.5), -1);
+        assert_eq!(accumulator.accumulate(-0.5), 0);
+        assert_eq!(accumulator.accumulate(-0.5), -1);
+    }
+
+    #[test]
+    fn test_scroll_accumulators_are_independent() {
+        let mut vertical = ScrollAccumulator::default();
+        let mut horizontal = ScrollAccumulator::default();
+
+        assert_eq!(vertical.accumulate(0.6), 0);
+        assert_eq!(horizontal.accumulate(0.6), 0);
+        assert_eq!(vertical.accumulate(0.6), 1);
+        assert_eq!(horizontal.accumulate(-0.7), -1);
+    }
+
+    #[test]
+    fn test_click_tracker_counts_clicks() {
+        let mut tracker = ClickTracker::default();
+        let start = Instant::now();
+        let position = PhysicalPosition::new(10.0, 10.0);
+        let click = |tracker: &mut ClickTracker, millis| {
+            tracker.press(
+                "left",
+                position,
+                start + Duration::from_millis(millis),
+                CLICK_INTERVAL,
+                CLICK_DISTANCE,
+            )
+        };
+
+        assert_eq!(click(&mut tracker, 0), 1);
+        assert_eq!(click(&mut tracker, 200), 2);
+        assert_eq!(click(&mut tracker, 400), 3);
+        assert_eq!(click(&mut tracker, 600), 4);
+        // Wraps around after the fourth click
+        assert_eq!(click(&mut tracker, 800), 1);
+        assert_eq!(click(&mut tracker, 1000), 2);
+    }
+
+    #[test]
+    fn test_click_tracker_resets_after_interval() {
+        let mut tracker = ClickTracker::default();
+        let start = Instant::now();
+        let position = PhysicalPosition::new(10.0, 10.0);
+
+        assert_eq!(
+            tracker.press("left", position, start, CLICK_INTERVAL, CLICK_DISTANCE),
+            1
+        );
+        assert_eq!(
+            tracker.press(
+                "left",
+                position,
+                start + Duration::from_millis(501),
+                CLICK_INTERVAL,
+                CLICK_DISTANCE
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn test_click_tracker_resets_after_distance() {
+        let mut tracker = ClickTracker::default();
+        let start = Instant::now();
+
+        assert_eq!(
+            tracker.press(
+                "left",
+                PhysicalPosition::new(10.0, 10.0),
+                start,
+                CLICK_INTERVAL,
+                CLICK_DISTANCE
+            ),
+            1
+        );
+        assert_eq!(
+            tracker.press(
+                "left",
+                PhysicalPosition::new(13.0, 12.0),
+                start,
+                CLICK_INTERVAL,
+                CLICK_DISTANCE
+            ),
+            2
+        );
+        assert_eq!(
+            tracker.press(
+                "left",
+                PhysicalPosition::new(20.0, 12.0),
+                start,
+                CLICK_INTERVAL,
+                CLICK_DISTANCE
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn test_click_tracker_resets_on_other_button() {
+        let mut tracker = ClickTracker::default();
+        let start = Instant::now();
+        let position = PhysicalPosition::new(10.0, 10.0);
+
+        assert_eq!(
+            tracker.press("left", position, start, CLICK_INTERVAL, CLICK_DISTANCE),
+            1
+        );
+        assert_eq!(
+            tracker.press("right", position, start, CLICK_INTERVAL, CLICK_DISTANCE),
+            1
+        );
+        assert_eq!(
+            tracker.press("right", position, start, CLICK_INTERVAL, CLICK_DISTANCE),
+            2
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+/// Decides when the mouse pointer should be hidden. Kept independent from winit so that the
+/// transitions can be tested without a window; the caller applies the result to the real pointer.
+#[derive(Debug)]
+pub struct MouseVisibility {
+    hidden: bool,
+    last_activity: Instant,
+}
+
+impl MouseVisibility {
+    pub fn new(now: Instant) -> MouseVisibility {
+        MouseVisibility {
+            hidden: false,
+            last_activity: now,
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Called when keyboard input was sent to neovim. Returns true if the visibility changed.
+    pub fn handle_typing(&mut self, hide_when_typing: bool, dragging: bool) -> bool {
+        self.set_hidden(self.hidden || (hide_when_typing && !dragging))
+    }
+
+    /// Called on any pointer motion, button or wheel event. Returns true if the visibility
+    /// changed.
+    pub fn handle_pointer_activity(&mut self, now: Instant) -> bool {
+        self.last_activity = now;
+        self.set_hidden(false)
+    }
+
+    /// Hides the pointer once it has been idle for longer than `hide_after`. Returns true if the
+    /// visibility changed.
+    pub fn update(&mut self, now: Instant, hide_after: Option<Duration>, dragging: bool) -> bool {
+        if dragging {
+            self.last_activity = now;
+            return false;
+        }
+
+        match hide_after {
+            Some(hide_after) if now.saturating_duration_since(self.last_activity) >= hide_after => {
+                self.set_hidden(true)
+            }
+            _ => false,
+        }
+    }
+
+    fn set_hidden(&mut self, hidden: bool) -> bool {
+        let changed = self.hidden != hidden;
+        self.hidden = hidden;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HIDE_AFTER: Option<Duration> = Some(Duration::from_secs(2));
+
+    #[test]
+    fn test_hides_on_typing() {
+        let mut visibility = MouseVisibility::new(Instant::now());
+
+        assert!(visibility.handle_typing(true, false));
+        assert!(visibility.is_hidden());
+
+        // Typing again doesn't change anything
+        assert!(!visibility.handle_typing(true, false));
+        assert!(visibility.is_hidden());
+    }
+
+    #[test]
+    fn test_typing_respects_setting() {
+        let mut visibility = MouseVisibility::new(Instant::now());
+
+        assert!(!visibility.handle_typing(false, false));
+        assert!(!visibility.is_hidden());
+    }
+
+    #[test]
+    fn test_shows_on_pointer_activity() {
+        let start = Instant::now();
+        let mut visibility = MouseVisibility::new(start);
+        visibility.handle_typing(true, false);
+
+        assert!(visibility.handle_pointer_activity(start + Duration::from_millis(10)));
+        assert!(!visibility.is_hidden());
+        assert!(!visibility.handle_pointer_activity(start + Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_hides_after_inactivity() {
+        let start = Instant::now();
+        let mut visibility = MouseVisibility::new(start);
+
+        assert!(!visibility.update(start + Duration::from_secs(1), HIDE_AFTER, false));
+        assert!(!visibility.is_hidden());
+
+        assert!(visibility.update(start + Duration::from_secs(2), HIDE_AFTER, false));
+        assert!(visibility.is_hidden());
+    }
+
+    #[test]
+    fn test_pointer_activity_restarts_timer() {
+        let start = Instant::now();
+        let mut visibility = MouseVisibility::new(start);
+
+        visibility.handle_pointer_activity(start + Duration::from_secs(1));
+        assert!(!visibility.update(start + Duration::from_secs(2), HIDE_AFTER, false));
+        assert!(visibility.update(start + Duration::from_secs(3), HIDE_AFTER, false));
+    }
+
+    #[test]
+    fn test_autohide_disabled() {
+        let start = Instant::now();
+        let mut visibility = MouseVisibility::new(start);
+
+        assert!(!visibility.update(start + Duration::from_secs(3600), None, false));
+        assert!(!visibility.is_hidden());
+    }
+
+    #[test]
+    fn test_never_hides_while_dragging() {
+        let start = Instant::now();
+        let mut visibility = MouseVisibility::new(start);
+
+        assert!(!visibility.handle_typing(true, true));
+        assert!(!visibility.update(start + Duration::from_secs(5), HIDE_AFTER, true));
+        assert!(!visibility.is_hidden());
+
+        // The inactivity timer starts over once the drag ends
+        assert!(!visibility.update(start + Duration::from_secs(6), HIDE_AFTER, false));
+        assert!(visibility.update(start + Duration::from_secs(7), HIDE_AFTER, false));
+    }
+}
@@ -10,9 +10,18 @@ pub struct WindowSettings {
     pub iso_layout: bool,
     pub remember_window_size: bool,
     pub remember_window_position: bool,
+    pub remember_working_directory: bool,
+    pub remember_zoom: bool,
+    pub remember_fullscreen: bool,
+    pub remember_monitor: bool,
     pub hide_mouse_when_typing: bool,
+    pub hide_mouse_after_idle: f32,
+    pub multi_click_interval: f32,
+    pub multi_click_distance: f32,
+    pub scroll_speed_multiplier: f32,
     pub touch_deadzone: f32,
     pub touch_drag_timeout: f32,
+    pub touch_long_press_duration: f32,
     pub background_color: String,
     pub confirm_quit: bool,
 }
@@ -28,9 +37,18 @@ impl Default for WindowSettings {
             no_idle: SETTINGS.get::<CmdLineSettings>().no_idle,
             remember_window_size: true,
             remember_window_position: true,
+            remember_working_directory: false,
+            remember_zoom: false,
+            remember_fullscreen: false,
+            remember_monitor: false,
             hide_mouse_when_typing: false,
+            hide_mouse_after_idle: 0.0,
+            multi_click_interval: 0.5,
+            multi_click_distance: 4.0,
+            scroll_speed_multiplier: 1.0,
             touch_deadzone: 6.0,
             touch_drag_timeout: 0.17,
+            touch_long_press_duration: 0.5,
             background_color: "".to_string(),
             confirm_quit: true,
         }
@@ -0,0 +1,465 @@
+use std::time::{Duration, Instant};
+
+use glutin::{dpi::PhysicalPosition, event::TouchPhase};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// Two fingers moved together. The delta follows winit's scroll convention: positive values
+    /// move the content right and down.
+    Pan {
+        center: PhysicalPosition<f32>,
+        delta: (f32, f32),
+    },
+    /// Two fingers moved apart or together. The scale is relative to the previous event.
+    Pinch {
+        center: PhysicalPosition<f32>,
+        scale: f32,
+    },
+    /// A single finger stayed in place for the long press duration.
+    LongPress { position: PhysicalPosition<f32> },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GestureSettings {
+    /// How far fingers have to move before a movement is recognized, in pixels
+    pub deadzone: f32,
+    pub long_press_duration: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MultiTouchMode {
+    Undecided,
+    Pan,
+    Pinch,
+}
+
+#[derive(Debug)]
+struct MultiTouch {
+    mode: MultiTouchMode,
+    last_center: PhysicalPosition<f32>,
+    last_distance: f32,
+    travelled: f32,
+    stretched: f32,
+}
+
+#[derive(Debug)]
+struct LongPressCandidate<F> {
+    finger: F,
+    start: PhysicalPosition<f32>,
+    start_time: Instant,
+    recognized: bool,
+}
+
+/// Recognizes gestures spanning more than a single touch event. Single finger taps and drags are
+/// left to the mouse manager, this only tracks multi touch gestures and long presses. It doesn't
+/// depend on a window, so it can be driven by synthetic touch sequences.
+pub struct GestureRecognizer<F> {
+    fingers: Vec<(F, PhysicalPosition<f32>)>,
+    multi_touch: Option<MultiTouch>,
+    long_press: Option<LongPressCandidate<F>>,
+}
+
+fn distance(a: PhysicalPosition<f32>, b: PhysicalPosition<f32>) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+impl<F: Copy + PartialEq> GestureRecognizer<F> {
+    pub fn new() -> GestureRecognizer<F> {
+        GestureRecognizer {
+            fingers: Vec::new(),
+            multi_touch: None,
+            long_press: None,
+        }
+    }
+
+    /// Whether more than one finger is involved in the current touch interaction. This stays true
+    /// until every finger has been lifted, so that lifting fingers one by one isn't mistaken for
+    /// a tap.
+    pub fn is_multi_touch(&self) -> bool {
+        self.multi_touch.is_some()
+    }
+
+    pub fn handle_touch(
+        &mut self,
+        finger: F,
+        location: PhysicalPosition<f32>,
+        phase: TouchPhase,
+        now: Instant,
+        settings: &GestureSettings,
+    ) -> Option<Gesture> {
+        match phase {
+            TouchPhase::Started => {
+                self.fingers.push((finger, location));
+
+                if self.fingers.len() > 1 {
+                    self.long_press = None;
+                    self.start_multi_touch();
+                } else if self.multi_touch.is_none() {
+                    self.long_press = Some(LongPressCandidate {
+                        finger,
+                        start: location,
+                        start_time: now,
+                        recognized: false,
+                    });
+                }
+
+                None
+            }
+            TouchPhase::Moved => {
+                if let Some((_, position)) = self.fingers.iter_mut().find(|(f, _)| *f == finger) {
+                    *position = location;
+                }
+
+                if let Some(candidate) = &self.long_press {
+                    if candidate.finger == finger
+                        && distance(candidate.start, location) > settings.deadzone
+                    {
+                        self.long_press = None;
+                    }
+                }
+
+                self.update_multi_touch(settings)
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.fingers.retain(|(f, _)| *f != finger);
+
+                if matches!(&self.long_press, Some(candidate) if candidate.finger == finger) {
+                    self.long_press = None;
+                }
+
+                if self.fingers.is_empty() {
+                    self.multi_touch = None;
+                } else if self.fingers.len() > 1 {
+                    // The pair of fingers changed, so measure from the new pair from now on
+                    self.start_multi_touch();
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Advances timers. Needs to be called regularly since a long press happens without any touch
+    /// events.
+    pub fn update(&mut self, now: Instant, settings: &GestureSettings) -> Option<Gesture> {
+        let long_press_duration = settings.long_press_duration?;
+        let candidate = self.long_press.as_mut()?;
+
+        if !candidate.recognized
+            && now.saturating_duration_since(candidate.start_time) >= long_press_duration
+        {
+            candidate.recognized = true;
+            Some(Gesture::LongPress {
+                position: candidate.start,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn finger_pair(&self) -> Option<(PhysicalPosition<f32>, f32)> {
+        if let [(_, first), (_, second), ..] = self.fingers[..] {
+            let center =
+                PhysicalPosition::new((first.x + second.x) / 2.0, (first.y + second.y) / 2.0);
+            Some((center, distance(first, second)))
+        } else {
+            None
+        }
+    }
+
+    fn start_multi_touch(&mut self) {
+        if let Some((center, distance)) = self.finger_pair() {
+            let mode = self
+                .multi_touch
+                .as_ref()
+                .map(|multi_touch| multi_touch.mode)
+                .unwrap_or(MultiTouchMode::Undecided);
+
+            self.multi_touch = Some(MultiTouch {
+                mode,
+                last_center: center,
+                last_distance: distance,
+                travelled: 0.0,
+                stretched: 0.0,
+            });
+        }
+    }
+
+    fn update_multi_touch(&mut self, settings: &GestureSettings) -> Option<Gesture> {
+        let (center, distance) = self.finger_pair()?;
+        let multi_touch = self.multi_touch.as_mut()?;
+
+        let delta = (
+            center.x - multi_touch.last_center.x,
+            center.y - multi_touch.last_center.y,
+        );
+        let scale = if multi_touch.last_distance > 0.0 {
+            distance / multi_touch.last_distance
+        } else {
+            1.0
+        };
+
+        if multi_touch.mode == MultiTouchMode::Undecided {
+            // Decide once on what the fingers are doing, so that a pan doesn't zoom slightly and
+            // the other way around
+            multi_touch.travelled += (delta.0.powi(2) + delta.1.powi(2)).sqrt();
+            multi_touch.stretched += (distance - multi_touch.last_distance).abs();
+
+            if multi_touch.stretched > settings.deadzone
+                && multi_touch.stretched > multi_touch.travelled
+            {
+                multi_touch.mode = MultiTouchMode::Pinch;
+            } else if multi_touch.travelled > settings.deadzone {
+                multi_touch.mode = MultiTouchMode::Pan;
+            }
+        }
+
+        multi_touch.last_center = center;
+        multi_touch.last_distance = distance;
+
+        match multi_touch.mode {
+            MultiTouchMode::Undecided => None,
+            MultiTouchMode::Pan => Some(Gesture::Pan { center, delta }),
+            MultiTouchMode::Pinch => Some(Gesture::Pinch { center, scale }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: GestureSettings = GestureSettings {
+        deadzone: 6.0,
+        long_press_duration: Some(Duration::from_millis(500)),
+    };
+
+    fn position(x: f32, y: f32) -> PhysicalPosition<f32> {
+        PhysicalPosition::new(x, y)
+    }
+
+    // Feeds a sequence of touch events and collects the recognized gestures
+    fn run(
+        recognizer: &mut GestureRecognizer<u64>,
+        start: Instant,
+        events: &[(u64, f32, f32, TouchPhase)],
+    ) -> Vec<Gesture> {
+        events
+            .iter()
+            .filter_map(|(finger, x, y, phase)| {
+                recognizer.handle_touch(*finger, position(*x, *y), *phase, start, &SETTINGS)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_two_finger_pan() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        let gestures = run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (2, 200.0, 100.0, TouchPhase::Started),
+                (1, 100.0, 110.0, TouchPhase::Moved),
+                (2, 200.0, 110.0, TouchPhase::Moved),
+                (1, 100.0, 120.0, TouchPhase::Moved),
+            ],
+        );
+
+        assert!(recognizer.is_multi_touch());
+        assert_eq!(
+            gestures,
+            vec![
+                Gesture::Pan {
+                    center: position(150.0, 110.0),
+                    delta: (0.0, 5.0),
+                },
+                Gesture::Pan {
+                    center: position(150.0, 115.0),
+                    delta: (0.0, 5.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pinch() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        let gestures = run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (2, 200.0, 100.0, TouchPhase::Started),
+                (2, 210.0, 100.0, TouchPhase::Moved),
+                (1, 90.0, 100.0, TouchPhase::Moved),
+            ],
+        );
+
+        // The first move only stretched the fingers by 10 pixels while moving the center by 5,
+        // which already decides on a pinch
+        assert_eq!(
+            gestures,
+            vec![
+                Gesture::Pinch {
+                    center: position(155.0, 100.0),
+                    scale: 1.1,
+                },
+                Gesture::Pinch {
+                    center: position(150.0, 100.0),
+                    scale: 120.0 / 110.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_small_movements_are_ignored() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        let gestures = run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (2, 200.0, 100.0, TouchPhase::Started),
+                (1, 102.0, 100.0, TouchPhase::Moved),
+                (2, 202.0, 100.0, TouchPhase::Moved),
+            ],
+        );
+
+        assert!(gestures.is_empty());
+    }
+
+    #[test]
+    fn test_multi_touch_lasts_until_all_fingers_are_lifted() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (2, 200.0, 100.0, TouchPhase::Started),
+                (1, 100.0, 100.0, TouchPhase::Ended),
+            ],
+        );
+        assert!(recognizer.is_multi_touch());
+
+        // Moving the remaining finger doesn't produce anything
+        assert!(run(
+            &mut recognizer,
+            start,
+            &[(2, 300.0, 300.0, TouchPhase::Moved)]
+        )
+        .is_empty());
+
+        run(
+            &mut recognizer,
+            start,
+            &[(2, 300.0, 300.0, TouchPhase::Ended)],
+        );
+        assert!(!recognizer.is_multi_touch());
+    }
+
+    #[test]
+    fn test_long_press() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (1, 103.0, 100.0, TouchPhase::Moved),
+            ],
+        );
+
+        assert_eq!(
+            recognizer.update(start + Duration::from_millis(400), &SETTINGS),
+            None
+        );
+        assert_eq!(
+            recognizer.update(start + Duration::from_millis(500), &SETTINGS),
+            Some(Gesture::LongPress {
+                position: position(100.0, 100.0)
+            })
+        );
+        // Only recognized once
+        assert_eq!(
+            recognizer.update(start + Duration::from_millis(600), &SETTINGS),
+            None
+        );
+    }
+
+    #[test]
+    fn test_long_press_cancelled_by_movement() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (1, 120.0, 100.0, TouchPhase::Moved),
+            ],
+        );
+
+        assert_eq!(
+            recognizer.update(start + Duration::from_secs(1), &SETTINGS),
+            None
+        );
+    }
+
+    #[test]
+    fn test_long_press_cancelled_by_second_finger() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+
+        run(
+            &mut recognizer,
+            start,
+            &[
+                (1, 100.0, 100.0, TouchPhase::Started),
+                (2, 200.0, 100.0, TouchPhase::Started),
+            ],
+        );
+
+        assert_eq!(
+            recognizer.update(start + Duration::from_secs(1), &SETTINGS),
+            None
+        );
+    }
+
+    #[test]
+    fn test_long_press_disabled() {
+        let mut recognizer = GestureRecognizer::new();
+        let start = Instant::now();
+        let settings = GestureSettings {
+            long_press_duration: None,
+            ..SETTINGS
+        };
+
+        recognizer.handle_touch(
+            1,
+            position(100.0, 100.0),
+            TouchPhase::Started,
+            start,
+            &settings,
+        );
+
+        assert_eq!(
+            recognizer.update(start + Duration::from_secs(10), &settings),
+            None
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use glutin::{platform::unix::WindowExtUnix, window::Window};
+use skia_safe::Color;
+use x11_dl::xlib::Xlib;
+
+lazy_static! {
+    static ref XLIB: Option<Xlib> = match Xlib::open() {
+        Ok(xlib) => Some(xlib),
+        Err(error) => {
+            log::warn!(
+                "Could not load xlib to set the window background: {}",
+                error
+            );
+            None
+        }
+    };
+}
+
+/// Sets the color the X server fills the window with before anything is drawn into it, which is
+/// what shows up while the window is mapped or resized. Wayland has no such server side background,
+/// there the window stays empty until the first frame, so this does nothing.
+pub fn set_x11_background(window: &Window, color: Color) {
+    let (display, x11_window) = match (window.xlib_display(), window.xlib_window()) {
+        (Some(display), Some(x11_window)) => (display, x11_window),
+        _ => return,
+    };
+
+    let xlib = match XLIB.as_ref() {
+        Some(xlib) => xlib,
+        None => return,
+    };
+
+    // Opaque alpha for visuals with an alpha channel, ignored by the others
+    let pixel = 0xff00_0000 | (color.r() as u64) << 16 | (color.g() as u64) << 8 | color.b() as u64;
+
+    unsafe {
+        let display = display as *mut _;
+        (xlib.XSetWindowBackground)(display, x11_window, pixel as _);
+        (xlib.XFlush)(display);
+    }
+}